@@ -11,6 +11,7 @@
 use rustc_hir as hir;
 use rustc_hir::def::Res;
 use rustc_hir::definitions::DefPathData;
+use rustc_session::parse::feature_err;
 use rustc_span::hygiene::ExpnId;
 use rustc_span::source_map::{respan, DesugaringKind, Span, Spanned};
 use rustc_span::symbol::{sym, Ident};
@@ -205,14 +206,18 @@ pub(super) fn lower_expr_mut(&mut self, e: &Expr) -> hir::Expr<'hir> {
                 ExprKind::Range(ref e1, ref e2, lims) => {
                     self.lower_expr_range(e.span, e1.as_deref(), e2.as_deref(), lims)
                 }
+                ExprKind::Underscore if self.sess.features_untracked().typed_holes => {
+                    hir::ExprKind::Hole
+                }
                 ExprKind::Underscore => {
-                    self.sess
-                        .struct_span_err(
-                            e.span,
-                            "in expressions, `_` can only be used on the left-hand side of an assignment",
-                        )
-                        .span_label(e.span, "`_` not allowed here")
-                        .emit();
+                    feature_err(
+                        &self.sess.parse_sess,
+                        sym::typed_holes,
+                        e.span,
+                        "in expressions, `_` can only be used on the left-hand side of an assignment",
+                    )
+                    .span_label(e.span, "`_` not allowed here")
+                    .emit();
                     hir::ExprKind::Err
                 }
                 ExprKind::Path(ref qself, ref path) => {
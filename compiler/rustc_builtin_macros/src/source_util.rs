@@ -9,7 +9,7 @@
 use rustc_parse::{self, new_parser_from_file};
 use rustc_session::lint::builtin::INCOMPLETE_INCLUDE;
 use rustc_span::symbol::Symbol;
-use rustc_span::{self, Pos, Span};
+use rustc_span::{self, Pos, SourceFileHash, Span};
 
 use smallvec::SmallVec;
 use std::rc::Rc;
@@ -216,7 +216,14 @@ pub fn expand_include_bytes(
         }
     };
     match cx.source_map().load_binary_file(&file) {
-        Ok(bytes) => base::MacEager::expr(cx.expr_lit(sp, ast::LitKind::ByteStr(bytes.into()))),
+        Ok(bytes) => {
+            // The contents of the file are hashed here, as the file isn't valid UTF-8
+            // in general and so its `SourceFile` might not have them.
+            let hash = SourceFileHash::new(cx.source_map().hash_kind(), &bytes);
+            let path = Symbol::intern(&file.to_string_lossy());
+            cx.sess.parse_sess.binary_depinfo.borrow_mut().insert(path, hash);
+            base::MacEager::expr(cx.expr_lit(sp, ast::LitKind::ByteStr(bytes.into())))
+        }
         Err(e) => {
             cx.span_err(sp, &format!("couldn't read {}: {}", file.display(), e));
             DummyResult::any(sp)
@@ -133,6 +133,10 @@ pub fn expand_test_or_bench(
         return vec![Annotatable::Item(item)];
     }
 
+    // The location of the test function's name, which libtest reports to tools.
+    let (source_file, start_line, start_col, end_line, end_col) =
+        span_location(cx, item.ident.span);
+
     let (sp, attr_sp) = (cx.with_def_site_ctxt(item.span), cx.with_def_site_ctxt(attr_sp));
 
     let test_id = Ident::new(sym::test, attr_sp);
@@ -164,6 +168,21 @@ pub fn expand_test_or_bench(
         )
     };
 
+    // creates test::TestLocation::new
+    let test_location_new_path = cx.path(
+        sp,
+        vec![
+            test_id,
+            Ident::from_str_and_span("TestLocation", sp),
+            Ident::from_str_and_span("new", sp),
+        ],
+    );
+
+    // creates test::static_message("...")
+    let static_message = |msg| {
+        cx.expr_call(sp, cx.expr_path(test_path("static_message")), vec![cx.expr_str(sp, msg)])
+    };
+
     // creates $name: $expr
     let field = |name, expr| cx.field_imm(sp, Ident::from_str_and_span(name, sp), expr);
 
@@ -261,16 +280,33 @@ pub fn expand_test_or_bench(
                                             )],
                                         ),
                                     ),
+                                    // location: test::TestLocation::new(<source_file>,
+                                    //     <start_line>, <start_col>, <end_line>, <end_col>)
+                                    // of the test fn identifier.
+                                    field(
+                                        "location",
+                                        cx.expr_call(
+                                            sp,
+                                            cx.expr_path(test_location_new_path),
+                                            vec![
+                                                cx.expr_str(sp, source_file),
+                                                cx.expr_usize(sp, start_line),
+                                                cx.expr_usize(sp, start_col),
+                                                cx.expr_usize(sp, end_line),
+                                                cx.expr_usize(sp, end_col),
+                                            ],
+                                        ),
+                                    ),
                                     // ignore: true | false
                                     field(
                                         "ignore",
                                         cx.expr_bool(sp, should_ignore(&cx.sess, &item)),
                                     ),
-                                    // ignore_message: Some("...") | None
+                                    // ignore_message: Some(test::static_message("...")) | None
                                     field(
                                         "ignore_message",
                                         if let Some(msg) = should_ignore_message(cx, &item) {
-                                            cx.expr_some(sp, cx.expr_str(sp, msg))
+                                            cx.expr_some(sp, static_message(msg))
                                         } else {
                                             cx.expr_none(sp)
                                         },
@@ -291,11 +327,12 @@ pub fn expand_test_or_bench(
                                             ShouldPanic::Yes(None) => {
                                                 cx.expr_path(should_panic_path("Yes"))
                                             }
-                                            // test::ShouldPanic::YesWithMessage("...")
+                                            // test::ShouldPanic::YesWithMessage(
+                                            //     test::static_message("..."))
                                             ShouldPanic::Yes(Some(sym)) => cx.expr_call(
                                                 sp,
                                                 cx.expr_path(should_panic_path("YesWithMessage")),
-                                                vec![cx.expr_str(sp, sym)],
+                                                vec![static_message(sym)],
                                             ),
                                         },
                                     ),
@@ -317,6 +354,24 @@ pub fn expand_test_or_bench(
                                             }
                                         },
                                     ),
+                                    // timeout: Some(...) | None
+                                    field(
+                                        "timeout",
+                                        if let Some(secs) = test_timeout(cx, &item) {
+                                            cx.expr_some(
+                                                sp,
+                                                cx.expr_lit(
+                                                    sp,
+                                                    ast::LitKind::Int(
+                                                        secs as u128,
+                                                        ast::LitIntType::Unsigned(ast::UintTy::U64),
+                                                    ),
+                                                ),
+                                            )
+                                        } else {
+                                            cx.expr_none(sp)
+                                        },
+                                    ),
                                     // },
                                 ],
                             ),
@@ -359,6 +414,15 @@ pub fn expand_test_or_bench(
     }
 }
 
+/// Returns the file of `span` along with its one-based start and end line and column.
+fn span_location(cx: &ExtCtxt<'_>, span: Span) -> (Symbol, usize, usize, usize, usize) {
+    let source_map = cx.sess.source_map();
+    let lo = source_map.lookup_char_pos(span.lo());
+    let hi = source_map.lookup_char_pos(span.hi());
+    let source_file = Symbol::intern(&lo.file.name.prefer_remapped().to_string());
+    (source_file, lo.line, lo.col.0 + 1, hi.line, hi.col.0 + 1)
+}
+
 fn item_path(mod_path: &[Ident], item_ident: &Ident) -> String {
     mod_path
         .iter()
@@ -429,6 +493,24 @@ fn should_panic(cx: &ExtCtxt<'_>, i: &ast::Item) -> ShouldPanic {
     }
 }
 
+/// Parses the number of seconds out of `#[test_timeout = "seconds"]`.
+fn test_timeout(cx: &ExtCtxt<'_>, i: &ast::Item) -> Option<u64> {
+    let attr = cx.sess.find_by_name(&i.attrs, sym::test_timeout)?;
+    let value = attr.value_str()?;
+    match value.as_str().parse::<u64>() {
+        Ok(secs) if secs > 0 => Some(secs),
+        _ => {
+            cx.sess
+                .parse_sess
+                .span_diagnostic
+                .struct_span_err(attr.span, "malformed `test_timeout` attribute input")
+                .note("the timeout must be a positive number of seconds")
+                .emit();
+            None
+        }
+    }
+}
+
 enum TestType {
     UnitTest,
     IntegrationTest,
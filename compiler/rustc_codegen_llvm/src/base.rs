@@ -26,7 +26,7 @@
 use rustc_data_structures::small_c_str::SmallCStr;
 use rustc_middle::dep_graph;
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrs;
-use rustc_middle::mir::mono::{Linkage, Visibility};
+use rustc_middle::mir::mono::{Linkage, MonoItem, Visibility};
 use rustc_middle::ty::TyCtxt;
 use rustc_session::config::DebugInfo;
 use rustc_span::symbol::Symbol;
@@ -55,6 +55,26 @@ pub fn iter_globals(llmod: &llvm::Module) -> ValueIter<'_> {
     unsafe { ValueIter { cur: llvm::LLVMGetFirstGlobal(llmod), step: llvm::LLVMGetNextGlobal } }
 }
 
+/// Counts the instructions of the function defined for each instance of a
+/// codegen unit, for `-Z mono-bloat-report`.
+fn record_llvm_instructions<'tcx>(
+    cx: &CodegenCx<'_, 'tcx>,
+    mono_items: &[(MonoItem<'tcx>, (Linkage, Visibility))],
+) {
+    for &(mono_item, _) in mono_items {
+        let MonoItem::Fn(instance) = mono_item else { continue };
+        let Some(&llfn) = cx.instances.borrow().get(&instance) else { continue };
+        let mut count = 0;
+        let mut block = Some(unsafe { llvm::LLVMGetFirstBasicBlock(llfn) });
+        while let Some(bb) = block {
+            let first = unsafe { llvm::LLVMGetFirstInstruction(bb) };
+            count += ValueIter { cur: first, step: llvm::LLVMGetNextInstruction }.count();
+            block = unsafe { llvm::LLVMGetNextBasicBlock(bb) };
+        }
+        cx.tcx.sess.code_stats.record_llvm_instructions(cx.tcx.symbol_name(instance).name, count);
+    }
+}
+
 pub fn compile_codegen_unit(tcx: TyCtxt<'_>, cgu_name: Symbol) -> (ModuleCodegen<ModuleLlvm>, u64) {
     let start_time = Instant::now();
 
@@ -93,6 +113,10 @@ fn module_codegen(tcx: TyCtxt<'_>, cgu_name: Symbol) -> ModuleCodegen<ModuleLlvm
                 mono_item.define::<Builder<'_, '_, '_>>(&cx);
             }
 
+            if cx.sess().opts.debugging_opts.mono_bloat_report.is_some() {
+                record_llvm_instructions(&cx, &mono_items);
+            }
+
             // If this codegen unit contains the main function, also create the
             // wrapper here
             if let Some(entry) = maybe_create_entry_wrapper::<Builder<'_, '_, '_>>(&cx) {
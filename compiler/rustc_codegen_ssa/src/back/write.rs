@@ -8,6 +8,7 @@
 
 use crate::traits::*;
 use jobserver::{Acquired, Client};
+use rustc_data_structures::fingerprint::Fingerprint;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::memmap::Mmap;
 use rustc_data_structures::profiling::SelfProfilerRef;
@@ -19,7 +20,8 @@
 use rustc_fs_util::link_or_copy;
 use rustc_hir::def_id::{CrateNum, LOCAL_CRATE};
 use rustc_incremental::{
-    copy_cgu_workproduct_to_incr_comp_cache_dir, in_incr_comp_dir, in_incr_comp_dir_sess,
+    copy_cgu_workproduct_to_incr_comp_cache_dir, copy_cgu_workproduct_to_shared_cache,
+    in_incr_comp_dir, in_incr_comp_dir_sess,
 };
 use rustc_metadata::EncodedMetadata;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
@@ -478,6 +480,7 @@ pub fn start_async_codegen<B: ExtraBackendMethods>(
         shared_emitter_main,
         future: coordinator_thread,
         output_filenames: tcx.output_filenames(()).clone(),
+        shared_object_cache_keys: FxHashMap::default(),
     }
 }
 
@@ -587,7 +590,12 @@ fn produce_final_output_artifacts(
                 user_wants_objects = true;
                 copy_if_one_unit(OutputType::Object, true);
             }
-            OutputType::Mir | OutputType::Metadata | OutputType::Exe | OutputType::DepInfo => {}
+            OutputType::Mir
+            | OutputType::MirJson
+            | OutputType::Metadata
+            | OutputType::Exe
+            | OutputType::DepInfo
+            | OutputType::DepInfoJson => {}
         }
     }
 
@@ -1814,6 +1822,9 @@ pub struct OngoingCodegen<B: ExtraBackendMethods> {
     pub shared_emitter_main: SharedEmitterMain,
     pub future: thread::JoinHandle<Result<CompiledModules, ()>>,
     pub output_filenames: Arc<OutputFilenames>,
+    /// The keys in the shared object cache of the codegen units that are compiled
+    /// rather than reused, so that their object files can be added to it.
+    pub shared_object_cache_keys: FxHashMap<String, Fingerprint>,
 }
 
 impl<B: ExtraBackendMethods> OngoingCodegen<B> {
@@ -1839,6 +1850,11 @@ pub fn join(self, sess: &Session) -> (CodegenResults, FxHashMap<WorkProductId, W
 
         let work_products =
             copy_all_cgu_workproducts_to_incr_comp_cache_dir(sess, &compiled_modules);
+        for work_product in work_products.values() {
+            if let Some(&key) = self.shared_object_cache_keys.get(&work_product.cgu_name) {
+                copy_cgu_workproduct_to_shared_cache(sess, work_product, key);
+            }
+        }
         produce_final_output_artifacts(sess, &compiled_modules, &self.output_filenames);
 
         // FIXME: time_llvm_passes support - does this use a global context or
@@ -12,8 +12,10 @@
 use crate::{CachedModuleCodegen, CompiledModule, CrateInfo, MemFlags, ModuleCodegen, ModuleKind};
 
 use rustc_attr as attr;
+use rustc_data_structures::fingerprint::Fingerprint;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::profiling::{get_resident_set_size, print_time_passes_entry};
+use rustc_data_structures::stable_hasher::{HashStable, StableHasher};
 
 use rustc_data_structures::sync::par_iter;
 #[cfg(parallel_compiler)]
@@ -23,6 +25,7 @@
 use rustc_hir::lang_items::LangItem;
 use rustc_index::vec::Idx;
 use rustc_metadata::EncodedMetadata;
+use rustc_middle::dep_graph;
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrs;
 use rustc_middle::middle::exported_symbols;
 use rustc_middle::middle::lang_items;
@@ -33,12 +36,13 @@
 use rustc_session::cgu_reuse_tracker::CguReuse;
 use rustc_session::config::{self, CrateType, EntryFnType, OutputType};
 use rustc_session::Session;
-use rustc_span::symbol::sym;
+use rustc_span::symbol::{sym, Symbol};
 use rustc_span::{DebuggerVisualizerFile, DebuggerVisualizerType};
 use rustc_target::abi::{Align, VariantIdx};
 
 use std::collections::BTreeSet;
 use std::convert::TryFrom;
+use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 use std::time::{Duration, Instant};
 
@@ -582,7 +586,7 @@ pub fn codegen_crate<B: ExtraBackendMethods>(
         metadata_module,
         codegen_units.len(),
     );
-    let ongoing_codegen = AbortCodegenOnDrop::<B>(Some(ongoing_codegen));
+    let mut ongoing_codegen = AbortCodegenOnDrop::<B>(Some(ongoing_codegen));
 
     // Codegen an allocator shim, if necessary.
     //
@@ -638,6 +642,47 @@ pub fn codegen_crate<B: ExtraBackendMethods>(
         codegen_units.iter().map(|cgu| determine_cgu_reuse(tcx, &cgu)).collect::<Vec<_>>()
     });
 
+    // Look up the codegen units that can't be reused from the previous session
+    // in the shared object cache. Those that aren't found there will be added
+    // to it once they are compiled.
+    let mut shared_object_cache_hits = FxHashMap::default();
+    if tcx.sess.opts.debugging_opts.shared_object_cache.is_some() {
+        tcx.sess.time("find_shared_object_cache_hits", || {
+            for (i, cgu) in codegen_units.iter().enumerate() {
+                if cgu_reuse[i] != CguReuse::No {
+                    continue;
+                }
+                let Some(key) = shared_object_cache_key(tcx, cgu) else {
+                    continue;
+                };
+                let cgu_name = cgu.name().as_str();
+                match rustc_incremental::copy_cgu_workproduct_from_shared_cache(
+                    tcx.sess, cgu_name, key,
+                ) {
+                    Some(work_product) => {
+                        // Record the dep-node that compiling the codegen unit would
+                        // have, so that the next session can mark it as green and
+                        // reuse the object file from its own incremental directory.
+                        // Its dependencies are those of the key, which covers all
+                        // that the object file depends on. The work product itself
+                        // is saved along with those of the other codegen units.
+                        tcx.dep_graph.with_task(
+                            cgu.codegen_dep_node(tcx),
+                            tcx,
+                            cgu.name(),
+                            shared_object_cache_key_of_cgu,
+                            Some(dep_graph::hash_result),
+                        );
+                        shared_object_cache_hits.insert(i, work_product);
+                    }
+                    None => {
+                        ongoing_codegen.shared_object_cache_keys.insert(cgu_name.to_string(), key);
+                    }
+                }
+            }
+        });
+    }
+
     let mut total_codegen_time = Duration::new(0, 0);
     let start_rss = tcx.sess.time_passes().then(|| get_resident_set_size());
 
@@ -657,7 +702,9 @@ pub fn codegen_crate<B: ExtraBackendMethods>(
             let cgus: Vec<_> = cgu_reuse
                 .iter()
                 .enumerate()
-                .filter(|&(_, reuse)| reuse == &CguReuse::No)
+                .filter(|&(i, reuse)| {
+                    reuse == &CguReuse::No && !shared_object_cache_hits.contains_key(&i)
+                })
                 .take(tcx.sess.threads())
                 .collect();
 
@@ -683,6 +730,18 @@ pub fn codegen_crate<B: ExtraBackendMethods>(
         ongoing_codegen.wait_for_signal_to_codegen_item();
         ongoing_codegen.check_for_errors(tcx.sess);
 
+        if let Some(work_product) = shared_object_cache_hits.remove(&i) {
+            // The object file is reused as if it had been compiled by the
+            // previous session.
+            tcx.sess.cgu_reuse_tracker.set_actual_reuse(cgu.name().as_str(), CguReuse::PostLto);
+            submit_post_lto_module_to_llvm(
+                &backend,
+                &ongoing_codegen.coordinator_send,
+                CachedModuleCodegen { name: cgu.name().to_string(), source: work_product },
+            );
+            continue;
+        }
+
         let cgu_reuse = cgu_reuse[i];
         tcx.sess.cgu_reuse_tracker.set_actual_reuse(cgu.name().as_str(), cgu_reuse);
 
@@ -735,6 +794,19 @@ pub fn codegen_crate<B: ExtraBackendMethods>(
         };
     }
 
+    // The codegen units that were actually recompiled are only known once they
+    // have all been submitted.
+    if tcx.sess.opts.debugging_opts.incremental_explain.is_some() {
+        tcx.sess.time("explain_cgu_recompilation", || {
+            for cgu in &codegen_units {
+                let reuse = tcx.sess.cgu_reuse_tracker.actual_reuse(cgu.name().as_str());
+                if reuse == Some(CguReuse::No) {
+                    rustc_incremental::explain_cgu_recompilation(tcx, cgu);
+                }
+            }
+        });
+    }
+
     ongoing_codegen.codegen_finished(tcx);
 
     // Since the main thread is sometimes blocked during codegen, we keep track
@@ -966,6 +1038,55 @@ pub fn provide(providers: &mut Providers) {
     };
 }
 
+/// Returns the key of a codegen unit in the shared object cache given to
+/// `-Z shared-object-cache`, or `None` if its object file can't be shared.
+///
+/// The key fingerprints everything the object file depends on: the compiler,
+/// the crate hash, which covers the source of the crate, the hashes of its
+/// dependencies and the options that affect compilation, and the items of the
+/// codegen unit with their linkage. The crate hash also covers the remapped
+/// paths of the source files and of the working directory, which end up in the
+/// object files, so checkouts in different directories only share object files
+/// if `--remap-path-prefix` maps their paths to the same ones.
+fn shared_object_cache_key<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cgu: &CodegenUnit<'tcx>,
+) -> Option<Fingerprint> {
+    let sess = tcx.sess;
+    if !tcx.dep_graph.is_fully_enabled() {
+        return None;
+    }
+    // Only object files are shared: neither LTO nor the other outputs of the
+    // backend can be produced from them.
+    let lto_type =
+        compute_per_cgu_lto_type(&sess.lto(), &sess.opts, &sess.crate_types(), ModuleKind::Regular);
+    if !matches!(lto_type, ComputedLtoType::No)
+        || sess.opts.output_types.contains_key(&OutputType::Bitcode)
+        || sess.opts.output_types.contains_key(&OutputType::Assembly)
+        || sess.opts.output_types.contains_key(&OutputType::LlvmAssembly)
+    {
+        return None;
+    }
+
+    let mut hcx = tcx.create_stable_hashing_context();
+    let mut hasher = StableHasher::new();
+    option_env!("CFG_VERSION").hash(&mut hasher);
+    tcx.crate_hash(LOCAL_CRATE).hash_stable(&mut hcx, &mut hasher);
+    cgu.name().as_str().hash(&mut hasher);
+    for (item, (linkage, visibility)) in cgu.items_in_deterministic_order(tcx) {
+        item.symbol_name(tcx).name.hash(&mut hasher);
+        linkage.hash_stable(&mut hcx, &mut hasher);
+        visibility.hash_stable(&mut hcx, &mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+/// The `shared_object_cache_key` of the codegen unit named `cgu_name`, as the
+/// task of its `CompileCodegenUnit` dep-node when it is found in the cache.
+fn shared_object_cache_key_of_cgu(tcx: TyCtxt<'_>, cgu_name: Symbol) -> Option<Fingerprint> {
+    shared_object_cache_key(tcx, tcx.codegen_unit(cgu_name))
+}
+
 fn determine_cgu_reuse<'tcx>(tcx: TyCtxt<'tcx>, cgu: &CodegenUnit<'tcx>) -> CguReuse {
     if !tcx.dep_graph.is_fully_enabled() {
         return CguReuse::No;
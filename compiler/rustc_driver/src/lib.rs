@@ -8,6 +8,7 @@
 #![feature(nll)]
 #![feature(let_else)]
 #![feature(once_cell)]
+#![feature(peer_credentials_unix_socket)]
 #![recursion_limit = "256"]
 #![allow(rustc::potential_query_instability)]
 
@@ -32,7 +33,7 @@
 use rustc_save_analysis::DumpHandler;
 use rustc_serialize::json::ToJson;
 use rustc_session::config::{nightly_options, CG_OPTIONS, DB_OPTIONS};
-use rustc_session::config::{ErrorOutputType, Input, OutputType, PrintRequest, TrimmedDefPaths};
+use rustc_session::config::{ErrorOutputType, Input, PrintRequest, TrimmedDefPaths};
 use rustc_session::cstore::MetadataLoader;
 use rustc_session::getopts;
 use rustc_session::lint::{Lint, LintId};
@@ -57,6 +58,8 @@
 
 pub mod args;
 pub mod pretty;
+#[cfg(unix)]
+mod server;
 
 /// Exit status code used for successful compilation and help output.
 pub const EXIT_SUCCESS: i32 = 0;
@@ -367,9 +370,7 @@ fn run_compiler(
 
             queries.prepare_outputs()?;
 
-            if sess.opts.output_types.contains_key(&OutputType::DepInfo)
-                && sess.opts.output_types.len() == 1
-            {
+            if sess.opts.output_types.only_dep_info() {
                 return early_exit();
             }
 
@@ -409,6 +410,10 @@ fn run_compiler(
                 sess.code_stats.print_type_sizes();
             }
 
+            if let Some(format) = sess.opts.debugging_opts.mono_bloat_report {
+                sess.code_stats.print_mono_bloat_report(format);
+            }
+
             let linker = queries.linker()?;
             Ok(Some(linker))
         })?;
@@ -1333,6 +1338,21 @@ pub fn main() -> ! {
                 })
             })
             .collect::<Vec<_>>();
+        // The compile server and its clients are only supported on Unix, elsewhere
+        // `--server` is an unknown flag and `RUSTC_SERVER` is ignored.
+        #[cfg(unix)]
+        {
+            if args.get(1).map(String::as_str) == Some("--server") {
+                server::serve(&args);
+            }
+            if let Some(socket) = env::var_os("RUSTC_SERVER") {
+                if let Some(exit_code) = server::forward(&socket, &args) {
+                    process::exit(exit_code);
+                }
+            }
+        }
+        // One SARIF log for the handlers that check the options and the one of the session.
+        let _sarif_log = rustc_errors::sarif::defer_session_log();
         RunCompiler::new(&args, &mut callbacks).run()
     });
 
@@ -0,0 +1,528 @@
+//! The compile server (`rustc --server <socket>`) and its client.
+//!
+//! The server compiles the crates of the clients that connect to its Unix socket, one at
+//! a time, in a single process. The raw metadata of the crates they load and the target
+//! specifications are kept between these compilations instead of being loaded again by
+//! each of them, and LLVM is only initialized once.
+//!
+//! Neither the decoded metadata nor the LLVM context are kept: each compilation decodes
+//! the metadata it uses into its own `CrateMetadata`, which refers to the crate numbers
+//! and interners of its session, and creates its own LLVM context, which the modules of
+//! its codegen units are tied to.
+//!
+//! `rustc` is a client when `RUSTC_SERVER` is set to the path of the socket: it forwards
+//! its arguments, environment and working directory to the server, which streams the
+//! standard output and error of the compilation back, followed by its exit code. The
+//! client compiles by itself when there is no server, when it is busy, and when its
+//! toolchain or process-wide options (see `PROCESS_OPTIONS`) differ from those of the
+//! server.
+//!
+//! Only processes of the user running the server can use it: the socket is created in
+//! a directory that only this user can access, and both ends check the user of the
+//! other one.
+
+use crate::{args, catch_with_exit_code, install_ice_hook, RunCompiler, TimePassesCallbacks};
+use rustc_data_structures::jobserver;
+use rustc_data_structures::profiling::{get_resident_set_size, print_time_passes_entry};
+use rustc_interface::util;
+use rustc_log::stderr_isatty;
+use rustc_metadata::locator;
+use rustc_session::config::{self, ErrorOutputType};
+use rustc_session::{early_error, getopts};
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The options that are set once per process, by the first compilation: those of LLVM,
+/// which is only initialized once, and the codegen backend, which is only loaded once.
+/// The target and the sysroot are also part of them.
+const PROCESS_OPTIONS: &[&str] = &[
+    "codegen-backend",
+    "llvm-args",
+    "llvm-plugins",
+    "llvm-time-trace",
+    "merge-functions",
+    "new-llvm-pass-manager",
+    "no-generate-arange-section",
+    "print-llvm-passes",
+    "time-llvm-passes",
+];
+
+/// The variables of the environment of the clients that aren't used by the server: they
+/// refer to the jobserver of the client, while the server uses its own.
+const CLIENT_ONLY_VARIABLES: &[&str] = &["CARGO_MAKEFLAGS", "MAKEFLAGS", "MFLAGS"];
+
+/// The exit code of a compilation that panicked, as that of the process would be.
+const PANIC_EXIT_CODE: i32 = 101;
+
+const REJECTED: u8 = 0;
+const ACCEPTED: u8 = 1;
+
+/// The tags of the frames the server sends once it accepted a compilation, see `Frame`.
+const STDOUT: u8 = 1;
+const STDERR: u8 = 2;
+const EXIT: u8 = 3;
+
+/// A compilation, as forwarded by a client.
+struct Request {
+    /// See `toolchain`.
+    toolchain: String,
+    /// The process-wide options of the compilation, see `PROCESS_OPTIONS`.
+    process_options: Vec<String>,
+    cwd: PathBuf,
+    args: Vec<String>,
+    env: Vec<(OsString, OsString)>,
+}
+
+impl Request {
+    fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        write_bytes(w, self.toolchain.as_bytes())?;
+        write_strings(w, &self.process_options)?;
+        write_bytes(w, self.cwd.as_os_str().as_bytes())?;
+        write_strings(w, &self.args)?;
+        w.write_all(&(self.env.len() as u32).to_le_bytes())?;
+        for (key, value) in &self.env {
+            write_bytes(w, key.as_bytes())?;
+            write_bytes(w, value.as_bytes())?;
+        }
+        w.flush()
+    }
+
+    fn read(r: &mut impl Read) -> io::Result<Request> {
+        let toolchain = read_string(r)?;
+        let process_options = read_strings(r)?;
+        let cwd = PathBuf::from(OsString::from_vec(read_bytes(r)?));
+        let args = read_strings(r)?;
+        let env = (0..read_u32(r)?)
+            .map(|_| {
+                let key = OsString::from_vec(read_bytes(r)?);
+                Ok((key, OsString::from_vec(read_bytes(r)?)))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Request { toolchain, process_options, cwd, args, env })
+    }
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    w.write_all(bytes)
+}
+
+fn write_strings(w: &mut impl Write, strings: &[String]) -> io::Result<()> {
+    w.write_all(&(strings.len() as u32).to_le_bytes())?;
+    strings.iter().try_for_each(|s| write_bytes(w, s.as_bytes()))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_u32(r)? as u64;
+    // Don't trust the length to allocate the buffer upfront.
+    let mut bytes = Vec::new();
+    r.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_string(r: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(r)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn read_strings(r: &mut impl Read) -> io::Result<Vec<String>> {
+    (0..read_u32(r)?).map(|_| read_string(r)).collect()
+}
+
+/// Whether the process at the other end of `stream` runs as the same user as this one.
+/// The peer can't be identified on the Unix systems not listed here, so no compilation
+/// is forwarded there.
+fn is_same_user(stream: &UnixStream) -> bool {
+    #[cfg(any(
+        target_os = "android",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "ios",
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    {
+        stream.peer_cred().map_or(false, |peer| peer.uid == unsafe { libc::geteuid() })
+    }
+    #[cfg(not(any(
+        target_os = "android",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "ios",
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )))]
+    {
+        let _ = stream;
+        false
+    }
+}
+
+/// Identifies the toolchain of this process: clients are only served by a server of the
+/// same toolchain, as they would load the same crates and codegen backend.
+fn toolchain() -> Option<String> {
+    let exe = env::current_exe().ok()?;
+    let modified = fs::metadata(&exe).ok()?.modified().ok()?;
+    let version = util::version_str().unwrap_or("unknown version");
+    Some(format!("{} {} {:?}", version, exe.display(), modified))
+}
+
+fn process_options(matches: &getopts::Matches) -> Vec<String> {
+    let mut options: Vec<_> = ["C", "Z"]
+        .into_iter()
+        .flat_map(|flag| {
+            matches
+                .opt_strs(flag)
+                .into_iter()
+                .filter(|option| {
+                    let name = option.split('=').next().unwrap().replace('_', "-");
+                    PROCESS_OPTIONS.contains(&name.as_str())
+                })
+                .map(move |option| format!("-{} {}", flag, option))
+        })
+        .collect();
+    options.extend(matches.opt_str("target").map(|target| format!("--target {}", target)));
+    options.extend(matches.opt_str("sysroot").map(|sysroot| format!("--sysroot {}", sysroot)));
+    options
+}
+
+/// Forwards the compilation of `at_args` to the compile server listening on `socket`, and
+/// returns its exit code. Returns `None` if the server didn't accept it, in which case
+/// nothing was output and the compilation has to be run by this process.
+pub(crate) fn forward(socket: &OsStr, at_args: &[String]) -> Option<i32> {
+    let mut options = getopts::Options::new();
+    for option in config::rustc_optgroups() {
+        (option.apply)(&mut options);
+    }
+    // Invalid arguments are reported by the local compilation.
+    let matches = options.parse(args::arg_expand_all(at_args).get(1..)?).ok()?;
+    // Invocations that don't compile anything, such as `rustc --version`, and those that
+    // read the input from the standard input aren't forwarded.
+    if !matches!(&matches.free[..], [input] if input != "-") {
+        return None;
+    }
+    // The logging of the server is set up once, from its own `RUSTC_LOG`, so the
+    // compilations that are to be logged are run by the client.
+    if env::var_os("RUSTC_LOG").is_some() {
+        return None;
+    }
+
+    let mut args = at_args.to_vec();
+    // The diagnostics are written to a pipe by the server.
+    if stderr_isatty() && !matches.opt_present("color") && !matches.opt_present("error-format") {
+        args.push("--color=always".to_string());
+    }
+    let request = Request {
+        toolchain: toolchain()?,
+        process_options: process_options(&matches),
+        cwd: env::current_dir().ok()?,
+        args,
+        env: env::vars_os().collect(),
+    };
+
+    let stream = UnixStream::connect(socket).ok()?;
+    if !is_same_user(&stream) {
+        return None;
+    }
+    request.write(&mut BufWriter::new(&stream)).ok()?;
+    let mut stream = BufReader::new(stream);
+    let mut accepted = [REJECTED];
+    stream.read_exact(&mut accepted).ok()?;
+    if accepted[0] != ACCEPTED {
+        return None;
+    }
+
+    let mut forwarded_output = false;
+    loop {
+        match read_frame(&mut stream) {
+            Ok(Frame::Exit(exit_code)) => return Some(exit_code),
+            Ok(Frame::Stdout(output)) => {
+                let _ = io::stdout().write_all(&output);
+            }
+            Ok(Frame::Stderr(output)) => {
+                let _ = io::stderr().write_all(&output);
+            }
+            // The compilation can be run again as long as none of its output was seen.
+            Err(_) if !forwarded_output => return None,
+            Err(err) => early_error(
+                ErrorOutputType::default(),
+                &format!("lost the connection to the compile server: {}", err),
+            ),
+        }
+        forwarded_output = true;
+    }
+}
+
+enum Frame {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    Exit(i32),
+}
+
+fn read_frame(r: &mut impl Read) -> io::Result<Frame> {
+    let mut tag = [0];
+    r.read_exact(&mut tag)?;
+    match tag[0] {
+        STDOUT => Ok(Frame::Stdout(read_bytes(r)?)),
+        STDERR => Ok(Frame::Stderr(read_bytes(r)?)),
+        EXIT => Ok(Frame::Exit(read_u32(r)? as i32)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown frame")),
+    }
+}
+
+/// Runs the compile server given by `rustc --server <socket>`, which never returns.
+pub(crate) fn serve(args: &[String]) -> ! {
+    if !rustc_feature::UnstableFeatures::from_environment(None).is_nightly_build() {
+        early_error(
+            ErrorOutputType::default(),
+            "the `--server` flag is only accepted on the nightly compiler",
+        );
+    }
+    let [_, _, socket] = args else {
+        early_error(
+            ErrorOutputType::default(),
+            "`--server` expects the path of a socket: `rustc --server <socket>`",
+        );
+    };
+    let toolchain = toolchain().unwrap_or_else(|| {
+        early_error(ErrorOutputType::default(), "failed to identify the toolchain of the server")
+    });
+
+    let listener = bind_private_socket(Path::new(socket)).unwrap_or_else(|err| {
+        early_error(
+            ErrorOutputType::default(),
+            &format!("failed to bind the compile server to `{}`: {}", socket, err),
+        )
+    });
+
+    // The jobserver of the clients can't be used by the server, which uses its own for all
+    // compilations.
+    jobserver::client();
+    locator::enable_metadata_cache();
+    config::enable_target_cache();
+
+    // A single compilation runs at a time, as they share the working directory, the
+    // environment and the standard streams of the process. The clients that connect in the
+    // meantime are rejected, and compile by themselves.
+    let (sender, receiver) = mpsc::sync_channel(0);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            if !is_same_user(&stream) {
+                let _ = stream.write_all(&[REJECTED]);
+                continue;
+            }
+            if let Err(TrySendError::Full(mut stream)) = sender.try_send(stream) {
+                let _ = stream.write_all(&[REJECTED]);
+            }
+        }
+    });
+
+    let mut process_options = None;
+    for stream in receiver {
+        serve_compilation(stream, &toolchain, &mut process_options);
+    }
+    unreachable!("the compile server stopped listening")
+}
+
+/// Binds a socket at `path` that only the user running the server can connect to: it is
+/// created with mode 0600, in a directory that only this user can access. The directory
+/// is created if it doesn't exist.
+fn bind_private_socket(path: &Path) -> io::Result<UnixListener> {
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => return Err(err),
+        _ => {}
+    }
+    let metadata = fs::metadata(dir)?;
+    if metadata.uid() != unsafe { libc::geteuid() } || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "`{}` must be a directory that only the user running the server can access",
+                dir.display()
+            ),
+        ));
+    }
+
+    // The socket of a previous server.
+    if fs::symlink_metadata(path).map_or(false, |metadata| metadata.file_type().is_socket()) {
+        let _ = fs::remove_file(path);
+    }
+    // The socket gets its mode from the umask when it is created, so that it is never
+    // accessible to other users, even briefly.
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    listener
+}
+
+/// Runs the compilation requested on `stream` if it can be served by this process, whose
+/// process-wide options are set by the first compilation it accepts.
+fn serve_compilation(
+    mut stream: UnixStream,
+    toolchain: &str,
+    process_options: &mut Option<Vec<String>>,
+) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+    let request = match Request::read(&mut BufReader::new(&stream)) {
+        Ok(request)
+            if request.toolchain == toolchain
+                && process_options.as_ref().map_or(true, |o| *o == request.process_options)
+                && env::set_current_dir(&request.cwd).is_ok() =>
+        {
+            request
+        }
+        _ => {
+            let _ = stream.write_all(&[REJECTED]);
+            return;
+        }
+    };
+
+    // Logged before the standard error is redirected to the client.
+    info!("compiling {:?} in `{}`", request.args, request.cwd.display());
+    let stream = Arc::new(Mutex::new(stream));
+    let redirections = Redirection::new(libc::STDOUT_FILENO, STDOUT, &stream)
+        .and_then(|stdout| Ok([stdout, Redirection::new(libc::STDERR_FILENO, STDERR, &stream)?]));
+    let Ok(redirections) = redirections else {
+        let _ = stream.lock().unwrap().write_all(&[REJECTED]);
+        return;
+    };
+    if stream.lock().unwrap().write_all(&[ACCEPTED]).is_err() {
+        return;
+    }
+    process_options.get_or_insert(request.process_options);
+
+    for (key, _) in env::vars_os() {
+        env::remove_var(key);
+    }
+    for (key, value) in request.env {
+        if !CLIENT_ONLY_VARIABLES.iter().any(|&variable| key == variable) {
+            env::set_var(key, value);
+        }
+    }
+    install_ice_hook();
+
+    let start_time = Instant::now();
+    let start_rss = get_resident_set_size();
+    let mut callbacks = TimePassesCallbacks::default();
+    let exit_code = catch_unwind(panic::AssertUnwindSafe(|| {
+        catch_with_exit_code(|| {
+            let _sarif_log = rustc_errors::sarif::defer_session_log();
+            RunCompiler::new(&request.args, &mut callbacks).run()
+        })
+    }))
+    .unwrap_or(PANIC_EXIT_CODE);
+    if callbacks.time_passes {
+        let end_rss = get_resident_set_size();
+        print_time_passes_entry("total", start_time.elapsed(), start_rss, end_rss);
+    }
+    let _ = io::stdout().flush();
+    drop(redirections);
+
+    let mut frame = vec![EXIT];
+    frame.extend(exit_code.to_le_bytes());
+    let _ = stream.lock().unwrap().write_all(&frame);
+}
+
+/// Redirects a standard stream of the process to the client while it exists.
+struct Redirection {
+    fd: RawFd,
+    saved_fd: RawFd,
+    forwarder: Option<JoinHandle<()>>,
+}
+
+impl Redirection {
+    fn new(fd: RawFd, tag: u8, stream: &Arc<Mutex<UnixStream>>) -> io::Result<Redirection> {
+        let mut pipe = [0; 2];
+        unsafe {
+            if libc::pipe(pipe.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let [read_fd, write_fd] = pipe;
+            let saved_fd = libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0);
+            if saved_fd < 0 || libc::dup2(write_fd, fd) < 0 {
+                let err = io::Error::last_os_error();
+                if saved_fd >= 0 {
+                    libc::close(saved_fd);
+                }
+                libc::close(read_fd);
+                libc::close(write_fd);
+                return Err(err);
+            }
+            libc::close(write_fd);
+            // Only the write end is inherited by the processes the compilation runs.
+            libc::fcntl(read_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+
+            let pipe = File::from_raw_fd(read_fd);
+            let stream = stream.clone();
+            let forwarder = thread::spawn(move || forward_pipe(pipe, tag, &stream));
+            Ok(Redirection { fd, saved_fd, forwarder: Some(forwarder) })
+        }
+    }
+}
+
+impl Drop for Redirection {
+    fn drop(&mut self) {
+        // Restoring the stream closes the write end of the pipe, so the forwarder stops once
+        // it forwarded the output written so far.
+        unsafe {
+            libc::dup2(self.saved_fd, self.fd);
+            libc::close(self.saved_fd);
+        }
+        if let Some(forwarder) = self.forwarder.take() {
+            let _ = forwarder.join();
+        }
+    }
+}
+
+fn forward_pipe(mut pipe: File, tag: u8, stream: &Mutex<UnixStream>) {
+    let mut buf = [0; 8192];
+    let mut connected = true;
+    loop {
+        let len = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        // Once the client is gone, the pipe is still drained so that the compilation
+        // doesn't block on it.
+        if connected {
+            let mut frame = vec![tag];
+            frame.extend((len as u32).to_le_bytes());
+            frame.extend(&buf[..len]);
+            connected = stream.lock().unwrap().write_all(&frame).is_ok();
+        }
+    }
+}
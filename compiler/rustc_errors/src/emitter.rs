@@ -10,7 +10,7 @@
 use Destination::*;
 
 use rustc_span::source_map::SourceMap;
-use rustc_span::{SourceFile, Span};
+use rustc_span::{FileName, SourceFile, Span};
 
 use crate::snippet::{Annotation, AnnotationType, Line, MultilineAnnotation, Style, StyledString};
 use crate::styled_buffer::StyledBuffer;
@@ -65,6 +65,7 @@ pub fn new_emitter(
         teach: bool,
         terminal_width: Option<usize>,
         macro_backtrace: bool,
+        hyperlinks: Option<Hyperlinks>,
     ) -> EmitterWriter {
         let (short, color_config) = self.unzip();
         let color = color_config.suggests_using_colors();
@@ -79,6 +80,8 @@ pub fn new_emitter(
             terminal_width,
             macro_backtrace,
         )
+        // Hyperlinks are escape sequences, which only belong in colored output.
+        .hyperlinks(hyperlinks.filter(|_| color))
     }
 }
 
@@ -696,6 +699,109 @@ fn suggests_using_colors(self) -> bool {
     }
 }
 
+/// Whether the file locations and error codes of human-readable diagnostics are
+/// wrapped in terminal hyperlinks (`-Z terminal-hyperlinks`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HyperlinkConfig {
+    Auto,
+    Always,
+    Never,
+}
+
+impl HyperlinkConfig {
+    fn enabled(self) -> bool {
+        match self {
+            HyperlinkConfig::Always => true,
+            HyperlinkConfig::Never => false,
+            HyperlinkConfig::Auto => {
+                atty::is(atty::Stream::Stderr) && terminal_supports_hyperlinks()
+            }
+        }
+    }
+}
+
+/// Detects the terminals known to support OSC 8 hyperlinks, as there is no
+/// standard way to query them.
+fn terminal_supports_hyperlinks() -> bool {
+    let var = |name| std::env::var(name).ok();
+    if ["WT_SESSION", "DOMTERM", "KONSOLE_VERSION"].iter().any(|name| var(name).is_some()) {
+        return true;
+    }
+    // VTE-based terminals, such as GNOME Terminal, since 0.50.
+    if var("VTE_VERSION")
+        .and_then(|version| version.parse::<u32>().ok())
+        .map_or(false, |version| version >= 5000)
+    {
+        return true;
+    }
+    matches!(
+        var("TERM_PROGRAM").as_deref(),
+        Some("iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty")
+    ) || matches!(var("TERM").as_deref(), Some("xterm-kitty" | "alacritty" | "foot"))
+}
+
+/// The URLs that file locations and error codes link to, as templates with
+/// `{path}`, `{line}` and `{col}`, or `{code}` placeholders.
+#[derive(Clone, Debug)]
+pub struct Hyperlinks {
+    file_url: String,
+    error_code_url: String,
+}
+
+impl Hyperlinks {
+    pub const DEFAULT_FILE_URL: &'static str = "file://{path}";
+    pub const DEFAULT_ERROR_CODE_URL: &'static str =
+        "https://doc.rust-lang.org/error-index.html#{code}";
+
+    /// Returns the hyperlinks to use, or `None` if they are disabled.
+    pub fn new(
+        config: HyperlinkConfig,
+        file_url: Option<String>,
+        error_code_url: Option<String>,
+    ) -> Option<Hyperlinks> {
+        config.enabled().then(|| Hyperlinks {
+            file_url: file_url.unwrap_or_else(|| Self::DEFAULT_FILE_URL.to_string()),
+            error_code_url: error_code_url
+                .unwrap_or_else(|| Self::DEFAULT_ERROR_CODE_URL.to_string()),
+        })
+    }
+
+    fn file(&self, path: &Path, line: usize, col: usize, text: &str) -> String {
+        let path = match std::env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+        // The path is substituted as is, but these characters would start the
+        // query or fragment of the URL, or a percent-encoded byte.
+        let path =
+            path.display().to_string().replace('%', "%25").replace('#', "%23").replace('?', "%3F");
+        let url = self
+            .file_url
+            .replace("{path}", &path)
+            .replace("{line}", &line.to_string())
+            .replace("{col}", &col.to_string());
+        hyperlink(&url, text)
+    }
+
+    fn error_code(&self, code: &str) -> String {
+        hyperlink(&self.error_code_url.replace("{code}", code), code)
+    }
+}
+
+/// Wraps `text` in an OSC 8 hyperlink to `url`, whose characters must be
+/// printable ASCII, so the others are percent-encoded.
+fn hyperlink(url: &str, text: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for byte in url.bytes() {
+        if byte.is_ascii_graphic() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    format!("\x1b]8;;{encoded}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
 /// Handles the writing of `HumanReadableErrorType::Default` and `HumanReadableErrorType::Short`
 pub struct EmitterWriter {
     dst: Destination,
@@ -706,6 +812,7 @@ pub struct EmitterWriter {
     teach: bool,
     ui_testing: bool,
     terminal_width: Option<usize>,
+    hyperlinks: Option<Hyperlinks>,
 
     macro_backtrace: bool,
 }
@@ -738,6 +845,7 @@ pub fn stderr(
             teach,
             ui_testing: false,
             terminal_width,
+            hyperlinks: None,
             macro_backtrace,
         }
     }
@@ -762,6 +870,7 @@ pub fn new(
             teach,
             ui_testing: false,
             terminal_width,
+            hyperlinks: None,
             macro_backtrace,
         }
     }
@@ -771,6 +880,27 @@ pub fn ui_testing(mut self, ui_testing: bool) -> Self {
         self
     }
 
+    pub fn hyperlinks(mut self, hyperlinks: Option<Hyperlinks>) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Formats a file location as `file:line:col`, or `file:line` without a
+    /// column, wrapped in a hyperlink if they are enabled.
+    fn location(&self, sm: &SourceMap, file: &FileName, line: usize, col: Option<usize>) -> String {
+        let line = sm.doctest_offset_line(file, line);
+        let mut location = format!("{}:{}", sm.filename_for_diagnostics(file), line);
+        if let Some(col) = col {
+            location.push_str(&format!(":{}", col));
+        }
+        if let (Some(hyperlinks), FileName::Real(real_file)) = (&self.hyperlinks, file) {
+            if let Some(path) = real_file.local_path() {
+                return hyperlinks.file(path, line, col.unwrap_or(1), &location);
+            }
+        }
+        location
+    }
+
     fn maybe_anonymized(&self, line_num: usize) -> String {
         if self.ui_testing { ANONYMIZED_LINE_NUM.to_string() } else { line_num.to_string() }
     }
@@ -1406,7 +1536,12 @@ fn emit_message_default(
             // only render error codes, not lint codes
             if let Some(DiagnosticId::Error(ref code)) = *code {
                 buffer.append(0, "[", Style::Level(*level));
-                buffer.append(0, &code, Style::Level(*level));
+                match &self.hyperlinks {
+                    Some(hyperlinks) => {
+                        buffer.append(0, &hyperlinks.error_code(code), Style::Level(*level))
+                    }
+                    None => buffer.append(0, &code, Style::Level(*level)),
+                }
                 buffer.append(0, "]", Style::Level(*level));
                 label_width += 2 + code.len();
             }
@@ -1474,12 +1609,7 @@ fn emit_message_default(
                     buffer.prepend(buffer_msg_line_offset, "--> ", Style::LineNumber);
                     buffer.append(
                         buffer_msg_line_offset,
-                        &format!(
-                            "{}:{}:{}",
-                            sm.filename_for_diagnostics(&loc.file.name),
-                            sm.doctest_offset_line(&loc.file.name, loc.line),
-                            loc.col.0 + 1,
-                        ),
+                        &self.location(sm, &loc.file.name, loc.line, Some(loc.col.0 + 1)),
                         Style::LineAndColumn,
                     );
                     for _ in 0..max_line_num_len {
@@ -1489,10 +1619,8 @@ fn emit_message_default(
                     buffer.prepend(
                         0,
                         &format!(
-                            "{}:{}:{}: ",
-                            sm.filename_for_diagnostics(&loc.file.name),
-                            sm.doctest_offset_line(&loc.file.name, loc.line),
-                            loc.col.0 + 1,
+                            "{}: ",
+                            self.location(sm, &loc.file.name, loc.line, Some(loc.col.0 + 1))
                         ),
                         Style::LineAndColumn,
                     );
@@ -1511,17 +1639,11 @@ fn emit_message_default(
                 // Then, the secondary file indicator
                 buffer.prepend(buffer_msg_line_offset + 1, "::: ", Style::LineNumber);
                 let loc = if let Some(first_line) = annotated_file.lines.first() {
-                    let col = if let Some(first_annotation) = first_line.annotations.first() {
-                        format!(":{}", first_annotation.start_col + 1)
-                    } else {
-                        String::new()
-                    };
-                    format!(
-                        "{}:{}{}",
-                        sm.filename_for_diagnostics(&annotated_file.file.name),
-                        sm.doctest_offset_line(&annotated_file.file.name, first_line.line_index),
-                        col
-                    )
+                    let col = first_line
+                        .annotations
+                        .first()
+                        .map(|first_annotation| first_annotation.start_col + 1);
+                    self.location(sm, &annotated_file.file.name, first_line.line_index, col)
                 } else {
                     format!("{}", sm.filename_for_diagnostics(&annotated_file.file.name))
                 };
@@ -1777,12 +1899,7 @@ enum DisplaySuggestion {
                     buffer.puts(row_num - 1, 0, "--> ", Style::LineNumber);
                     buffer.append(
                         row_num - 1,
-                        &format!(
-                            "{}:{}:{}",
-                            sm.filename_for_diagnostics(&loc.file.name),
-                            sm.doctest_offset_line(&loc.file.name, loc.line),
-                            loc.col.0 + 1,
-                        ),
+                        &self.location(sm, &loc.file.name, loc.line, Some(loc.col.0 + 1)),
                         Style::LineAndColumn,
                     );
                     for _ in 0..max_line_num_len {
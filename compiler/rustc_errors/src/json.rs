@@ -11,7 +11,7 @@
 
 use rustc_span::source_map::{FilePathMapping, SourceMap};
 
-use crate::emitter::{Emitter, HumanReadableErrorType};
+use crate::emitter::{Emitter, HumanReadableErrorType, Hyperlinks};
 use crate::registry::Registry;
 use crate::DiagnosticId;
 use crate::{
@@ -44,6 +44,7 @@ pub struct JsonEmitter {
     json_rendered: HumanReadableErrorType,
     terminal_width: Option<usize>,
     macro_backtrace: bool,
+    hyperlinks: Option<Hyperlinks>,
 }
 
 impl JsonEmitter {
@@ -68,6 +69,7 @@ pub fn stderr(
             json_rendered,
             terminal_width,
             macro_backtrace,
+            hyperlinks: None,
         }
     }
 
@@ -114,12 +116,19 @@ pub fn new(
             json_rendered,
             terminal_width,
             macro_backtrace,
+            hyperlinks: None,
         }
     }
 
     pub fn ui_testing(self, ui_testing: bool) -> Self {
         Self { ui_testing, ..self }
     }
+
+    /// Sets the hyperlinks of the `rendered` field, which only has them when it
+    /// is colored.
+    pub fn hyperlinks(self, hyperlinks: Option<Hyperlinks>) -> Self {
+        Self { hyperlinks, ..self }
+    }
 }
 
 impl Emitter for JsonEmitter {
@@ -347,6 +356,7 @@ fn flush(&mut self) -> io::Result<()> {
                 false,
                 je.terminal_width,
                 je.macro_backtrace,
+                je.hyperlinks.clone(),
             )
             .ui_testing(je.ui_testing)
             .emit_diagnostic(diag);
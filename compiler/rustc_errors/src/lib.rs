@@ -9,6 +9,7 @@
 #![feature(let_else)]
 #![feature(never_type)]
 #![feature(nll)]
+#![feature(once_cell)]
 #![feature(adt_const_params)]
 #![allow(incomplete_features)]
 #![allow(rustc::potential_query_instability)]
@@ -56,6 +57,7 @@
 pub mod json;
 mod lock;
 pub mod registry;
+pub mod sarif;
 mod snippet;
 mod styled_buffer;
 
@@ -426,6 +428,14 @@ struct HandlerInner {
 
     future_breakage_diagnostics: Vec<Diagnostic>,
 
+    /// The machine-applicable suggestions of the emitted diagnostics, if
+    /// `HandlerFlags::collect_suggestions` is set.
+    machine_applicable_suggestions: Vec<CodeSuggestion>,
+
+    /// Suppresses the warnings that aren't emitted by lints it returns `true` for,
+    /// see [`Handler::set_warning_filter`].
+    warning_filter: Option<Box<dyn Fn(&Diagnostic) -> bool + sync::Send>>,
+
     /// The [`Self::unstable_expect_diagnostics`] should be empty when this struct is
     /// dropped. However, it can have values if the compilation is stopped early
     /// or is only partially executed. To avoid ICEs, like in rust#94953 we only
@@ -480,6 +490,9 @@ pub struct HandlerFlags {
     pub macro_backtrace: bool,
     /// If true, identical diagnostics are reported only once.
     pub deduplicate_diagnostics: bool,
+    /// If true, machine-applicable suggestions are kept to be applied at the end.
+    /// (rustc: see `-Z apply-suggestions`)
+    pub collect_suggestions: bool,
 }
 
 impl Drop for HandlerInner {
@@ -583,6 +596,8 @@ pub fn with_emitter_and_flags(
                 emitted_diagnostics: Default::default(),
                 stashed_diagnostics: Default::default(),
                 future_breakage_diagnostics: Vec::new(),
+                machine_applicable_suggestions: Vec::new(),
+                warning_filter: None,
                 check_unstable_expect_diagnostics: false,
                 unstable_expect_diagnostics: Vec::new(),
                 fulfilled_expectations: Default::default(),
@@ -941,6 +956,17 @@ pub fn take_future_breakage_diagnostics(&self) -> Vec<Diagnostic> {
         std::mem::take(&mut self.inner.borrow_mut().future_breakage_diagnostics)
     }
 
+    pub fn take_machine_applicable_suggestions(&self) -> Vec<CodeSuggestion> {
+        std::mem::take(&mut self.inner.borrow_mut().machine_applicable_suggestions)
+    }
+
+    /// Installs a filter for the warnings that aren't emitted by lints: those it
+    /// returns `true` for are not emitted. Lints are filtered before they are built,
+    /// since their level depends on where they are emitted.
+    pub fn set_warning_filter(&self, filter: Box<dyn Fn(&Diagnostic) -> bool + sync::Send>) {
+        self.inner.borrow_mut().warning_filter = Some(filter);
+    }
+
     pub fn abort_if_errors(&self) {
         self.inner.borrow_mut().abort_if_errors()
     }
@@ -1089,6 +1115,14 @@ fn emit_diagnostic(&mut self, diagnostic: &mut Diagnostic) -> Option<ErrorGuaran
             return None;
         }
 
+        if diagnostic.level == Warning && !diagnostic.is_lint && !diagnostic.is_force_warn() {
+            if let Some(filter) = &self.warning_filter {
+                if filter(diagnostic) {
+                    return None;
+                }
+            }
+        }
+
         // The `LintExpectationId` can be stable or unstable depending on when it was created.
         // Diagnostics created before the definition of `HirId`s are unstable and can not yet
         // be stored. Instead, they are buffered until the `LintExpectationId` is replaced by
@@ -1138,6 +1172,16 @@ fn emit_diagnostic(&mut self, diagnostic: &mut Diagnostic) -> Option<ErrorGuaran
             diagnostic.children.drain_filter(already_emitted_sub).for_each(|_| {});
 
             self.emitter.emit_diagnostic(&diagnostic);
+            if self.flags.collect_suggestions {
+                if let Ok(suggestions) = &diagnostic.suggestions {
+                    self.machine_applicable_suggestions.extend(
+                        suggestions
+                            .iter()
+                            .filter(|sugg| sugg.applicability == Applicability::MachineApplicable)
+                            .cloned(),
+                    );
+                }
+            }
             if diagnostic.is_error() {
                 self.deduplicated_err_count += 1;
             } else if diagnostic.level == Warning {
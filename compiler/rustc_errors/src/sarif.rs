@@ -0,0 +1,476 @@
+//! A [SARIF 2.1.0] emitter for errors, for code scanning tools.
+//!
+//! Unlike the JSON emitter, which writes one object per diagnostic, this collects
+//! the diagnostics of the whole session and writes them out as a single SARIF log
+//! when the emitter is dropped, since a SARIF log can't be streamed.
+//!
+//! A session reports diagnostics through several `Handler`s: the command-line
+//! options are checked by short-lived ones before the `Session` creates its own,
+//! and rustdoc keeps another one next to it. The emitters writing to the standard
+//! error therefore add to a single run for the whole process, which is written
+//! once the last of them is dropped, or once the `DeferredSessionLog` returned by
+//! `defer_session_log` is, whichever comes last.
+//!
+//! Each diagnostic becomes a result: its primary spans are the result's locations,
+//! its secondary spans and sub-diagnostics are related locations, and its code
+//! suggestions are fixes. Error codes and lint names are the rules.
+//!
+//! [SARIF 2.1.0]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use rustc_span::source_map::{FilePathMapping, SourceMap};
+
+use crate::emitter::Emitter;
+use crate::registry::Registry;
+use crate::{CodeSuggestion, DiagnosticId, FluentBundle, LazyFallbackBundle, Level, MultiSpan};
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::Lrc;
+use rustc_error_messages::FluentArgs;
+use rustc_serialize::json::{Json, Object};
+use rustc_span::Span;
+use std::io::{self, Write};
+use std::lazy::SyncLazy;
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(test)]
+mod tests;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+
+/// The run of the emitters writing to the standard error.
+static SESSION_RUN: SyncLazy<Mutex<SharedRun>> = SyncLazy::new(Default::default);
+
+/// The rules and results of a SARIF run.
+#[derive(Default)]
+struct Run {
+    /// One `reportingDescriptor` per error code or lint, in order of appearance.
+    rules: Vec<Json>,
+    rule_indices: FxHashMap<String, usize>,
+    results: Vec<Json>,
+}
+
+/// A run shared by several emitters.
+#[derive(Default)]
+struct SharedRun {
+    run: Run,
+    /// The emitters adding to `run` that haven't been dropped yet.
+    emitters: usize,
+    /// Whether an emitter was added since `run` was last written.
+    used: bool,
+    /// The number of `DeferredSessionLog`s alive.
+    deferrals: usize,
+}
+
+impl SharedRun {
+    /// Whether `run` is due to be written, as nothing can add to it anymore.
+    fn is_complete(&self) -> bool {
+        self.used && self.emitters == 0 && self.deferrals == 0
+    }
+
+    fn take_log(&mut self) -> Json {
+        self.used = false;
+        log(mem::take(&mut self.run))
+    }
+}
+
+fn lock(run: &Mutex<SharedRun>) -> MutexGuard<'_, SharedRun> {
+    // Emitters are dropped while unwinding, possibly from a panic in another one.
+    run.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Delays writing the SARIF log of the emitters writing to the standard error
+/// until the returned value is dropped, so that the handlers created one after
+/// the other during a session share a single log.
+pub fn defer_session_log() -> DeferredSessionLog {
+    lock(&SESSION_RUN).deferrals += 1;
+    DeferredSessionLog { _private: () }
+}
+
+/// Writes the log of the emitters writing to the standard error when dropped,
+/// unless some of them are still alive. See `defer_session_log`.
+#[must_use]
+pub struct DeferredSessionLog {
+    _private: (),
+}
+
+impl Drop for DeferredSessionLog {
+    fn drop(&mut self) {
+        let mut shared = lock(&SESSION_RUN);
+        shared.deferrals -= 1;
+        if shared.is_complete() {
+            let log = shared.take_log();
+            write_log(&mut io::BufWriter::new(io::stderr()), &log);
+        }
+    }
+}
+
+pub struct SarifEmitter {
+    dst: Box<dyn Write + Send>,
+    registry: Option<Registry>,
+    sm: Lrc<SourceMap>,
+    fluent_bundle: Option<Lrc<FluentBundle>>,
+    fallback_bundle: LazyFallbackBundle,
+    /// The run of this emitter, unless it adds to a shared one.
+    run: Run,
+    shared_run: Option<&'static Mutex<SharedRun>>,
+}
+
+impl SarifEmitter {
+    pub fn stderr(
+        registry: Option<Registry>,
+        source_map: Lrc<SourceMap>,
+        fluent_bundle: Option<Lrc<FluentBundle>>,
+        fallback_bundle: LazyFallbackBundle,
+    ) -> SarifEmitter {
+        SarifEmitter::with_run(
+            Box::new(io::BufWriter::new(io::stderr())),
+            registry,
+            source_map,
+            fluent_bundle,
+            fallback_bundle,
+            Some(&SESSION_RUN),
+        )
+    }
+
+    pub fn basic(
+        fluent_bundle: Option<Lrc<FluentBundle>>,
+        fallback_bundle: LazyFallbackBundle,
+    ) -> SarifEmitter {
+        let file_path_mapping = FilePathMapping::empty();
+        SarifEmitter::stderr(
+            None,
+            Lrc::new(SourceMap::new(file_path_mapping)),
+            fluent_bundle,
+            fallback_bundle,
+        )
+    }
+
+    /// Creates an emitter writing its own log to `dst`.
+    pub fn new(
+        dst: Box<dyn Write + Send>,
+        registry: Option<Registry>,
+        source_map: Lrc<SourceMap>,
+        fluent_bundle: Option<Lrc<FluentBundle>>,
+        fallback_bundle: LazyFallbackBundle,
+    ) -> SarifEmitter {
+        SarifEmitter::with_run(dst, registry, source_map, fluent_bundle, fallback_bundle, None)
+    }
+
+    fn with_run(
+        dst: Box<dyn Write + Send>,
+        registry: Option<Registry>,
+        source_map: Lrc<SourceMap>,
+        fluent_bundle: Option<Lrc<FluentBundle>>,
+        fallback_bundle: LazyFallbackBundle,
+        shared_run: Option<&'static Mutex<SharedRun>>,
+    ) -> SarifEmitter {
+        if let Some(shared_run) = shared_run {
+            let mut shared = lock(shared_run);
+            shared.emitters += 1;
+            shared.used = true;
+        }
+        SarifEmitter {
+            dst,
+            registry,
+            sm: source_map,
+            fluent_bundle,
+            fallback_bundle,
+            run: Run::default(),
+            shared_run,
+        }
+    }
+
+    /// Returns the index of the rule for `code` in `tool.driver.rules` of `run`,
+    /// adding it if this is the first diagnostic with that code.
+    fn rule_index(&self, run: &mut Run, code: &DiagnosticId) -> (String, usize) {
+        let (id, is_error_code) = match code {
+            DiagnosticId::Error(s) => (s.clone(), true),
+            DiagnosticId::Lint { name, .. } => (name.clone(), false),
+        };
+        if let Some(&index) = run.rule_indices.get(&id) {
+            return (id, index);
+        }
+
+        let mut rule = Object::new();
+        rule.insert("id".to_string(), Json::String(id.clone()));
+        if is_error_code {
+            let explanation = self
+                .registry
+                .as_ref()
+                .and_then(|registry| registry.try_find_description(&id).ok().flatten());
+            if let Some(explanation) = explanation {
+                rule.insert("fullDescription".to_string(), message(explanation.trim()));
+            }
+            rule.insert(
+                "helpUri".to_string(),
+                Json::String(format!("https://doc.rust-lang.org/error-index.html#{id}")),
+            );
+        }
+        let index = run.rules.len();
+        run.rules.push(Json::Object(rule));
+        run.rule_indices.insert(id.clone(), index);
+        (id, index)
+    }
+
+    fn result(&self, run: &mut Run, diag: &crate::Diagnostic) -> Json {
+        let args = self.to_fluent_args(diag.args());
+        let mut result = Object::new();
+
+        if let Some(code) = &diag.code {
+            let (id, index) = self.rule_index(run, code);
+            result.insert("ruleId".to_string(), Json::String(id));
+            result.insert("ruleIndex".to_string(), Json::U64(index as u64));
+        }
+        result.insert("level".to_string(), Json::String(sarif_level(diag.level).to_string()));
+        let text = self.translate_messages(&diag.message, &args).to_string();
+        result.insert("message".to_string(), message(&text));
+
+        let (locations, mut related_locations) = self.locations(&diag.span, &args);
+        for child in &diag.children {
+            let text = self.translate_messages(&child.message, &args);
+            let text = format!("{}: {}", child.level.to_str(), text);
+            let span = child.render_span.as_ref().unwrap_or(&child.span);
+            let primary_spans = span.primary_spans();
+            if primary_spans.is_empty() {
+                related_locations.push(self.location(None, Some(&text)));
+            }
+            for &span in primary_spans {
+                related_locations.push(self.location(Some(span), Some(&text)));
+            }
+        }
+        if !locations.is_empty() {
+            result.insert("locations".to_string(), Json::Array(locations));
+        }
+        if !related_locations.is_empty() {
+            // Related locations are referred to by their id in SARIF, which is
+            // unique within the result.
+            for (id, location) in related_locations.iter_mut().enumerate() {
+                if let Json::Object(location) = location {
+                    location.insert("id".to_string(), Json::U64(id as u64));
+                }
+            }
+            result.insert("relatedLocations".to_string(), Json::Array(related_locations));
+        }
+
+        let fixes: Vec<Json> =
+            diag.suggestions.iter().flatten().flat_map(|sugg| self.fixes(sugg, &args)).collect();
+        if !fixes.is_empty() {
+            result.insert("fixes".to_string(), Json::Array(fixes));
+        }
+
+        Json::Object(result)
+    }
+
+    /// Splits the spans of `msp` into the locations of the result, for the primary
+    /// spans, and its related locations, for the labelled secondary spans.
+    fn locations(&self, msp: &MultiSpan, args: &FluentArgs<'_>) -> (Vec<Json>, Vec<Json>) {
+        let mut locations = Vec::new();
+        let mut related_locations = Vec::new();
+        for span_label in msp.span_labels() {
+            if span_label.span.is_dummy() {
+                continue;
+            }
+            let label = span_label.label.as_ref().map(|m| self.translate_message(m, args));
+            if span_label.is_primary {
+                locations.push(self.location(Some(span_label.span), label.as_deref()));
+            } else if label.is_some() {
+                related_locations.push(self.location(Some(span_label.span), label.as_deref()));
+            }
+        }
+        (locations, related_locations)
+    }
+
+    /// A `location`, which may have a message but no physical location, for notes
+    /// that aren't attached to any code.
+    fn location(&self, span: Option<Span>, text: Option<&str>) -> Json {
+        let mut location = Object::new();
+        if let Some(span) = span.filter(|span| !span.is_dummy()) {
+            let mut physical_location = Object::new();
+            physical_location.insert("artifactLocation".to_string(), self.artifact_location(span));
+            physical_location.insert("region".to_string(), self.region(span));
+            location.insert("physicalLocation".to_string(), Json::Object(physical_location));
+        }
+        if let Some(text) = text {
+            location.insert("message".to_string(), message(text));
+        }
+        Json::Object(location)
+    }
+
+    fn artifact_location(&self, span: Span) -> Json {
+        let file = self.sm.lookup_source_file(span.lo());
+        let name = self.sm.filename_for_diagnostics(&file.name).to_string();
+        let mut artifact_location = Object::new();
+        artifact_location.insert("uri".to_string(), Json::String(name.replace('\\', "/")));
+        Json::Object(artifact_location)
+    }
+
+    fn region(&self, span: Span) -> Json {
+        let start = self.sm.lookup_char_pos(span.lo());
+        let end = self.sm.lookup_char_pos(span.hi());
+        let byte_start = start.file.original_relative_byte_pos(span.lo()).0;
+        let byte_end = start.file.original_relative_byte_pos(span.hi()).0;
+        let mut region = Object::new();
+        region.insert("startLine".to_string(), Json::U64(start.line as u64));
+        region.insert("startColumn".to_string(), Json::U64(start.col.0 as u64 + 1));
+        region.insert("endLine".to_string(), Json::U64(end.line as u64));
+        region.insert("endColumn".to_string(), Json::U64(end.col.0 as u64 + 1));
+        region.insert("byteOffset".to_string(), Json::U64(byte_start as u64));
+        region.insert("byteLength".to_string(), Json::U64((byte_end - byte_start) as u64));
+        Json::Object(region)
+    }
+
+    /// One `fix` per alternative of the suggestion, with its replacements grouped
+    /// by file.
+    fn fixes(&self, sugg: &CodeSuggestion, args: &FluentArgs<'_>) -> Vec<Json> {
+        let description = self.translate_message(&sugg.msg, args).to_string();
+        sugg.substitutions
+            .iter()
+            .map(|substitution| {
+                let mut changes: Vec<(Json, Vec<Json>)> = Vec::new();
+                for part in &substitution.parts {
+                    let artifact_location = self.artifact_location(part.span);
+                    let mut replacement = Object::new();
+                    replacement.insert("deletedRegion".to_string(), self.region(part.span));
+                    replacement.insert("insertedContent".to_string(), content(&part.snippet));
+                    let replacement = Json::Object(replacement);
+                    match changes.iter_mut().find(|(location, _)| *location == artifact_location) {
+                        Some((_, replacements)) => replacements.push(replacement),
+                        None => changes.push((artifact_location, vec![replacement])),
+                    }
+                }
+
+                let mut fix = Object::new();
+                fix.insert("description".to_string(), message(&description));
+                let changes = changes
+                    .into_iter()
+                    .map(|(artifact_location, replacements)| {
+                        let mut change = Object::new();
+                        change.insert("artifactLocation".to_string(), artifact_location);
+                        change.insert("replacements".to_string(), Json::Array(replacements));
+                        Json::Object(change)
+                    })
+                    .collect();
+                fix.insert("artifactChanges".to_string(), Json::Array(changes));
+                Json::Object(fix)
+            })
+            .collect()
+    }
+}
+
+impl Emitter for SarifEmitter {
+    fn emit_diagnostic(&mut self, diag: &crate::Diagnostic) {
+        // Notes such as "aborting due to previous error" are about the compilation
+        // as a whole, which is already described by the results.
+        if diag.level == Level::FailureNote {
+            return;
+        }
+        match self.shared_run {
+            None => {
+                let mut run = mem::take(&mut self.run);
+                let result = self.result(&mut run, diag);
+                run.results.push(result);
+                self.run = run;
+            }
+            Some(shared_run) => {
+                let mut shared = lock(shared_run);
+                let result = self.result(&mut shared.run, diag);
+                shared.run.results.push(result);
+            }
+        }
+    }
+
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        Some(&self.sm)
+    }
+
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        self.fluent_bundle.as_ref()
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &**self.fallback_bundle
+    }
+
+    fn should_show_explain(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for SarifEmitter {
+    fn drop(&mut self) {
+        let log = match self.shared_run {
+            None => log(mem::take(&mut self.run)),
+            Some(shared_run) => {
+                let mut shared = lock(shared_run);
+                shared.emitters -= 1;
+                if !shared.is_complete() {
+                    return;
+                }
+                shared.take_log()
+            }
+        };
+        write_log(&mut self.dst, &log);
+    }
+}
+
+/// The SARIF log made of `run`.
+fn log(run: Run) -> Json {
+    let Run { rules, results, .. } = run;
+    let mut driver = Object::new();
+    driver.insert("name".to_string(), Json::String("rustc".to_string()));
+    driver.insert(
+        "informationUri".to_string(),
+        Json::String("https://www.rust-lang.org/".to_string()),
+    );
+    driver.insert("rules".to_string(), Json::Array(rules));
+    let mut tool = Object::new();
+    tool.insert("driver".to_string(), Json::Object(driver));
+
+    let mut run = Object::new();
+    run.insert("tool".to_string(), Json::Object(tool));
+    // Columns are counted in characters, like in the other output formats,
+    // rather than in the UTF-16 code units SARIF defaults to.
+    run.insert("columnKind".to_string(), Json::String("unicodeCodePoints".to_string()));
+    run.insert("results".to_string(), Json::Array(results));
+
+    let mut log = Object::new();
+    log.insert("$schema".to_string(), Json::String(SARIF_SCHEMA.to_string()));
+    log.insert("version".to_string(), Json::String(SARIF_VERSION.to_string()));
+    log.insert("runs".to_string(), Json::Array(vec![Json::Object(run)]));
+    Json::Object(log)
+}
+
+fn write_log(dst: &mut dyn Write, log: &Json) {
+    let result = writeln!(dst, "{}", log).and_then(|_| dst.flush());
+    // Don't turn an unwinding `FatalError` into an abort.
+    if let Err(e) = result {
+        if !std::thread::panicking() {
+            panic!("failed to print diagnostics: {:?}", e);
+        }
+    }
+}
+
+fn sarif_level(level: Level) -> &'static str {
+    match level {
+        Level::Bug | Level::DelayedBug | Level::Fatal | Level::Error { .. } => "error",
+        Level::Warning => "warning",
+        Level::Note | Level::OnceNote | Level::Help | Level::FailureNote => "note",
+        Level::Allow | Level::Expect(_) => "none",
+    }
+}
+
+/// A SARIF `message` object.
+fn message(text: &str) -> Json {
+    let mut message = Object::new();
+    message.insert("text".to_string(), Json::String(text.to_string()));
+    Json::Object(message)
+}
+
+/// A SARIF `artifactContent` object.
+fn content(text: &str) -> Json {
+    let mut content = Object::new();
+    content.insert("text".to_string(), Json::String(text.to_string()));
+    Json::Object(content)
+}
@@ -0,0 +1,148 @@
+use super::*;
+
+use crate::{Applicability, Handler};
+use rustc_serialize::json;
+use rustc_span::{BytePos, Span};
+
+use std::path::Path;
+use std::str;
+use std::sync::{Arc, Mutex};
+
+struct Shared<T> {
+    data: Arc<Mutex<T>>,
+}
+
+impl<T: Write> Write for Shared<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.data.lock().unwrap().flush()
+    }
+}
+
+/// A handler writing its SARIF log to `output`, adding to `shared_run` if any.
+fn sarif_handler(
+    code: &str,
+    output: &Arc<Mutex<Vec<u8>>>,
+    shared_run: Option<&'static Mutex<SharedRun>>,
+) -> Handler {
+    let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    sm.new_source_file(Path::new("test.rs").to_owned().into(), code.to_owned());
+    let fallback_bundle =
+        crate::fallback_fluent_bundle(rustc_error_messages::DEFAULT_LOCALE_RESOURCES, false);
+    let se = SarifEmitter::with_run(
+        Box::new(Shared { data: output.clone() }),
+        None,
+        sm,
+        None,
+        fallback_bundle,
+        shared_run,
+    );
+    Handler::with_emitter(true, None, Box::new(se))
+}
+
+/// Emits diagnostics about `code` with `emit` and returns the SARIF log.
+fn sarif_log(code: &str, emit: impl FnOnce(&Handler)) -> json::Json {
+    rustc_span::create_default_session_globals_then(|| {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let handler = sarif_handler(code, &output, None);
+        emit(&handler);
+        // The log is only written once the session is over.
+        assert!(output.lock().unwrap().is_empty());
+        drop(handler);
+
+        let bytes = output.lock().unwrap();
+        json::from_str(str::from_utf8(&bytes).unwrap()).unwrap()
+    })
+}
+
+fn span(lo: u32, hi: u32) -> Span {
+    Span::with_root_ctxt(BytePos(lo), BytePos(hi))
+}
+
+#[test]
+fn empty_log() {
+    let log = sarif_log("fn main() {}", |_| {});
+    assert_eq!(log["version"].as_string(), Some("2.1.0"));
+    let runs = log["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["tool"]["driver"]["name"].as_string(), Some("rustc"));
+    assert!(runs[0]["results"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn error_with_suggestion() {
+    let log = sarif_log("let x: u8 = \"a\";", |handler| {
+        handler
+            .struct_span_err_with_code(
+                span(12, 15),
+                "mismatched types",
+                DiagnosticId::Error("E0308".to_string()),
+            )
+            .span_label(span(7, 9), "expected due to this")
+            .note("some note")
+            .span_suggestion(span(7, 9), "change the type", "&str", Applicability::MaybeIncorrect)
+            .emit();
+        handler.struct_span_warn(span(4, 5), "unused variable").emit();
+    });
+    let run = &log["runs"][0];
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0]["id"].as_string(), Some("E0308"));
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    let error = &results[0];
+    assert_eq!(error["ruleId"].as_string(), Some("E0308"));
+    assert_eq!(error["ruleIndex"].as_u64(), Some(0));
+    assert_eq!(error["level"].as_string(), Some("error"));
+    assert_eq!(error["message"]["text"].as_string(), Some("mismatched types"));
+
+    let location = &error["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"].as_string(), Some("test.rs"));
+    assert_eq!(location["region"]["startColumn"].as_u64(), Some(13));
+    assert_eq!(location["region"]["endColumn"].as_u64(), Some(16));
+
+    let related = error["relatedLocations"].as_array().unwrap();
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0]["message"]["text"].as_string(), Some("expected due to this"));
+    assert_eq!(related[1]["message"]["text"].as_string(), Some("note: some note"));
+    assert!(related[1].find("physicalLocation").is_none());
+
+    let fix = &error["fixes"][0];
+    assert_eq!(fix["description"]["text"].as_string(), Some("change the type"));
+    let replacement = &fix["artifactChanges"][0]["replacements"][0];
+    assert_eq!(replacement["deletedRegion"]["byteOffset"].as_u64(), Some(7));
+    assert_eq!(replacement["deletedRegion"]["byteLength"].as_u64(), Some(2));
+    assert_eq!(replacement["insertedContent"]["text"].as_string(), Some("&str"));
+
+    let warning = &results[1];
+    assert_eq!(warning["level"].as_string(), Some("warning"));
+    assert!(warning.find("ruleId").is_none());
+}
+
+#[test]
+fn handlers_share_a_run() {
+    static RUN: SyncLazy<Mutex<SharedRun>> = SyncLazy::new(Default::default);
+
+    rustc_span::create_default_session_globals_then(|| {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let early_handler = sarif_handler("fn main() {}", &output, Some(&RUN));
+        let handler = sarif_handler("fn main() {}", &output, Some(&RUN));
+        early_handler.struct_warn("unknown option").emit();
+        drop(early_handler);
+        // The log is only written once every handler of the session is dropped.
+        assert!(output.lock().unwrap().is_empty());
+        handler.struct_span_err(span(3, 7), "some error").emit();
+        drop(handler);
+
+        let bytes = output.lock().unwrap();
+        let log = json::from_str(str::from_utf8(&bytes).unwrap()).unwrap();
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["message"]["text"].as_string(), Some("unknown option"));
+        assert_eq!(results[1]["message"]["text"].as_string(), Some("some error"));
+    })
+}
@@ -12,12 +12,12 @@
 use rustc_session::parse::ParseSess;
 use rustc_span::def_id::CrateNum;
 use rustc_span::symbol::{self, kw, sym, Symbol};
-use rustc_span::{BytePos, FileName, Pos, SourceFile, Span};
+use rustc_span::{BytePos, FileName, Pos, SourceFile, SourceFileHash, Span};
 
 use pm::bridge::{server, TokenTree};
 use pm::{Delimiter, Level, LineColumn, Spacing};
 use std::ops::Bound;
-use std::{ascii, panic};
+use std::{ascii, fs, panic};
 
 trait FromInternal<T> {
     fn from_internal(x: T) -> Self;
@@ -403,7 +403,11 @@ fn track_env_var(&mut self, var: &str, value: Option<&str>) {
     }
 
     fn track_path(&mut self, path: &str) {
-        self.sess().file_depinfo.borrow_mut().insert(Symbol::intern(path));
+        // The proc macro reads the file itself, so it is hashed now, before the
+        // build can move on and the file can change.
+        let hash_kind = self.sess().source_map().hash_kind();
+        let hash = fs::read(path).ok().map(|bytes| SourceFileHash::new(hash_kind, bytes));
+        self.sess().file_depinfo.borrow_mut().insert(Symbol::intern(path), hash);
     }
 }
 
@@ -173,6 +173,12 @@ pub fn set(&self, features: &mut Features, span: Span) {
     (active, test_2018_feature, "1.31.0", None, Some(Edition::Edition2018)),
     /// Added for testing unstable lints; perma-unstable.
     (active, test_unstable_lint, "1.60.0", None, None),
+    /// Allows overriding the libtest `--test-timeout` for a single test with `#[test_timeout]`.
+    // FIXME: move out of this group once it has a tracking issue.
+    (active, test_timeout, "1.63.0", None, None),
+    /// Allows using `_` as a typed hole in expressions, reporting its expected type.
+    // FIXME: move out of this group once it has a tracking issue.
+    (active, typed_holes, "1.63.0", None, None),
     /// Allows non-`unsafe` —and thus, unsound— access to `Pin` constructions.
     /// Marked `incomplete` since perma-unstable and unsound.
     (incomplete, unsafe_pin_internals, "1.60.0", None, None),
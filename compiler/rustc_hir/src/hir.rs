@@ -1654,6 +1654,7 @@ pub fn precedence(&self) -> ExprPrecedence {
             ExprKind::Struct(..) => ExprPrecedence::Struct,
             ExprKind::Repeat(..) => ExprPrecedence::Repeat,
             ExprKind::Yield(..) => ExprPrecedence::Yield,
+            ExprKind::Hole => ExprPrecedence::Path,
             ExprKind::Err => ExprPrecedence::Err,
         }
     }
@@ -1720,6 +1721,7 @@ pub fn is_place_expr(&self, mut allow_projections_from: impl FnMut(&Self) -> boo
             | ExprKind::Yield(..)
             | ExprKind::Cast(..)
             | ExprKind::DropTemps(..)
+            | ExprKind::Hole
             | ExprKind::Err => false,
         }
     }
@@ -1798,6 +1800,7 @@ pub fn can_have_side_effects(&self) -> bool {
             | ExprKind::Binary(..)
             | ExprKind::Yield(..)
             | ExprKind::DropTemps(..)
+            | ExprKind::Hole
             | ExprKind::Err => true,
         }
     }
@@ -1948,6 +1951,10 @@ pub enum ExprKind<'hir> {
     /// A suspension point for generators (i.e., `yield <expr>`).
     Yield(&'hir Expr<'hir>, YieldSource),
 
+    /// A typed hole (`_`), a placeholder for an expression whose expected type is
+    /// reported as an error (`#![feature(typed_holes)]`).
+    Hole,
+
     /// A placeholder for an expression that wasn't syntactically well formed in some way.
     Err,
 }
@@ -1540,6 +1540,7 @@ pub fn print_expr(&mut self, expr: &hir::Expr<'_>) {
                 self.word_space("yield");
                 self.print_expr_maybe_paren(&expr, parser::PREC_JUMP);
             }
+            hir::ExprKind::Hole => self.word("_"),
             hir::ExprKind::Err => {
                 self.popen();
                 self.word("/*ERROR*/");
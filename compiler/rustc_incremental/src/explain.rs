@@ -0,0 +1,189 @@
+//! Explanations of why codegen units are recompiled (`-Z incremental-explain`).
+//!
+//! A codegen unit is reused when its dep-node can be marked green. Otherwise,
+//! `try_mark_green` stopped at a dependency that is red, i.e. whose result changed,
+//! which was itself re-evaluated because of one of its dependencies, and so on.
+//! The explanation follows this chain of red dep-nodes in the dependency graph of
+//! the previous session down to the input that changed: the HIR of an item, the
+//! spans of an item in a source file, or the metadata of an upstream crate. When
+//! the command-line options changed, the whole dependency graph was discarded.
+
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::definitions::DefPathHash;
+use rustc_macros::Encodable;
+use rustc_middle::dep_graph::{DepKind, DepNode, DepNodeExt, FingerprintStyle};
+use rustc_middle::mir::mono::CodegenUnit;
+use rustc_middle::ty::TyCtxt;
+use rustc_serialize::json::as_json;
+use rustc_session::config::IncrementalExplainFormat;
+
+#[derive(Encodable)]
+struct Explanation {
+    codegen_unit: String,
+    /// The kind of the changed input: `command_line`, `hir_owner`, `source_file`,
+    /// `crate_metadata` or `query`. `unknown_dependency` for a dependency that
+    /// couldn't be re-evaluated, and `new_codegen_unit` or `no_previous_session`
+    /// when there was nothing to reuse.
+    cause: String,
+    /// The changed input: the names of the changed options, the path of the item,
+    /// the source file, the name of the crate, or the dep-node of the query.
+    input: String,
+    /// The dep-nodes from the changed input up to the codegen unit, each of them
+    /// re-evaluated because of the previous one.
+    chain: Vec<String>,
+}
+
+/// With `-Z incremental-explain`, explains why `cgu`, which couldn't be reused
+/// from the previous session, is recompiled.
+pub fn explain_cgu_recompilation<'tcx>(tcx: TyCtxt<'tcx>, cgu: &CodegenUnit<'tcx>) {
+    let Some(format) = tcx.sess.opts.debugging_opts.incremental_explain else {
+        return;
+    };
+    if !tcx.dep_graph.is_fully_enabled() {
+        return;
+    }
+
+    let explanation = explain(tcx, cgu);
+    match format {
+        IncrementalExplainFormat::Human => {
+            eprintln!(
+                "[incremental] recompiling `{}`: {}",
+                explanation.codegen_unit,
+                describe_cause(&explanation)
+            );
+            for (i, node) in explanation.chain.iter().enumerate() {
+                let arrow = if i == 0 { "" } else { "-> " };
+                eprintln!("[incremental]     {arrow}{node}");
+            }
+        }
+        IncrementalExplainFormat::Json => eprintln!("{}", as_json(&explanation)),
+    }
+}
+
+fn explain<'tcx>(tcx: TyCtxt<'tcx>, cgu: &CodegenUnit<'tcx>) -> Explanation {
+    let codegen_unit = cgu.name().to_string();
+    let without_chain = |cause: &str, input: String| Explanation {
+        codegen_unit: codegen_unit.clone(),
+        cause: cause.to_string(),
+        input,
+        chain: Vec::new(),
+    };
+
+    if let Some(options) = tcx.sess.incr_comp_changed_options.get() {
+        return without_chain("command_line", options.join(", "));
+    }
+    if tcx.dep_graph.previous_work_product(&cgu.work_product_id()).is_none() {
+        if tcx.dep_graph.previous_work_products().is_empty() {
+            return without_chain("no_previous_session", String::new());
+        }
+        return without_chain("new_codegen_unit", String::new());
+    }
+
+    let dep_node = cgu.codegen_dep_node(tcx);
+    let Some(chain) = tcx.dep_graph.red_dependency_chain(&dep_node, |node| is_input(node.kind))
+    else {
+        return without_chain("new_codegen_unit", String::new());
+    };
+    let (cause, input) = describe_input(tcx, &chain[chain.len() - 1]);
+    Explanation {
+        cause: cause.to_string(),
+        input,
+        chain: chain
+            .iter()
+            .rev()
+            .map(|node| {
+                if node.kind == DepKind::CompileCodegenUnit {
+                    format!("{:?}({})", node.kind, codegen_unit)
+                } else {
+                    describe_node(tcx, node)
+                }
+            })
+            .collect(),
+        codegen_unit,
+    }
+}
+
+/// Whether `kind` is that of a dep-node computed from the inputs of the
+/// compilation, which the explanation stops at.
+fn is_input(kind: DepKind) -> bool {
+    matches!(
+        kind,
+        DepKind::hir_owner
+            | DepKind::hir_owner_nodes
+            | DepKind::hir_owner_parent
+            | DepKind::hir_attrs
+            | DepKind::source_span
+            | DepKind::crate_hash
+    )
+}
+
+fn describe_input(tcx: TyCtxt<'_>, node: &DepNode) -> (&'static str, String) {
+    let def_id = node_def_id(tcx, node);
+    let removed = || "a removed item".to_string();
+    match node.kind {
+        DepKind::hir_owner
+        | DepKind::hir_owner_nodes
+        | DepKind::hir_owner_parent
+        | DepKind::hir_attrs => {
+            ("hir_owner", def_id.map_or_else(removed, |def_id| tcx.def_path_str(def_id)))
+        }
+        DepKind::source_span => {
+            let file = def_id.and_then(DefId::as_local).map(|def_id| {
+                let source_map = tcx.sess.source_map();
+                let file = source_map.span_to_filename(tcx.source_span(def_id));
+                source_map.filename_for_diagnostics(&file).to_string()
+            });
+            ("source_file", file.unwrap_or_else(removed))
+        }
+        DepKind::crate_hash if def_id.map_or(true, |def_id| !def_id.is_local()) => (
+            "crate_metadata",
+            def_id.map_or_else(
+                || "a removed crate".to_string(),
+                |def_id| tcx.crate_name(def_id.krate).to_string(),
+            ),
+        ),
+        _ if tcx.dep_graph.is_red(node) => ("query", describe_node(tcx, node)),
+        _ => ("unknown_dependency", describe_node(tcx, node)),
+    }
+}
+
+fn describe_cause(explanation: &Explanation) -> String {
+    let input = &explanation.input;
+    match explanation.cause.as_str() {
+        "command_line" if input.is_empty() => "the command-line options changed".to_string(),
+        "command_line" => format!("the command-line options changed: {input}"),
+        "no_previous_session" => "there is no previous session to reuse it from".to_string(),
+        "new_codegen_unit" => "it didn't exist in the previous session".to_string(),
+        "hir_owner" => format!("the HIR of `{input}` changed"),
+        "source_file" => format!("the source file `{input}` changed"),
+        "crate_metadata" => format!("the metadata of the crate `{input}` changed"),
+        "query" => format!("`{input}` changed"),
+        _ => format!("`{input}` couldn't be re-evaluated, so it is assumed to have changed"),
+    }
+}
+
+fn describe_node(tcx: TyCtxt<'_>, node: &DepNode) -> String {
+    match (node_def_id(tcx, node), node.kind.fingerprint_style(tcx)) {
+        (Some(def_id), _) => format!("{:?}({})", node.kind, tcx.def_path_str(def_id)),
+        (None, FingerprintStyle::Unit) => format!("{:?}()", node.kind),
+        (None, _) => format!("{:?}(..)", node.kind),
+    }
+}
+
+/// Returns the item that the key of `node` refers to, if it still exists.
+fn node_def_id(tcx: TyCtxt<'_>, node: &DepNode) -> Option<DefId> {
+    if node.kind.fingerprint_style(tcx) != FingerprintStyle::DefPathHash {
+        return None;
+    }
+    let def_path_hash = DefPathHash(node.hash.into());
+    if def_path_hash.stable_crate_id() == tcx.sess.local_stable_crate_id() {
+        let local_def_index =
+            tcx.definitions_untracked().def_path_hash_to_def_index_map().get(&def_path_hash)?;
+        Some(LocalDefId { local_def_index }.to_def_id())
+    } else if tcx.dep_graph.is_red(node) || tcx.dep_graph.is_green(node) {
+        // The node was evaluated in this session, so its upstream item exists.
+        node.extract_def_id(tcx)
+    } else {
+        None
+    }
+}
@@ -104,6 +104,61 @@ pub(crate) fn save_in<F>(sess: &Session, path_buf: PathBuf, name: &str, encode:
     debug!("save: data written to disk successfully");
 }
 
+/// Like `save_in`, but instead of writing the file from scratch, copies the first
+/// `len` bytes of `prev_path`, header included, to `path_buf` and appends to them.
+pub(crate) fn append_in<F>(
+    sess: &Session,
+    prev_path: &Path,
+    path_buf: PathBuf,
+    len: usize,
+    name: &str,
+    encode: F,
+) where
+    F: FnOnce(&mut FileEncoder) -> FileEncodeResult,
+{
+    debug!("append: copying data from {} to {}", prev_path.display(), path_buf.display());
+
+    // Unlike copying through the encoder, this lets the OS copy the data without
+    // reading it, or share it between both files on file systems that support it.
+    if let Err(err) = sess.time("incr_comp_copy_previous_data", || fs::copy(prev_path, &path_buf)) {
+        sess.err(&format!(
+            "failed to copy {} from `{}` to `{}`: {}",
+            name,
+            prev_path.display(),
+            path_buf.display(),
+            err
+        ));
+        return;
+    }
+    sess.prof.artifact_size(&format!("reused_{}", name.replace(' ', "_")), "copied", len as u64);
+
+    let mut encoder = match FileEncoder::append(&path_buf, len) {
+        Ok(encoder) => encoder,
+        Err(err) => {
+            sess.err(&format!("failed to open {} at `{}`: {}", name, path_buf.display(), err));
+            return;
+        }
+    };
+
+    if let Err(err) = encode(&mut encoder) {
+        sess.err(&format!("failed to write {} to `{}`: {}", name, path_buf.display(), err));
+        return;
+    }
+
+    if let Err(err) = encoder.flush() {
+        sess.err(&format!("failed to flush {} to `{}`: {}", name, path_buf.display(), err));
+        return;
+    }
+
+    sess.prof.artifact_size(
+        &name.replace(' ', "_"),
+        path_buf.file_name().unwrap().to_string_lossy(),
+        encoder.position() as u64,
+    );
+
+    debug!("append: data written to disk successfully");
+}
+
 /// Reads the contents of a file with a file header as defined in this module.
 ///
 /// - Returns `Ok(Some(data, pos))` if the file existed and was generated by a
@@ -50,6 +50,15 @@
 //! any more and will delete those. It will also delete any finalized session
 //! directories for a given crate except for the most recent one.
 //!
+//! With `-Z incremental-cache-limit`, the compiler also bounds the size of the
+//! whole incremental compilation directory, which is shared by all the crates
+//! of a build. Once it grows past the limit, the finalized session directories
+//! of all crates are evicted, least recently used first: a session directory
+//! was last used by the session that created it, as recorded by its timestamp.
+//! The work products of a session are evicted before the rest of its directory,
+//! since the next session can still reuse its dependency graph and only has to
+//! recompile the codegen units whose object files are missing.
+//!
 //! ## Synchronization
 //!
 //! There is some synchronization needed in order for the compiler to be able to
@@ -126,6 +135,7 @@
 const STAGING_DEP_GRAPH_FILENAME: &str = "dep-graph.part.bin";
 const WORK_PRODUCTS_FILENAME: &str = "work-products.bin";
 const QUERY_CACHE_FILENAME: &str = "query-cache.bin";
+const STAGING_QUERY_CACHE_FILENAME: &str = "query-cache.part.bin";
 
 // We encode integers using the following base, so they are shorter than decimal
 // or hexadecimal numbers (we want short file and directory names). Since these
@@ -151,6 +161,11 @@ pub fn work_products_path(sess: &Session) -> PathBuf {
 pub fn query_cache_path(sess: &Session) -> PathBuf {
     in_incr_comp_dir_sess(sess, QUERY_CACHE_FILENAME)
 }
+/// Returns the path to a session's staging query cache, which the new query cache is
+/// written to while the previous one, which it copies from, is still memory-mapped.
+pub fn staging_query_cache_path(sess: &Session) -> PathBuf {
+    in_incr_comp_dir_sess(sess, STAGING_QUERY_CACHE_FILENAME)
+}
 
 /// Locks a given session directory.
 pub fn lock_file_path(session_dir: &Path) -> PathBuf {
@@ -394,6 +409,15 @@ pub fn finalize_session_directory(sess: &Session, svh: Svh) {
     }
 
     let _ = garbage_collect_session_directories(sess);
+
+    if let Some(limit) = sess.opts.debugging_opts.incremental_cache_limit {
+        if let Err(err) = enforce_cache_limit(sess, limit) {
+            sess.warn(&format!(
+                "Failed to enforce the size limit of the incremental compilation directory: {}",
+                err
+            ));
+        }
+    }
 }
 
 pub fn delete_all_session_dir_contents(sess: &Session) -> io::Result<()> {
@@ -903,6 +927,223 @@ pub fn garbage_collect_session_directories(sess: &Session) -> io::Result<()> {
     Ok(())
 }
 
+/// A finalized session directory that may be evicted by `enforce_cache_limit`.
+struct CachedSession {
+    timestamp: SystemTime,
+    path: PathBuf,
+}
+
+/// Evicts the least recently used finalized session directories of all the
+/// crates in the incremental compilation directory, until it takes up at most
+/// `limit` bytes. The current session is left alone, and so are the directories
+/// that other compiler processes are reading from or working in: as with garbage
+/// collection, a directory is only evicted while holding its exclusive lock.
+/// Crate directories are never removed, even once empty, as another compiler
+/// process may be about to create a session directory in them.
+///
+/// The files that are hard-linked between session directories are only counted
+/// once, and only free space once their last link is removed.
+fn enforce_cache_limit(sess: &Session, limit: u64) -> io::Result<()> {
+    let incr_dir = sess.opts.incremental.as_ref().unwrap();
+    let current_session_dir_name = sess.incr_comp_session_dir().file_name().map(|n| n.to_owned());
+
+    let mut total_size = 0;
+    let mut seen_files = FxHashSet::default();
+    let mut sessions = Vec::new();
+    for crate_dir in incr_dir.read_dir()? {
+        let Ok(crate_dir) = crate_dir else {
+            // Ignore any errors
+            continue;
+        };
+        let Ok(entries) = crate_dir.path().read_dir() else {
+            // Not a crate directory
+            continue;
+        };
+
+        for entry in entries {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let entry_name = entry.file_name();
+            let path = entry.path();
+            let is_current_session = Some(&entry_name) == current_session_dir_name.as_ref();
+            let entry_name = entry_name.to_string_lossy();
+
+            if !is_session_directory(&entry_name) {
+                // Lock files, and files that we don't know about
+                total_size += entry.metadata().map_or(0, |metadata| metadata.len());
+                continue;
+            }
+
+            total_size += session_dir_size(&path, &mut seen_files);
+            if is_current_session || !is_finalized(&entry_name) {
+                continue;
+            }
+            let Ok(timestamp) = extract_timestamp_from_session_dir(&entry_name) else {
+                continue;
+            };
+            sessions.push(CachedSession { timestamp, path });
+        }
+    }
+
+    debug!("enforce_cache_limit() - {} bytes, limit: {} bytes", total_size, limit);
+    if total_size <= limit {
+        return Ok(());
+    }
+
+    sessions.sort_by_key(|session| session.timestamp);
+    let mut sessions_evicted = 0;
+    let mut work_products_evicted = 0;
+    for session in sessions {
+        if total_size <= limit {
+            break;
+        }
+
+        let lock_file_path = lock_file_path(&session.path);
+        let Ok(lock) = flock::Lock::new(
+            &lock_file_path,
+            false, // don't wait
+            false, // don't create the lock-file
+            true,  // get an exclusive lock
+        ) else {
+            debug!(
+                "enforce_cache_limit() - not evicting, still in use: {}",
+                session.path.display()
+            );
+            continue;
+        };
+
+        debug!("enforce_cache_limit() - evicting work products of `{}`", session.path.display());
+        let work_products_size = delete_work_product_files(&session.path);
+        total_size = total_size.saturating_sub(work_products_size);
+
+        if total_size > limit {
+            debug!("enforce_cache_limit() - evicting `{}`", session.path.display());
+            let freed_size = unlinked_size(&session.path);
+            match safe_remove_dir_all(&session.path) {
+                Ok(()) => {
+                    total_size = total_size.saturating_sub(freed_size);
+                    sessions_evicted += 1;
+                    delete_session_dir_lock_file(sess, &lock_file_path);
+                }
+                Err(err) => sess.warn(&format!(
+                    "Failed to evict incremental compilation session directory `{}`: {}",
+                    session.path.display(),
+                    err
+                )),
+            }
+        } else if work_products_size > 0 {
+            work_products_evicted += 1;
+        }
+
+        // Let's make it explicit that the file lock is released at this point,
+        // or rather, that we held on to it until here
+        mem::drop(lock);
+    }
+
+    if sess.opts.debugging_opts.incremental_info {
+        eprintln!(
+            "[incremental] cache limit: evicted {} session directories and the work \
+             products of {} others, the incremental directory now takes up {} bytes \
+             for a limit of {} bytes",
+            sessions_evicted, work_products_evicted, total_size, limit
+        );
+    }
+
+    Ok(())
+}
+
+/// Identifies a file across its hard links, where the platform allows it.
+fn file_id(metadata: &std_fs::Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Whether removing this link to a file frees its space. Elsewhere than on
+/// Unix, hard links can't be told apart, and every file is assumed to have a
+/// single link, which overestimates the space that is freed.
+fn is_last_link(metadata: &std_fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink() <= 1
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+/// Returns the size of the files in a session directory, leaving out those in
+/// `seen_files`, i.e. those that are hard links to files already counted, and
+/// adding the others to it.
+fn session_dir_size(session_dir: &Path, seen_files: &mut FxHashSet<(u64, u64)>) -> u64 {
+    let Ok(entries) = session_dir.read_dir() else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| file_id(metadata).map_or(true, |id| seen_files.insert(id)))
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Returns the size of the files in a session directory that aren't linked
+/// from anywhere else, i.e. the space that removing the directory frees.
+fn unlinked_size(session_dir: &Path) -> u64 {
+    let Ok(entries) = session_dir.read_dir() else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(is_last_link)
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Deletes the work product files of a session directory, returning the space
+/// that was freed. The next session will recompile the codegen units whose files
+/// are missing.
+fn delete_work_product_files(session_dir: &Path) -> u64 {
+    let Ok(entries) = session_dir.read_dir() else {
+        return 0;
+    };
+    let mut deleted_size = 0;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if !is_work_product_file(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let file_size =
+            entry.metadata().ok().filter(is_last_link).map_or(0, |metadata| metadata.len());
+        if safe_remove_file(&entry.path()).is_ok() {
+            deleted_size += file_size;
+        }
+    }
+    deleted_size
+}
+
+/// Work products are all the files of a session directory, except for the
+/// dependency graph and the other files that describe the session.
+fn is_work_product_file(file_name: &str) -> bool {
+    ![
+        DEP_GRAPH_FILENAME,
+        STAGING_DEP_GRAPH_FILENAME,
+        WORK_PRODUCTS_FILENAME,
+        QUERY_CACHE_FILENAME,
+        STAGING_QUERY_CACHE_FILENAME,
+    ]
+    .contains(&file_name)
+}
+
 fn delete_old(sess: &Session, path: &Path) {
     debug!("garbage_collect_session_directories() - deleting `{}`", path.display());
 
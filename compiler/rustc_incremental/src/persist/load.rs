@@ -8,6 +8,7 @@
 use rustc_serialize::Decodable;
 use rustc_session::config::IncrementalStateAssertion;
 use rustc_session::Session;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use super::data::*;
@@ -27,6 +28,11 @@ pub enum LoadResult<T> {
     },
     /// The file either didn't exist or was produced by an incompatible compiler version.
     DataOutOfDate,
+    /// The dependency graph was produced with different command-line options.
+    OptionsChanged {
+        /// The names of the tracked options that changed.
+        options: Vec<String>,
+    },
     /// An error occurred.
     Error {
         #[allow(missing_docs)]
@@ -47,7 +53,9 @@ pub fn open(self, sess: &Session) -> T {
             }
             (
                 Some(IncrementalStateAssertion::Loaded),
-                LoadResult::Error { .. } | LoadResult::DataOutOfDate,
+                LoadResult::Error { .. }
+                | LoadResult::DataOutOfDate
+                | LoadResult::OptionsChanged { .. },
             ) => {
                 sess.fatal(
                     "We asserted that an existing incremental cache directory should \
@@ -73,6 +81,11 @@ pub fn open(self, sess: &Session) -> T {
                 }
                 Default::default()
             }
+            LoadResult::OptionsChanged { options } => {
+                // Kept for `-Z incremental-explain`, the data is as out of date.
+                let _ = sess.incr_comp_changed_options.set(options);
+                LoadResult::<T>::DataOutOfDate.open(sess)
+            }
             LoadResult::Ok { data } => data,
         }
     }
@@ -143,6 +156,7 @@ pub fn load_dep_graph(sess: &Session) -> DepGraphFuture {
     let path = dep_graph_path(&sess);
     let report_incremental_info = sess.opts.debugging_opts.incremental_info;
     let expected_hash = sess.opts.dep_tracking_hash(false);
+    let expected_hashes = sess.opts.dep_tracking_hashes(false);
 
     let mut prev_work_products = FxHashMap::default();
     let nightly_build = sess.is_nightly_build();
@@ -193,10 +207,12 @@ pub fn load_dep_graph(sess: &Session) -> DepGraphFuture {
 
         match load_data(report_incremental_info, &path, nightly_build) {
             LoadResult::DataOutOfDate => LoadResult::DataOutOfDate,
+            LoadResult::OptionsChanged { options } => LoadResult::OptionsChanged { options },
             LoadResult::Error { message } => LoadResult::Error { message },
             LoadResult::Ok { data: (bytes, start_pos) } => {
                 let mut decoder = Decoder::new(&bytes, start_pos);
                 let prev_commandline_args_hash = u64::decode(&mut decoder);
+                let prev_commandline_args_hashes = BTreeMap::<String, u64>::decode(&mut decoder);
 
                 if prev_commandline_args_hash != expected_hash {
                     if report_incremental_info {
@@ -209,7 +225,8 @@ pub fn load_dep_graph(sess: &Session) -> DepGraphFuture {
                     debug!("load_dep_graph_new: differing commandline arg hashes");
 
                     // No need to do any further work
-                    return LoadResult::DataOutOfDate;
+                    let options = changed_options(&prev_commandline_args_hashes, &expected_hashes);
+                    return LoadResult::OptionsChanged { options };
                 }
 
                 let dep_graph = SerializedDepGraph::decode(&mut decoder);
@@ -220,6 +237,19 @@ pub fn load_dep_graph(sess: &Session) -> DepGraphFuture {
     }))
 }
 
+/// Returns the names of the options whose hashes differ, including those that
+/// only one of the sessions knows about.
+fn changed_options(prev: &BTreeMap<String, u64>, current: &BTreeMap<String, u64>) -> Vec<String> {
+    let mut options: Vec<String> = current
+        .iter()
+        .filter(|&(name, hash)| prev.get(name) != Some(hash))
+        .map(|(name, _)| name.clone())
+        .collect();
+    options.extend(prev.keys().filter(|name| !current.contains_key(*name)).cloned());
+    options.sort();
+    options
+}
+
 /// Attempts to load the query result cache from disk
 ///
 /// If we are not in incremental compilation mode, returns `None`.
@@ -21,5 +21,7 @@
 pub use save::build_dep_graph;
 pub use save::save_dep_graph;
 pub use save::save_work_product_index;
+pub use work_product::copy_cgu_workproduct_from_shared_cache;
 pub use work_product::copy_cgu_workproduct_to_incr_comp_cache_dir;
+pub use work_product::copy_cgu_workproduct_to_shared_cache;
 pub use work_product::delete_workproduct_files;
@@ -33,6 +33,7 @@ pub fn save_dep_graph(tcx: TyCtxt<'_>) {
         }
 
         let query_cache_path = query_cache_path(sess);
+        let staging_query_cache_path = staging_query_cache_path(sess);
         let dep_graph_path = dep_graph_path(sess);
         let staging_dep_graph_path = staging_dep_graph_path(sess);
 
@@ -46,14 +47,42 @@ pub fn save_dep_graph(tcx: TyCtxt<'_>) {
         join(
             move || {
                 sess.time("incr_comp_persist_result_cache", || {
-                    // Drop the memory map so that we can remove the file and write to it.
-                    if let Some(odc) = &tcx.on_disk_cache {
-                        odc.drop_serialized_data(tcx);
+                    // The new query cache keeps the results that are still valid from
+                    // the previous one, so it is written next to it. The previous file
+                    // is hard-linked from the directory of the previous session, which
+                    // has to stay intact until this one is finalized, so it is copied
+                    // and appended to rather than appended to in place.
+                    let reusable_len =
+                        tcx.on_disk_cache.as_ref().and_then(|odc| odc.reusable_data_len(tcx));
+                    match reusable_len {
+                        Some(len) => file_format::append_in(
+                            sess,
+                            &query_cache_path,
+                            staging_query_cache_path.clone(),
+                            len,
+                            "query cache",
+                            |e| encode_query_cache(tcx, e),
+                        ),
+                        None => file_format::save_in(
+                            sess,
+                            staging_query_cache_path.clone(),
+                            "query cache",
+                            |e| encode_query_cache(tcx, e),
+                        ),
                     }
 
-                    file_format::save_in(sess, query_cache_path, "query cache", |e| {
-                        encode_query_cache(tcx, e)
-                    });
+                    // Drop the memory map so that we can replace the previous query cache.
+                    if let Some(odc) = &tcx.on_disk_cache {
+                        odc.drop_serialized_data();
+                    }
+                    if let Err(err) = fs::rename(&staging_query_cache_path, &query_cache_path) {
+                        sess.err(&format!(
+                            "failed to move query cache from `{}` to `{}`: {}",
+                            staging_query_cache_path.display(),
+                            query_cache_path.display(),
+                            err
+                        ));
+                    }
                 });
             },
             move || {
@@ -194,6 +223,17 @@ pub fn build_dep_graph(
         return None;
     }
 
+    // Then the hash of each option, to tell which of them changed when the
+    // hashes differ (`-Z incremental-explain`).
+    if let Err(err) = sess.opts.dep_tracking_hashes(false).encode(&mut encoder) {
+        sess.err(&format!(
+            "failed to write dependency graph option hashes `{}`: {}",
+            path_buf.display(),
+            err
+        ));
+        return None;
+    }
+
     Some(DepGraph::new(
         &sess.prof,
         prev_graph,
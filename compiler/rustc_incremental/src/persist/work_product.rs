@@ -3,11 +3,13 @@
 //! [work products]: WorkProduct
 
 use crate::persist::fs::*;
+use rand::{thread_rng, RngCore};
+use rustc_data_structures::fingerprint::Fingerprint;
 use rustc_fs_util::link_or_copy;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
 use rustc_session::Session;
 use std::fs as std_fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Copies a CGU work product to the incremental compilation directory, so next compilation can find and reuse it.
 pub fn copy_cgu_workproduct_to_incr_comp_cache_dir(
@@ -59,3 +61,96 @@ pub fn delete_workproduct_files(sess: &Session, work_product: &WorkProduct) {
         }
     }
 }
+
+/// Returns the path of the object file with the given key in the shared object
+/// cache. Objects are spread over subdirectories named after their first byte.
+fn shared_cache_object_path(cache_dir: &Path, key: Fingerprint) -> PathBuf {
+    let key = key.to_hex();
+    cache_dir.join(&key[..2]).join(format!("{}.o", key))
+}
+
+/// Looks up the object file of a CGU in the shared object cache given to
+/// `-Z shared-object-cache`, by a key that fingerprints everything the object
+/// file depends on. If it is found, it is copied to the incremental compilation
+/// directory, and the CGU can reuse it as if it had been compiled by a previous
+/// session.
+pub fn copy_cgu_workproduct_from_shared_cache(
+    sess: &Session,
+    cgu_name: &str,
+    key: Fingerprint,
+) -> Option<WorkProduct> {
+    let cache_dir = sess.opts.debugging_opts.shared_object_cache.as_ref()?;
+    sess.opts.incremental.as_ref()?;
+
+    let path = shared_cache_object_path(cache_dir, key);
+    if !path.exists() {
+        debug!("copy_cgu_workproduct_from_shared_cache: no object for {}", cgu_name);
+        return None;
+    }
+
+    let file_name = format!("{}.o", cgu_name);
+    let path_in_incr_dir = in_incr_comp_dir_sess(sess, &file_name);
+    match link_or_copy(&path, &path_in_incr_dir) {
+        Ok(_) => {
+            debug!("copy_cgu_workproduct_from_shared_cache: reusing {}", path.display());
+            if sess.opts.debugging_opts.incremental_info {
+                eprintln!(
+                    "[incremental] reusing the object file of `{}` from the shared object cache",
+                    cgu_name
+                );
+            }
+            Some(WorkProduct { cgu_name: cgu_name.to_string(), saved_file: Some(file_name) })
+        }
+        Err(err) => {
+            sess.warn(&format!(
+                "error copying object file `{}` from the shared object cache as `{}`: {}",
+                path.display(),
+                path_in_incr_dir.display(),
+                err
+            ));
+            None
+        }
+    }
+}
+
+/// Adds the object file of a CGU work product to the shared object cache given
+/// to `-Z shared-object-cache`, under the given key.
+///
+/// Concurrent compiler processes may add the same object file: it is first
+/// copied under a unique temporary name, and then atomically renamed, so that
+/// the object files found in the cache are always complete.
+pub fn copy_cgu_workproduct_to_shared_cache(
+    sess: &Session,
+    work_product: &WorkProduct,
+    key: Fingerprint,
+) {
+    let Some(cache_dir) = sess.opts.debugging_opts.shared_object_cache.as_ref() else {
+        return;
+    };
+    let Some(ref file_name) = work_product.saved_file else {
+        return;
+    };
+
+    let path = shared_cache_object_path(cache_dir, key);
+    if path.exists() {
+        return;
+    }
+
+    let result = (|| {
+        std_fs::create_dir_all(path.parent().unwrap())?;
+        let temp_path = path.with_extension(format!("{:x}.tmp", thread_rng().next_u64()));
+        link_or_copy(in_incr_comp_dir_sess(sess, file_name), &temp_path)?;
+        std_fs::rename(&temp_path, &path).map_err(|err| {
+            let _ = std_fs::remove_file(&temp_path);
+            err
+        })
+    })();
+    if let Err(err) = result {
+        sess.warn(&format!(
+            "error copying object file `{}` to the shared object cache as `{}`: {}",
+            file_name,
+            path.display(),
+            err
+        ));
+    }
+}
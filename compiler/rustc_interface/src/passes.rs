@@ -27,14 +27,14 @@
 use rustc_plugin_impl as plugin;
 use rustc_query_impl::{OnDiskCache, Queries as TcxQueries};
 use rustc_resolve::{Resolver, ResolverArenas};
-use rustc_serialize::json;
+use rustc_serialize::json::{self, Json};
 use rustc_session::config::{CrateType, Input, OutputFilenames, OutputType};
-use rustc_session::cstore::{MetadataLoader, MetadataLoaderDyn};
+use rustc_session::cstore::{CrateStore, MetadataLoader, MetadataLoaderDyn};
 use rustc_session::output::{filename_for_input, filename_for_metadata};
 use rustc_session::search_paths::PathKind;
 use rustc_session::{Limit, Session};
 use rustc_span::symbol::{sym, Symbol};
-use rustc_span::FileName;
+use rustc_span::{FileName, SourceFileHash, SourceFileHashAlgorithm};
 use rustc_trait_selection::traits;
 use rustc_typeck as typeck;
 use tempfile::Builder as TempFileBuilder;
@@ -616,7 +616,7 @@ fn write_out_deps(
         // Account for explicitly marked-to-track files
         // (e.g. accessed in proc macros).
         let file_depinfo = sess.parse_sess.file_depinfo.borrow();
-        let extra_tracked_files = file_depinfo.iter().map(|path_sym| {
+        let extra_tracked_files = file_depinfo.keys().map(|path_sym| {
             let path = PathBuf::from(path_sym.as_str());
             let file = FileName::from(path);
             escape_dep_filename(&file.prefer_local().to_string())
@@ -697,6 +697,151 @@ fn write_out_deps(
     }
 }
 
+/// Writes the dependency information as JSON, with the hashes of the inputs so
+/// that build systems can tell precisely whether the crate needs to be rebuilt.
+fn write_out_deps_json(
+    sess: &Session,
+    boxed_resolver: &RefCell<BoxedResolver>,
+    outputs: &OutputFilenames,
+    out_filenames: &[PathBuf],
+) {
+    if !sess.opts.output_types.contains_key(&OutputType::DepInfoJson) {
+        return;
+    }
+    let deps_filename = outputs.path(OutputType::DepInfoJson);
+
+    fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+    fn hash(hash: Option<SourceFileHash>) -> Json {
+        hash.map_or(Json::Null, |hash| {
+            Json::String(hash.hash_bytes().iter().map(|byte| format!("{:02x}", byte)).collect())
+        })
+    }
+    fn path(path: &Path) -> Json {
+        Json::String(path.display().to_string())
+    }
+    fn file_name(path: &str) -> String {
+        FileName::from(PathBuf::from(path)).prefer_local().to_string()
+    }
+
+    let hash_kind = sess.source_map().hash_kind();
+
+    // Files included with `include_bytes!` also have a `SourceFile`, whose hash
+    // is only correct if their contents are valid UTF-8.
+    let mut include_bytes: Vec<_> = sess
+        .parse_sess
+        .binary_depinfo
+        .borrow()
+        .iter()
+        .map(|(path, &hash)| (file_name(path.as_str()), hash))
+        .collect();
+    include_bytes.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+
+    let mut sources: Vec<_> = sess
+        .source_map()
+        .files()
+        .iter()
+        .filter(|fmap| fmap.is_real_file())
+        .filter(|fmap| !fmap.is_imported())
+        .map(|fmap| (fmap.name.prefer_local().to_string(), fmap.src_hash))
+        .filter(|(name, _)| !include_bytes.iter().any(|(path, _)| path == name))
+        .collect();
+    sources.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+    sources.dedup();
+
+    // Paths tracked by proc macros are hashed when they are tracked, see
+    // `track_path` in `proc_macro_server`.
+    let mut tracked_paths: Vec<_> = sess
+        .parse_sess
+        .file_depinfo
+        .borrow()
+        .iter()
+        .map(|(path, &hash)| (file_name(path.as_str()), hash))
+        .collect();
+    tracked_paths.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+
+    let mut envs: Vec<_> = sess
+        .parse_sess
+        .env_depinfo
+        .borrow()
+        .iter()
+        .map(|(name, value)| {
+            (name.to_string(), value.map(|value| SourceFileHash::new(hash_kind, value.as_str())))
+        })
+        .collect();
+    envs.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+
+    let mut extern_crates = Vec::new();
+    boxed_resolver.borrow_mut().access(|resolver| {
+        let cstore = resolver.cstore();
+        for cnum in cstore.crates_untracked() {
+            let source = cstore.crate_source_untracked(cnum);
+            let file = |file: &Option<(PathBuf, PathKind)>| {
+                file.as_ref().map_or(Json::Null, |(file, _)| path(file))
+            };
+            extern_crates.push(object([
+                ("name", Json::String(cstore.crate_name(cnum).to_string())),
+                ("rlib", file(&source.rlib)),
+                ("rmeta", file(&source.rmeta)),
+                ("dylib", file(&source.dylib)),
+            ]));
+        }
+    });
+
+    let files = |files: Vec<(String, Option<SourceFileHash>)>| {
+        Json::Array(
+            files
+                .into_iter()
+                .map(|(name, file_hash)| {
+                    object([("path", Json::String(name)), ("hash", hash(file_hash))])
+                })
+                .collect(),
+        )
+    };
+    let hash_algorithm = match hash_kind {
+        SourceFileHashAlgorithm::Md5 => "md5",
+        SourceFileHashAlgorithm::Sha1 => "sha1",
+        SourceFileHashAlgorithm::Sha256 => "sha256",
+    };
+    let deps = object([
+        ("outputs", Json::Array(out_filenames.iter().map(|file| path(file)).collect())),
+        ("hash_algorithm", Json::String(hash_algorithm.to_string())),
+        ("sources", files(sources.into_iter().map(|(name, hash)| (name, Some(hash))).collect())),
+        (
+            "include_bytes",
+            files(include_bytes.into_iter().map(|(name, hash)| (name, Some(hash))).collect()),
+        ),
+        ("tracked_paths", files(tracked_paths)),
+        (
+            "env",
+            Json::Array(
+                envs.into_iter()
+                    .map(|(name, value_hash)| {
+                        object([("name", Json::String(name)), ("value_hash", hash(value_hash))])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("extern_crates", Json::Array(extern_crates)),
+    ]);
+
+    match fs::write(&deps_filename, format!("{}\n", deps.pretty())) {
+        Ok(()) => {
+            if sess.opts.json_artifact_notifications {
+                sess.parse_sess
+                    .span_diagnostic
+                    .emit_artifact_notification(&deps_filename, "dep-info-json");
+            }
+        }
+        Err(e) => sess.fatal(&format!(
+            "error writing dependencies to `{}`: {}",
+            deps_filename.display(),
+            e
+        )),
+    }
+}
+
 pub fn prepare_outputs(
     sess: &Session,
     compiler: &Compiler,
@@ -751,11 +896,9 @@ pub fn prepare_outputs(
     }
 
     write_out_deps(sess, boxed_resolver, &outputs, &output_paths);
+    write_out_deps_json(sess, boxed_resolver, &outputs, &output_paths);
 
-    let only_dep_info = sess.opts.output_types.contains_key(&OutputType::DepInfo)
-        && sess.opts.output_types.len() == 1;
-
-    if !only_dep_info {
+    if !sess.opts.output_types.only_dep_info() {
         if let Some(ref dir) = compiler.output_dir {
             if fs::create_dir_all(dir).is_err() {
                 let reported =
@@ -1105,6 +1248,13 @@ pub fn start_codegen<'tcx>(
         }
     }
 
+    if tcx.sess.opts.output_types.contains_key(&OutputType::MirJson) {
+        if let Err(e) = rustc_mir_transform::dump_mir::emit_mir_json(tcx, outputs) {
+            tcx.sess.err(&format!("could not emit MIR as JSON: {}", e));
+            tcx.sess.abort_if_errors();
+        }
+    }
+
     codegen
 }
 
@@ -1,8 +1,16 @@
 use crate::interface::parse_cfgspecs;
 
 use rustc_data_structures::fx::FxHashSet;
+use rustc_errors::emitter::HyperlinkConfig;
 use rustc_errors::{emitter::HumanReadableErrorType, registry, ColorConfig};
+use rustc_session::config::ApplySuggestions;
+use rustc_session::config::DiagnosticBaselineMode;
+use rustc_session::config::ExplainTraitErrorsFormat;
+use rustc_session::config::IncrementalExplainFormat;
 use rustc_session::config::InstrumentCoverage;
+use rustc_session::config::MirJsonPhase;
+use rustc_session::config::MonoBloatReportFormat;
+use rustc_session::config::SaveAnalysisFormat;
 use rustc_session::config::Strip;
 use rustc_session::config::{build_configuration, build_session_options, to_crate_config};
 use rustc_session::config::{
@@ -643,12 +651,17 @@ macro_rules! untracked {
 
     // Make sure that changing an [UNTRACKED] option leaves the hash unchanged.
     // This list is in alphabetical order.
+    untracked!(apply_suggestions, Some(ApplySuggestions::Diff));
     untracked!(assert_incr_state, Some(String::from("loaded")));
     untracked!(ast_json, true);
     untracked!(ast_json_noexpand, true);
     untracked!(borrowck, String::from("other"));
     untracked!(deduplicate_diagnostics, false);
     untracked!(dep_tasks, true);
+    untracked!(
+        diagnostic_baseline,
+        Some((DiagnosticBaselineMode::Record, PathBuf::from("baseline.txt")))
+    );
     untracked!(dlltool, Some(PathBuf::from("custom_dlltool.exe")));
     untracked!(dont_buffer_diagnostics, true);
     untracked!(dump_dep_graph, true);
@@ -658,9 +671,12 @@ macro_rules! untracked {
     untracked!(dump_mir_exclude_pass_number, true);
     untracked!(dump_mir_graphviz, true);
     untracked!(emit_stack_sizes, true);
+    untracked!(explain_trait_errors, Some(ExplainTraitErrorsFormat::Json));
     untracked!(future_incompat_test, true);
     untracked!(hir_stats, true);
     untracked!(identify_regions, true);
+    untracked!(incremental_cache_limit, Some(10 << 30));
+    untracked!(incremental_explain, Some(IncrementalExplainFormat::Json));
     untracked!(incremental_ignore_spans, true);
     untracked!(incremental_info, true);
     untracked!(incremental_verify_ich, true);
@@ -671,6 +687,8 @@ macro_rules! untracked {
     untracked!(ls, true);
     untracked!(macro_backtrace, true);
     untracked!(meta_stats, true);
+    untracked!(mir_json_phase, MirJsonPhase::Built);
+    untracked!(mono_bloat_report, Some(MonoBloatReportFormat::Json));
     untracked!(nll_facts, true);
     untracked!(no_analysis, true);
     untracked!(no_interleave_lints, true);
@@ -687,11 +705,16 @@ macro_rules! untracked {
     untracked!(proc_macro_backtrace, true);
     untracked!(query_dep_graph, true);
     untracked!(save_analysis, true);
+    untracked!(save_analysis_format, SaveAnalysisFormat::Lsif);
     untracked!(self_profile, SwitchWithOptPath::Enabled(None));
     untracked!(self_profile_events, Some(vec![String::new()]));
+    untracked!(shared_object_cache, Some(PathBuf::from("abc")));
     untracked!(span_debug, true);
     untracked!(span_free_formats, true);
     untracked!(temps_dir, Some(String::from("abc")));
+    untracked!(terminal_hyperlinks, HyperlinkConfig::Always);
+    untracked!(terminal_hyperlinks_error_code_url, Some(String::from("abc")));
+    untracked!(terminal_hyperlinks_file_url, Some(String::from("abc")));
     untracked!(terminal_width, Some(80));
     untracked!(threads, 99);
     untracked!(time, true);
@@ -19,6 +19,7 @@
 use rustc_ast::ptr::P;
 use rustc_ast::visit::{self as ast_visit, Visitor};
 use rustc_ast::{self as ast, walk_list, HasAttrs};
+use rustc_ast_pretty::pprust;
 use rustc_middle::ty::RegisteredTools;
 use rustc_session::lint::{BufferedEarlyLint, LintBuffer, LintPass};
 use rustc_session::Session;
@@ -69,6 +70,17 @@ fn with_lint_attrs<F>(&mut self, id: ast::NodeId, attrs: &'a [ast::Attribute], f
         self.context.builder.pop(push);
     }
 
+    /// Calls `f` with `segment` appended to the path of the item being visited,
+    /// which identifies the lints emitted in it in `-Z diagnostic-baseline`.
+    fn with_item_path<F>(&mut self, segment: String, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.context.builder.enter_item(segment);
+        f(self);
+        self.context.builder.exit_item();
+    }
+
     fn enter_attrs(&mut self, attrs: &'a [ast::Attribute]) {
         debug!("early context: enter_attrs({:?})", attrs);
         run_early_pass!(self, enter_lint_attrs, attrs);
@@ -80,6 +92,23 @@ fn exit_attrs(&mut self, attrs: &'a [ast::Attribute]) {
     }
 }
 
+/// The segment `it` adds to the path of the items in it, spelled like the paths of
+/// the lints emitted after the HIR is built. Impls are named after their self type
+/// (and trait), and items without a name, such as `use` items, add no segment.
+fn item_path_segment(it: &ast::Item) -> String {
+    match &it.kind {
+        ast::ItemKind::Impl(box ast::Impl { of_trait: Some(trait_ref), self_ty, .. }) => {
+            let trait_path = pprust::path_to_string(&trait_ref.path);
+            format!("<{} as {trait_path}>", pprust::ty_to_string(self_ty))
+        }
+        ast::ItemKind::Impl(box ast::Impl { self_ty, .. }) => match self_ty.kind {
+            ast::TyKind::Path(None, _) => pprust::ty_to_string(self_ty),
+            _ => format!("<{}>", pprust::ty_to_string(self_ty)),
+        },
+        _ => it.ident.to_string(),
+    }
+}
+
 impl<'a, T: EarlyLintPass> ast_visit::Visitor<'a> for EarlyContextAndPass<'a, T> {
     fn visit_param(&mut self, param: &'a ast::Param) {
         self.with_lint_attrs(param.id, &param.attrs, |cx| {
@@ -89,18 +118,22 @@ fn visit_param(&mut self, param: &'a ast::Param) {
     }
 
     fn visit_item(&mut self, it: &'a ast::Item) {
-        self.with_lint_attrs(it.id, &it.attrs, |cx| {
-            run_early_pass!(cx, check_item, it);
-            ast_visit::walk_item(cx, it);
-            run_early_pass!(cx, check_item_post, it);
+        self.with_item_path(item_path_segment(it), |cx| {
+            cx.with_lint_attrs(it.id, &it.attrs, |cx| {
+                run_early_pass!(cx, check_item, it);
+                ast_visit::walk_item(cx, it);
+                run_early_pass!(cx, check_item_post, it);
+            })
         })
     }
 
     fn visit_foreign_item(&mut self, it: &'a ast::ForeignItem) {
-        self.with_lint_attrs(it.id, &it.attrs, |cx| {
-            run_early_pass!(cx, check_foreign_item, it);
-            ast_visit::walk_foreign_item(cx, it);
-            run_early_pass!(cx, check_foreign_item_post, it);
+        self.with_item_path(it.ident.to_string(), |cx| {
+            cx.with_lint_attrs(it.id, &it.attrs, |cx| {
+                run_early_pass!(cx, check_foreign_item, it);
+                ast_visit::walk_foreign_item(cx, it);
+                run_early_pass!(cx, check_foreign_item_post, it);
+            })
         })
     }
 
@@ -259,17 +292,19 @@ fn visit_poly_trait_ref(&mut self, t: &'a ast::PolyTraitRef, m: &'a ast::TraitBo
     }
 
     fn visit_assoc_item(&mut self, item: &'a ast::AssocItem, ctxt: ast_visit::AssocCtxt) {
-        self.with_lint_attrs(item.id, &item.attrs, |cx| match ctxt {
-            ast_visit::AssocCtxt::Trait => {
-                run_early_pass!(cx, check_trait_item, item);
-                ast_visit::walk_assoc_item(cx, item, ctxt);
-                run_early_pass!(cx, check_trait_item_post, item);
-            }
-            ast_visit::AssocCtxt::Impl => {
-                run_early_pass!(cx, check_impl_item, item);
-                ast_visit::walk_assoc_item(cx, item, ctxt);
-                run_early_pass!(cx, check_impl_item_post, item);
-            }
+        self.with_item_path(item.ident.to_string(), |cx| {
+            cx.with_lint_attrs(item.id, &item.attrs, |cx| match ctxt {
+                ast_visit::AssocCtxt::Trait => {
+                    run_early_pass!(cx, check_trait_item, item);
+                    ast_visit::walk_assoc_item(cx, item, ctxt);
+                    run_early_pass!(cx, check_trait_item_post, item);
+                }
+                ast_visit::AssocCtxt::Impl => {
+                    run_early_pass!(cx, check_impl_item, item);
+                    ast_visit::walk_assoc_item(cx, item, ctxt);
+                    run_early_pass!(cx, check_impl_item_post, item);
+                }
+            })
         });
     }
 
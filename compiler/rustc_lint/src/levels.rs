@@ -8,8 +8,9 @@
 use rustc_hir::{intravisit, HirId};
 use rustc_middle::hir::nested_filter;
 use rustc_middle::lint::{
-    struct_lint_level, LevelAndSource, LintDiagnosticBuilder, LintExpectation, LintLevelMap,
-    LintLevelSets, LintLevelSource, LintSet, LintStackIndex, COMMAND_LINE,
+    diagnostic_baseline_level, struct_lint_level, LevelAndSource, LintDiagnosticBuilder,
+    LintExpectation, LintLevelMap, LintLevelSets, LintLevelSource, LintSet, LintStackIndex,
+    COMMAND_LINE,
 };
 use rustc_middle::ty::query::Providers;
 use rustc_middle::ty::{RegisteredTools, TyCtxt};
@@ -55,6 +56,8 @@ pub struct LintLevelsBuilder<'s> {
     warn_about_weird_lints: bool,
     store: &'s LintStore,
     registered_tools: &'s RegisteredTools,
+    /// The path of the item being visited by the early lint passes, by segment.
+    item_path: Vec<String>,
 }
 
 pub struct BuilderPush {
@@ -79,6 +82,7 @@ pub fn new(
             warn_about_weird_lints,
             store,
             registered_tools,
+            item_path: Vec::new(),
         };
         builder.process_command_line(sess, store);
         assert_eq!(builder.sets.list.len(), 1);
@@ -674,9 +678,27 @@ pub fn struct_lint(
         decorate: impl for<'a> FnOnce(LintDiagnosticBuilder<'a, ()>),
     ) {
         let (level, src) = self.lint_level(lint);
+        let level =
+            diagnostic_baseline_level(self.sess, lint, level, || self.item_path(), span.as_ref());
         struct_lint_level(self.sess, lint, level, src, span, decorate)
     }
 
+    /// Enters an item named `segment`, for the path of the items lints are emitted
+    /// in before the HIR is built. Unnamed items pass an empty `segment`.
+    pub fn enter_item(&mut self, segment: String) {
+        self.item_path.push(segment);
+    }
+
+    pub fn exit_item(&mut self) {
+        self.item_path.pop();
+    }
+
+    fn item_path(&self) -> String {
+        let segments: Vec<&str> =
+            self.item_path.iter().map(|s| s.as_str()).filter(|s| !s.is_empty()).collect();
+        segments.join("::")
+    }
+
     /// Registers the ID provided with the current set of lints stored in
     /// this context.
     pub fn register_id(&mut self, id: HirId) {
@@ -234,7 +234,10 @@
 use snap::read::FrameDecoder;
 use std::fmt::Write as _;
 use std::io::{Read, Result as IoResult, Write};
+use std::lazy::SyncOnceCell;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use std::{cmp, fmt, fs};
 use tracing::{debug, info};
 
@@ -271,7 +274,7 @@ pub(crate) fn new(name: Symbol, source: CrateSource) -> CratePaths {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum CrateFlavor {
     Rlib,
     Rmeta,
@@ -740,6 +743,92 @@ pub(crate) fn into_error(self, root: Option<CratePaths>) -> CrateError {
     }
 }
 
+/// The total size of metadata above which the least recently used metadata is evicted
+/// from the `MetadataCache`.
+const METADATA_CACHE_CAPACITY: usize = 1 << 30;
+
+/// The raw metadata of the crates loaded by earlier sessions of this process. See
+/// `enable_metadata_cache`.
+#[derive(Default)]
+struct MetadataCache {
+    entries: FxHashMap<(PathBuf, CrateFlavor), CachedMetadata>,
+    /// The total size of the metadata in `entries`.
+    size: usize,
+    /// Incremented on each use of the cache, to find the least recently used entries.
+    clock: u64,
+}
+
+struct CachedMetadata {
+    /// The file the metadata was loaded from, as it was then.
+    stamp: FileStamp,
+    data: Arc<[u8]>,
+    last_use: u64,
+}
+
+/// Identifies a version of a file without reading it: a file that is replaced gets a
+/// new inode, and one that is written to in place a new modification time or size.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: SystemTime,
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileStamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok()?,
+            #[cfg(unix)]
+            dev: std::os::unix::fs::MetadataExt::dev(&metadata),
+            #[cfg(unix)]
+            ino: std::os::unix::fs::MetadataExt::ino(&metadata),
+        })
+    }
+}
+
+impl MetadataCache {
+    fn get(&mut self, key: &(PathBuf, CrateFlavor), stamp: FileStamp) -> Option<Arc<[u8]>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key).filter(|entry| entry.stamp == stamp)?;
+        entry.last_use = self.clock;
+        Some(entry.data.clone())
+    }
+
+    fn insert(&mut self, key: (PathBuf, CrateFlavor), stamp: FileStamp, data: Arc<[u8]>) {
+        if data.len() > METADATA_CACHE_CAPACITY {
+            return;
+        }
+        self.clock += 1;
+        self.size += data.len();
+        let entry = CachedMetadata { stamp, data, last_use: self.clock };
+        if let Some(replaced) = self.entries.insert(key, entry) {
+            self.size -= replaced.data.len();
+        }
+        while self.size > METADATA_CACHE_CAPACITY {
+            let lru = self.entries.iter().min_by_key(|(_, entry)| entry.last_use);
+            let lru = lru.map(|(key, _)| key.clone()).unwrap();
+            let evicted = self.entries.remove(&lru).unwrap();
+            self.size -= evicted.data.len();
+        }
+    }
+}
+
+static METADATA_CACHE: SyncOnceCell<Mutex<MetadataCache>> = SyncOnceCell::new();
+
+/// Makes the sessions of this process share the raw metadata of the crates they load,
+/// which is then inflated for dylibs and copied out of rlibs only once per version of
+/// each file. This is how the compile server (`rustc --server`) keeps it between
+/// compilations. Each session still decodes the metadata it uses into its own
+/// `CrateMetadata`, which refers to the crate numbers and interners of the session.
+pub fn enable_metadata_cache() {
+    METADATA_CACHE.get_or_init(Default::default);
+}
+
 fn get_metadata_section<'p>(
     target: &Target,
     flavor: CrateFlavor,
@@ -749,6 +838,56 @@ fn get_metadata_section<'p>(
     if !filename.exists() {
         return Err(MetadataError::NotPresent(filename));
     }
+    let raw_bytes = match METADATA_CACHE.get() {
+        Some(cache) => load_cached_metadata_section(cache, target, flavor, filename, loader)?,
+        None => load_metadata_section(target, flavor, filename, loader)?,
+    };
+    let blob = MetadataBlob::new(raw_bytes);
+    if blob.is_compatible() {
+        Ok(blob)
+    } else {
+        Err(MetadataError::LoadFailure(format!(
+            "invalid metadata version found: {}",
+            filename.display()
+        )))
+    }
+}
+
+fn load_cached_metadata_section<'p>(
+    cache: &Mutex<MetadataCache>,
+    target: &Target,
+    flavor: CrateFlavor,
+    filename: &'p Path,
+    loader: &dyn MetadataLoader,
+) -> Result<MetadataRef, MetadataError<'p>> {
+    let Some(stamp) = FileStamp::of(filename) else {
+        return load_metadata_section(target, flavor, filename, loader);
+    };
+
+    let key = (filename.to_path_buf(), flavor);
+    let cached = cache.lock().unwrap().get(&key, stamp);
+    let data = match cached {
+        Some(data) => data,
+        None => {
+            let data: Arc<[u8]> =
+                Arc::from(&*load_metadata_section(target, flavor, filename, loader)?);
+            // The file may have changed while it was read, in which case the metadata
+            // is only used by this session.
+            if FileStamp::of(filename) == Some(stamp) {
+                cache.lock().unwrap().insert(key, stamp, data.clone());
+            }
+            data
+        }
+    };
+    Ok(rustc_erase_owner!(OwningRef::new(data).map_owner_box()))
+}
+
+fn load_metadata_section<'p>(
+    target: &Target,
+    flavor: CrateFlavor,
+    filename: &'p Path,
+    loader: &dyn MetadataLoader,
+) -> Result<MetadataRef, MetadataError<'p>> {
     let raw_bytes: MetadataRef = match flavor {
         CrateFlavor::Rlib => {
             loader.get_rlib_metadata(target, filename).map_err(MetadataError::LoadFailure)?
@@ -802,15 +941,7 @@ fn get_metadata_section<'p>(
             rustc_erase_owner!(OwningRef::new(mmap).map_owner_box())
         }
     };
-    let blob = MetadataBlob::new(raw_bytes);
-    if blob.is_compatible() {
-        Ok(blob)
-    } else {
-        Err(MetadataError::LoadFailure(format!(
-            "invalid metadata version found: {}",
-            filename.display()
-        )))
-    }
+    Ok(raw_bytes)
 }
 
 /// Look for a plugin registrar. Returns its library path and crate disambiguator.
@@ -301,6 +301,38 @@ pub fn explain_lint_level_source(
     }
 }
 
+/// Checks a lint against the baseline of `-Z diagnostic-baseline`, returning the level
+/// to emit it at: `Level::Allow` if the baseline has it, and `level` otherwise.
+///
+/// `item_path` is the path of the item the lint is emitted in, which is part of the
+/// lint's entry in the baseline along with the code at its primary span.
+pub fn diagnostic_baseline_level(
+    sess: &Session,
+    lint: &'static Lint,
+    level: Level,
+    item_path: impl FnOnce() -> String,
+    span: Option<&MultiSpan>,
+) -> Level {
+    let Some(baseline) = &sess.diagnostic_baseline else {
+        return level;
+    };
+    // `--force-warn` asks for the lint to be reported no matter what.
+    if !matches!(level, Level::Warn | Level::Deny | Level::Forbid) {
+        return level;
+    }
+    let primary_span = span.and_then(|span| span.primary_span());
+    if let Some(span) = primary_span {
+        // These are not reported, see `struct_lint_level`.
+        if !lint.report_in_external_macro && in_external_macro(sess, span) {
+            return level;
+        }
+    }
+    let snippet = primary_span
+        .and_then(|span| sess.source_map().span_to_snippet(span).ok())
+        .unwrap_or_default();
+    if baseline.allows(&lint.name_lower(), &item_path(), &snippet) { Level::Allow } else { level }
+}
+
 pub fn struct_lint_level<'s, 'd>(
     sess: &'s Session,
     lint: &'static Lint,
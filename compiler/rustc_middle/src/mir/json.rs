@@ -0,0 +1,568 @@
+//! A machine-readable representation of MIR bodies, written by `--emit=mir-json`.
+//!
+//! Unlike the output of `--emit=mir` and `-Z dump-mir`, which is meant for humans,
+//! the schema of this representation is versioned: any change to it that could
+//! break a consumer must increment `MIR_JSON_VERSION`, and be described in the
+//! documentation of `--emit=mir-json` in the unstable book.
+//!
+//! Types, and the values of constants, are written with the compiler's pretty
+//! printer, with full paths.
+
+use super::*;
+use crate::ty::adjustment::PointerCast;
+use crate::ty::print::with_no_trimmed_paths;
+use rustc_ast::InlineAsmTemplatePiece;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::{AsyncGeneratorKind, GeneratorKind, Unsafety};
+use rustc_serialize::json::{Json, Object};
+
+/// The version of the schema of `--emit=mir-json`.
+pub const MIR_JSON_VERSION: u64 = 1;
+
+/// Converts `body` to JSON. `promoted` is the index of the body if it is a
+/// promoted constant of the item it belongs to.
+pub fn body_to_json<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    promoted: Option<Promoted>,
+) -> Json {
+    with_no_trimmed_paths!(BodyJson { tcx, body }.body(promoted))
+}
+
+fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+    let mut object = Object::new();
+    for (key, value) in fields {
+        object.insert(key.to_string(), value);
+    }
+    Json::Object(object)
+}
+
+fn string(s: impl ToString) -> Json {
+    Json::String(s.to_string())
+}
+
+fn index(idx: impl Idx) -> Json {
+    Json::U64(idx.index() as u64)
+}
+
+fn opt_index(idx: Option<impl Idx>) -> Json {
+    idx.map_or(Json::Null, index)
+}
+
+// The names below are part of the schema, so they are spelled out rather than
+// taken from the `Debug` implementations, which can change with the compiler.
+
+fn phase_name(phase: MirPhase) -> &'static str {
+    match phase {
+        MirPhase::Built => "Built",
+        MirPhase::Const => "Const",
+        MirPhase::ConstsPromoted => "ConstsPromoted",
+        MirPhase::DropsLowered => "DropsLowered",
+        MirPhase::Derefered => "Derefered",
+        MirPhase::Deaggregated => "Deaggregated",
+        MirPhase::GeneratorsLowered => "GeneratorsLowered",
+        MirPhase::Optimized => "Optimized",
+    }
+}
+
+fn retag_kind_name(retag_kind: RetagKind) -> &'static str {
+    match retag_kind {
+        RetagKind::FnEntry => "FnEntry",
+        RetagKind::TwoPhase => "TwoPhase",
+        RetagKind::Raw => "Raw",
+        RetagKind::Default => "Default",
+    }
+}
+
+/// Pointer casts are named after their `PointerCast`, without the `Pointer(..)`
+/// wrapper of `CastKind`.
+fn cast_kind_name(cast_kind: CastKind) -> &'static str {
+    match cast_kind {
+        CastKind::PointerExposeAddress => "PointerExposeAddress",
+        CastKind::PointerFromExposedAddress => "PointerFromExposedAddress",
+        CastKind::Pointer(PointerCast::ReifyFnPointer) => "ReifyFnPointer",
+        CastKind::Pointer(PointerCast::UnsafeFnPointer) => "UnsafeFnPointer",
+        CastKind::Pointer(PointerCast::ClosureFnPointer(Unsafety::Normal)) => "ClosureFnPointer",
+        CastKind::Pointer(PointerCast::ClosureFnPointer(Unsafety::Unsafe)) => {
+            "UnsafeClosureFnPointer"
+        }
+        CastKind::Pointer(PointerCast::MutToConstPointer) => "MutToConstPointer",
+        CastKind::Pointer(PointerCast::ArrayToPointer) => "ArrayToPointer",
+        CastKind::Pointer(PointerCast::Unsize) => "Unsize",
+        CastKind::Misc => "Misc",
+    }
+}
+
+fn bin_op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "Add",
+        BinOp::Sub => "Sub",
+        BinOp::Mul => "Mul",
+        BinOp::Div => "Div",
+        BinOp::Rem => "Rem",
+        BinOp::BitXor => "BitXor",
+        BinOp::BitAnd => "BitAnd",
+        BinOp::BitOr => "BitOr",
+        BinOp::Shl => "Shl",
+        BinOp::Shr => "Shr",
+        BinOp::Eq => "Eq",
+        BinOp::Lt => "Lt",
+        BinOp::Le => "Le",
+        BinOp::Ne => "Ne",
+        BinOp::Ge => "Ge",
+        BinOp::Gt => "Gt",
+        BinOp::Offset => "Offset",
+    }
+}
+
+fn null_op_name(op: NullOp) -> &'static str {
+    match op {
+        NullOp::SizeOf => "SizeOf",
+        NullOp::AlignOf => "AlignOf",
+    }
+}
+
+fn un_op_name(op: UnOp) -> &'static str {
+    match op {
+        UnOp::Not => "Not",
+        UnOp::Neg => "Neg",
+    }
+}
+
+fn generator_kind_name(generator_kind: GeneratorKind) -> &'static str {
+    match generator_kind {
+        GeneratorKind::Gen => "Generator",
+        GeneratorKind::Async(AsyncGeneratorKind::Block) => "AsyncBlock",
+        GeneratorKind::Async(AsyncGeneratorKind::Closure) => "AsyncClosure",
+        GeneratorKind::Async(AsyncGeneratorKind::Fn) => "AsyncFn",
+    }
+}
+
+struct BodyJson<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    body: &'a Body<'tcx>,
+}
+
+impl<'a, 'tcx> BodyJson<'a, 'tcx> {
+    fn body(&self, promoted: Option<Promoted>) -> Json {
+        let tcx = self.tcx;
+        let body = self.body;
+        let def_id = body.source.def_id();
+
+        // Name the locals that are user variables.
+        let mut names = FxHashMap::default();
+        for var_debug_info in &body.var_debug_info {
+            if let VarDebugInfoContents::Place(place) = var_debug_info.value {
+                if place.projection.is_empty() {
+                    names.entry(place.local).or_insert(var_debug_info.name);
+                }
+            }
+        }
+
+        let locals = body
+            .local_decls
+            .iter_enumerated()
+            .map(|(local, decl)| {
+                object([
+                    ("index", index(local)),
+                    ("name", names.get(&local).map_or(Json::Null, string)),
+                    ("ty", self.ty(decl.ty)),
+                    ("mutable", Json::Boolean(decl.mutability == Mutability::Mut)),
+                    ("span", self.span(decl.source_info.span)),
+                ])
+            })
+            .collect();
+
+        let basic_blocks = body
+            .basic_blocks()
+            .iter_enumerated()
+            .map(|(bb, data)| {
+                object([
+                    ("index", index(bb)),
+                    ("cleanup", Json::Boolean(data.is_cleanup)),
+                    (
+                        "statements",
+                        Json::Array(
+                            data.statements.iter().map(|stmt| self.statement(stmt)).collect(),
+                        ),
+                    ),
+                    ("terminator", self.terminator(data.terminator())),
+                ])
+            })
+            .collect();
+
+        object([
+            ("def_path", string(tcx.def_path_str(def_id))),
+            ("kind", string(tcx.def_kind(def_id).descr(def_id))),
+            ("promoted", opt_index(promoted)),
+            ("phase", string(phase_name(body.phase))),
+            ("span", self.span(body.span)),
+            ("arg_count", Json::U64(body.arg_count as u64)),
+            ("locals", Json::Array(locals)),
+            ("basic_blocks", Json::Array(basic_blocks)),
+        ])
+    }
+
+    fn ty(&self, ty: Ty<'tcx>) -> Json {
+        string(ty)
+    }
+
+    fn span(&self, span: Span) -> Json {
+        if span.is_dummy() {
+            return Json::Null;
+        }
+        let source_map = self.tcx.sess.source_map();
+        let lo = source_map.lookup_char_pos(span.lo());
+        let hi = source_map.lookup_char_pos(span.hi());
+        object([
+            ("file", string(lo.file.name.prefer_remapped())),
+            ("line_start", Json::U64(lo.line as u64)),
+            ("column_start", Json::U64(lo.col.0 as u64 + 1)),
+            ("line_end", Json::U64(hi.line as u64)),
+            ("column_end", Json::U64(hi.col.0 as u64 + 1)),
+        ])
+    }
+
+    fn place(&self, place: Place<'tcx>) -> Json {
+        let projection = place
+            .projection
+            .iter()
+            .map(|elem| match elem {
+                ProjectionElem::Deref => object([("kind", string("Deref"))]),
+                ProjectionElem::Field(field, ty) => object([
+                    ("kind", string("Field")),
+                    ("field", index(field)),
+                    ("ty", self.ty(ty)),
+                ]),
+                ProjectionElem::Index(local) => {
+                    object([("kind", string("Index")), ("local", index(local))])
+                }
+                ProjectionElem::ConstantIndex { offset, min_length, from_end } => object([
+                    ("kind", string("ConstantIndex")),
+                    ("offset", Json::U64(offset)),
+                    ("min_length", Json::U64(min_length)),
+                    ("from_end", Json::Boolean(from_end)),
+                ]),
+                ProjectionElem::Subslice { from, to, from_end } => object([
+                    ("kind", string("Subslice")),
+                    ("from", Json::U64(from)),
+                    ("to", Json::U64(to)),
+                    ("from_end", Json::Boolean(from_end)),
+                ]),
+                ProjectionElem::Downcast(name, variant) => object([
+                    ("kind", string("Downcast")),
+                    ("variant", index(variant)),
+                    ("name", name.map_or(Json::Null, string)),
+                ]),
+            })
+            .collect();
+        object([
+            ("local", index(place.local)),
+            ("projection", Json::Array(projection)),
+            ("ty", self.ty(place.ty(self.body, self.tcx).ty)),
+        ])
+    }
+
+    fn operand(&self, operand: &Operand<'tcx>) -> Json {
+        match operand {
+            Operand::Copy(place) => {
+                object([("kind", string("Copy")), ("place", self.place(*place))])
+            }
+            Operand::Move(place) => {
+                object([("kind", string("Move")), ("place", self.place(*place))])
+            }
+            Operand::Constant(constant) => {
+                object([("kind", string("Constant")), ("constant", self.constant(constant))])
+            }
+        }
+    }
+
+    fn operands(&self, operands: &[Operand<'tcx>]) -> Json {
+        Json::Array(operands.iter().map(|operand| self.operand(operand)).collect())
+    }
+
+    fn constant(&self, constant: &Constant<'tcx>) -> Json {
+        let ty = constant.ty();
+        // Calls are to constants of function item types, so make the callee easy
+        // to find.
+        let function = match *ty.kind() {
+            ty::FnDef(def_id, substs) => object([
+                ("def_path", string(self.tcx.def_path_str(def_id))),
+                ("substs", Json::Array(substs.iter().map(string).collect())),
+            ]),
+            _ => Json::Null,
+        };
+        object([
+            ("ty", self.ty(ty)),
+            ("value", string(constant.literal)),
+            ("function", function),
+            ("span", self.span(constant.span)),
+        ])
+    }
+
+    fn statement(&self, statement: &Statement<'tcx>) -> Json {
+        let mut json = match &statement.kind {
+            StatementKind::Assign(box (place, rvalue)) => object([
+                ("kind", string("Assign")),
+                ("place", self.place(*place)),
+                ("rvalue", self.rvalue(rvalue)),
+            ]),
+            StatementKind::FakeRead(box (_, place)) => {
+                object([("kind", string("FakeRead")), ("place", self.place(*place))])
+            }
+            StatementKind::SetDiscriminant { place, variant_index } => object([
+                ("kind", string("SetDiscriminant")),
+                ("place", self.place(**place)),
+                ("variant", index(*variant_index)),
+            ]),
+            StatementKind::Deinit(place) => {
+                object([("kind", string("Deinit")), ("place", self.place(**place))])
+            }
+            StatementKind::StorageLive(local) => {
+                object([("kind", string("StorageLive")), ("local", index(*local))])
+            }
+            StatementKind::StorageDead(local) => {
+                object([("kind", string("StorageDead")), ("local", index(*local))])
+            }
+            StatementKind::Retag(retag_kind, place) => object([
+                ("kind", string("Retag")),
+                ("retag_kind", string(retag_kind_name(*retag_kind))),
+                ("place", self.place(**place)),
+            ]),
+            StatementKind::AscribeUserType(box (place, _), _) => {
+                object([("kind", string("AscribeUserType")), ("place", self.place(*place))])
+            }
+            StatementKind::Coverage(_) => object([("kind", string("Coverage"))]),
+            StatementKind::CopyNonOverlapping(box CopyNonOverlapping { src, dst, count }) => {
+                object([
+                    ("kind", string("CopyNonOverlapping")),
+                    ("src", self.operand(src)),
+                    ("dst", self.operand(dst)),
+                    ("count", self.operand(count)),
+                ])
+            }
+            StatementKind::Nop => object([("kind", string("Nop"))]),
+        };
+        if let Json::Object(object) = &mut json {
+            object.insert("span".to_string(), self.span(statement.source_info.span));
+        }
+        json
+    }
+
+    fn rvalue(&self, rvalue: &Rvalue<'tcx>) -> Json {
+        match rvalue {
+            Rvalue::Use(operand) => {
+                object([("kind", string("Use")), ("operand", self.operand(operand))])
+            }
+            Rvalue::Repeat(operand, count) => object([
+                ("kind", string("Repeat")),
+                ("operand", self.operand(operand)),
+                ("count", string(count)),
+            ]),
+            Rvalue::Ref(_, borrow_kind, place) => {
+                let borrow_kind = match borrow_kind {
+                    BorrowKind::Shared => "Shared",
+                    BorrowKind::Shallow => "Shallow",
+                    BorrowKind::Unique => "Unique",
+                    BorrowKind::Mut { .. } => "Mut",
+                };
+                object([
+                    ("kind", string("Ref")),
+                    ("borrow_kind", string(borrow_kind)),
+                    ("place", self.place(*place)),
+                ])
+            }
+            Rvalue::ThreadLocalRef(def_id) => object([
+                ("kind", string("ThreadLocalRef")),
+                ("def_path", string(self.tcx.def_path_str(*def_id))),
+            ]),
+            Rvalue::AddressOf(mutability, place) => object([
+                ("kind", string("AddressOf")),
+                ("mutable", Json::Boolean(*mutability == Mutability::Mut)),
+                ("place", self.place(*place)),
+            ]),
+            Rvalue::Len(place) => object([("kind", string("Len")), ("place", self.place(*place))]),
+            Rvalue::Cast(cast_kind, operand, ty) => object([
+                ("kind", string("Cast")),
+                ("cast_kind", string(cast_kind_name(*cast_kind))),
+                ("operand", self.operand(operand)),
+                ("ty", self.ty(*ty)),
+            ]),
+            Rvalue::BinaryOp(op, box (lhs, rhs)) | Rvalue::CheckedBinaryOp(op, box (lhs, rhs)) => {
+                let kind =
+                    if let Rvalue::BinaryOp(..) = rvalue { "BinaryOp" } else { "CheckedBinaryOp" };
+                object([
+                    ("kind", string(kind)),
+                    ("op", string(bin_op_name(*op))),
+                    ("lhs", self.operand(lhs)),
+                    ("rhs", self.operand(rhs)),
+                ])
+            }
+            Rvalue::NullaryOp(op, ty) => object([
+                ("kind", string("NullaryOp")),
+                ("op", string(null_op_name(*op))),
+                ("ty", self.ty(*ty)),
+            ]),
+            Rvalue::UnaryOp(op, operand) => object([
+                ("kind", string("UnaryOp")),
+                ("op", string(un_op_name(*op))),
+                ("operand", self.operand(operand)),
+            ]),
+            Rvalue::Discriminant(place) => {
+                object([("kind", string("Discriminant")), ("place", self.place(*place))])
+            }
+            Rvalue::Aggregate(aggregate_kind, operands) => object([
+                ("kind", string("Aggregate")),
+                ("aggregate", self.aggregate_kind(aggregate_kind)),
+                ("operands", self.operands(operands)),
+            ]),
+            Rvalue::ShallowInitBox(operand, ty) => object([
+                ("kind", string("ShallowInitBox")),
+                ("operand", self.operand(operand)),
+                ("ty", self.ty(*ty)),
+            ]),
+        }
+    }
+
+    fn aggregate_kind(&self, aggregate_kind: &AggregateKind<'tcx>) -> Json {
+        let substs = |substs: SubstsRef<'tcx>| Json::Array(substs.iter().map(string).collect());
+        match *aggregate_kind {
+            AggregateKind::Array(ty) => object([("kind", string("Array")), ("ty", self.ty(ty))]),
+            AggregateKind::Tuple => object([("kind", string("Tuple"))]),
+            AggregateKind::Adt(def_id, variant, adt_substs, _, active_field) => object([
+                ("kind", string("Adt")),
+                ("def_path", string(self.tcx.def_path_str(def_id))),
+                ("variant", index(variant)),
+                ("substs", substs(adt_substs)),
+                ("active_field", active_field.map_or(Json::Null, |field| Json::U64(field as u64))),
+            ]),
+            AggregateKind::Closure(def_id, closure_substs) => object([
+                ("kind", string("Closure")),
+                ("def_path", string(self.tcx.def_path_str(def_id))),
+                ("substs", substs(closure_substs)),
+            ]),
+            AggregateKind::Generator(def_id, generator_substs, _) => object([
+                ("kind", string("Generator")),
+                ("def_path", string(self.tcx.def_path_str(def_id))),
+                ("substs", substs(generator_substs)),
+            ]),
+        }
+    }
+
+    fn assert_kind(&self, msg: &AssertMessage<'tcx>) -> Json {
+        match msg {
+            AssertKind::BoundsCheck { len, index } => object([
+                ("kind", string("BoundsCheck")),
+                ("len", self.operand(len)),
+                ("index", self.operand(index)),
+            ]),
+            AssertKind::Overflow(op, lhs, rhs) => object([
+                ("kind", string("Overflow")),
+                ("op", string(bin_op_name(*op))),
+                ("lhs", self.operand(lhs)),
+                ("rhs", self.operand(rhs)),
+            ]),
+            AssertKind::OverflowNeg(operand) => {
+                object([("kind", string("OverflowNeg")), ("operand", self.operand(operand))])
+            }
+            AssertKind::DivisionByZero(operand) => {
+                object([("kind", string("DivisionByZero")), ("operand", self.operand(operand))])
+            }
+            AssertKind::RemainderByZero(operand) => {
+                object([("kind", string("RemainderByZero")), ("operand", self.operand(operand))])
+            }
+            AssertKind::ResumedAfterReturn(generator_kind) => object([
+                ("kind", string("ResumedAfterReturn")),
+                ("generator_kind", string(generator_kind_name(*generator_kind))),
+            ]),
+            AssertKind::ResumedAfterPanic(generator_kind) => object([
+                ("kind", string("ResumedAfterPanic")),
+                ("generator_kind", string(generator_kind_name(*generator_kind))),
+            ]),
+        }
+    }
+
+    fn terminator(&self, terminator: &Terminator<'tcx>) -> Json {
+        let mut json = match &terminator.kind {
+            TerminatorKind::Goto { target } => {
+                object([("kind", string("Goto")), ("target", index(*target))])
+            }
+            TerminatorKind::SwitchInt { discr, switch_ty, targets } => {
+                let values = targets
+                    .iter()
+                    .map(|(value, target)| {
+                        object([("value", string(value)), ("target", index(target))])
+                    })
+                    .collect();
+                object([
+                    ("kind", string("SwitchInt")),
+                    ("discr", self.operand(discr)),
+                    ("switch_ty", self.ty(*switch_ty)),
+                    ("targets", Json::Array(values)),
+                    ("otherwise", index(targets.otherwise())),
+                ])
+            }
+            TerminatorKind::Resume => object([("kind", string("Resume"))]),
+            TerminatorKind::Abort => object([("kind", string("Abort"))]),
+            TerminatorKind::Return => object([("kind", string("Return"))]),
+            TerminatorKind::Unreachable => object([("kind", string("Unreachable"))]),
+            TerminatorKind::Drop { place, target, unwind } => object([
+                ("kind", string("Drop")),
+                ("place", self.place(*place)),
+                ("target", index(*target)),
+                ("unwind", opt_index(*unwind)),
+            ]),
+            TerminatorKind::DropAndReplace { place, value, target, unwind } => object([
+                ("kind", string("DropAndReplace")),
+                ("place", self.place(*place)),
+                ("value", self.operand(value)),
+                ("target", index(*target)),
+                ("unwind", opt_index(*unwind)),
+            ]),
+            TerminatorKind::Call { func, args, destination, target, cleanup, .. } => object([
+                ("kind", string("Call")),
+                ("func", self.operand(func)),
+                ("args", self.operands(args)),
+                ("destination", self.place(*destination)),
+                ("target", opt_index(*target)),
+                ("cleanup", opt_index(*cleanup)),
+            ]),
+            TerminatorKind::Assert { cond, expected, msg, target, cleanup } => object([
+                ("kind", string("Assert")),
+                ("cond", self.operand(cond)),
+                ("expected", Json::Boolean(*expected)),
+                ("msg", self.assert_kind(msg)),
+                ("target", index(*target)),
+                ("cleanup", opt_index(*cleanup)),
+            ]),
+            TerminatorKind::Yield { value, resume, resume_arg, drop } => object([
+                ("kind", string("Yield")),
+                ("value", self.operand(value)),
+                ("resume", index(*resume)),
+                ("resume_arg", self.place(*resume_arg)),
+                ("drop", opt_index(*drop)),
+            ]),
+            TerminatorKind::GeneratorDrop => object([("kind", string("GeneratorDrop"))]),
+            TerminatorKind::FalseEdge { real_target, imaginary_target } => object([
+                ("kind", string("FalseEdge")),
+                ("real_target", index(*real_target)),
+                ("imaginary_target", index(*imaginary_target)),
+            ]),
+            TerminatorKind::FalseUnwind { real_target, unwind } => object([
+                ("kind", string("FalseUnwind")),
+                ("real_target", index(*real_target)),
+                ("unwind", opt_index(*unwind)),
+            ]),
+            TerminatorKind::InlineAsm { template, destination, cleanup, .. } => object([
+                ("kind", string("InlineAsm")),
+                ("template", string(InlineAsmTemplatePiece::to_string(template))),
+                ("destination", opt_index(*destination)),
+                ("cleanup", opt_index(*cleanup)),
+            ]),
+        };
+        if let Json::Object(object) = &mut json {
+            object.insert("span".to_string(), self.span(terminator.source_info.span));
+        }
+        json
+    }
+}
@@ -54,6 +54,7 @@
 mod graph_cyclic_cache;
 pub mod graphviz;
 pub mod interpret;
+pub mod json;
 pub mod mono;
 pub mod patch;
 mod predecessors;
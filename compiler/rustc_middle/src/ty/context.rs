@@ -4,7 +4,9 @@
 use crate::dep_graph::{DepGraph, DepKind, DepKindStruct};
 use crate::hir::place::Place as HirPlace;
 use crate::infer::canonical::{Canonical, CanonicalVarInfo, CanonicalVarInfos};
-use crate::lint::{struct_lint_level, LintDiagnosticBuilder, LintLevelSource};
+use crate::lint::{
+    diagnostic_baseline_level, struct_lint_level, LintDiagnosticBuilder, LintLevelSource,
+};
 use crate::middle::codegen_fn_attrs::CodegenFnAttrs;
 use crate::middle::resolve_lifetime::{self, LifetimeScopeForPath};
 use crate::middle::stability;
@@ -14,6 +16,7 @@
 };
 use crate::thir::Thir;
 use crate::traits;
+use crate::ty::print::with_no_trimmed_paths;
 use crate::ty::query::{self, TyCtxtAt};
 use crate::ty::subst::{GenericArg, GenericArgKind, InternalSubsts, Subst, SubstsRef, UserSubsts};
 use crate::ty::{
@@ -85,7 +88,12 @@ fn new_empty(source_map: &'tcx SourceMap) -> Self
     where
         Self: Sized;
 
-    fn drop_serialized_data(&self, tcx: TyCtxt<'tcx>);
+    fn drop_serialized_data(&self);
+
+    /// Returns the length of the prefix of the serialized data, header included,
+    /// that the cache of the current session can start with, if any. `serialize`
+    /// then only has to write what comes after it.
+    fn reusable_data_len(&self, tcx: TyCtxt<'tcx>) -> Option<usize>;
 
     fn serialize(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileEncodeResult;
 }
@@ -2778,8 +2786,16 @@ pub fn struct_span_lint_hir(
         span: impl Into<MultiSpan>,
         decorate: impl for<'a> FnOnce(LintDiagnosticBuilder<'a, ()>),
     ) {
+        let span = span.into();
         let (level, src) = self.lint_level_at_node(lint, hir_id);
-        struct_lint_level(self.sess, lint, level, src, Some(span.into()), decorate);
+        let level = diagnostic_baseline_level(
+            self.sess,
+            lint,
+            level,
+            || self.lint_item_path(hir_id),
+            Some(&span),
+        );
+        struct_lint_level(self.sess, lint, level, src, Some(span), decorate);
     }
 
     pub fn struct_lint_node(
@@ -2789,9 +2805,17 @@ pub fn struct_lint_node(
         decorate: impl for<'a> FnOnce(LintDiagnosticBuilder<'a, ()>),
     ) {
         let (level, src) = self.lint_level_at_node(lint, id);
+        let level =
+            diagnostic_baseline_level(self.sess, lint, level, || self.lint_item_path(id), None);
         struct_lint_level(self.sess, lint, level, src, None, decorate);
     }
 
+    /// The path of the item containing `id`, which identifies lints emitted at `id`
+    /// in the baseline of `-Z diagnostic-baseline`.
+    fn lint_item_path(self, id: HirId) -> String {
+        with_no_trimmed_paths!(self.def_path_str(id.owner.to_def_id()))
+    }
+
     pub fn in_scope_traits(self, id: HirId) -> Option<&'tcx [TraitCandidate]> {
         let map = self.in_scope_traits_map(id.owner)?;
         let candidates = map.get(&id.local_id)?;
@@ -2,13 +2,18 @@
 
 use std::borrow::Cow;
 use std::fs::File;
-use std::io;
+use std::io::{self, Write};
+use std::mem;
 
 use crate::MirPass;
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::mir::json::{body_to_json, MIR_JSON_VERSION};
+use rustc_middle::mir::pretty::dump_mir_def_ids;
 use rustc_middle::mir::write_mir_pretty;
-use rustc_middle::mir::Body;
-use rustc_middle::ty::TyCtxt;
-use rustc_session::config::{OutputFilenames, OutputType};
+use rustc_middle::mir::{Body, Promoted};
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_serialize::json::{Json, Object};
+use rustc_session::config::{MirJsonPhase, OutputFilenames, OutputType};
 
 pub struct Marker(pub &'static str);
 
@@ -26,3 +31,66 @@ pub fn emit_mir(tcx: TyCtxt<'_>, outputs: &OutputFilenames) -> io::Result<()> {
     write_mir_pretty(tcx, None, &mut f)?;
     Ok(())
 }
+
+/// Records `body` for `--emit=mir-json` if it is the MIR of the requested phase,
+/// as it is stolen by the next phase before the MIR is emitted.
+pub(crate) fn record_mir_json<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    phase: MirJsonPhase,
+    body: &Body<'tcx>,
+    promoted: Option<Promoted>,
+) {
+    if tcx.sess.opts.output_types.contains_key(&OutputType::MirJson)
+        && tcx.sess.opts.debugging_opts.mir_json_phase == phase
+    {
+        let json = body_to_json(tcx, body, promoted);
+        tcx.sess.mir_json_bodies.lock().push(json);
+    }
+}
+
+pub fn emit_mir_json(tcx: TyCtxt<'_>, outputs: &OutputFilenames) -> io::Result<()> {
+    let phase = tcx.sess.opts.debugging_opts.mir_json_phase;
+    let bodies = match phase {
+        MirJsonPhase::Optimized => {
+            let mut bodies = Vec::new();
+            for def_id in dump_mir_def_ids(tcx, None) {
+                // Like `--emit=mir`, write the MIR of `const fn`s that is codegened.
+                let body = if tcx.is_const_fn_raw(def_id) {
+                    tcx.optimized_mir(def_id)
+                } else {
+                    tcx.instance_mir(ty::InstanceDef::Item(ty::WithOptConstParam::unknown(def_id)))
+                };
+                bodies.push(body_to_json(tcx, body, None));
+                for (promoted, body) in tcx.promoted_mir(def_id).iter_enumerated() {
+                    bodies.push(body_to_json(tcx, body, Some(promoted)));
+                }
+            }
+            bodies
+        }
+        MirJsonPhase::Built | MirJsonPhase::Analysis => {
+            let mut bodies = mem::take(&mut *tcx.sess.mir_json_bodies.lock());
+            // The bodies are recorded in the order they are built, which isn't deterministic.
+            bodies.sort_by_cached_key(|body| {
+                let def_path = body.find("def_path").and_then(Json::as_string).map(str::to_owned);
+                (def_path, body.find("promoted").and_then(Json::as_u64))
+            });
+            bodies
+        }
+    };
+
+    let phase = match phase {
+        MirJsonPhase::Built => "built",
+        MirJsonPhase::Analysis => "analysis",
+        MirJsonPhase::Optimized => "optimized",
+    };
+    let mut mir = Object::new();
+    mir.insert("version".to_string(), Json::U64(MIR_JSON_VERSION));
+    mir.insert("crate".to_string(), Json::String(tcx.crate_name(LOCAL_CRATE).to_string()));
+    mir.insert("phase".to_string(), Json::String(phase.to_string()));
+    mir.insert("bodies".to_string(), Json::Array(bodies));
+
+    let path = outputs.path(OutputType::MirJson);
+    let mut f = io::BufWriter::new(File::create(&path)?);
+    writeln!(f, "{}", Json::Object(mir))?;
+    Ok(())
+}
@@ -28,6 +28,7 @@
 use rustc_middle::mir::{traversal, Body, ConstQualifs, MirPass, MirPhase, Promoted};
 use rustc_middle::ty::query::Providers;
 use rustc_middle::ty::{self, TyCtxt, TypeFoldable};
+use rustc_session::config::MirJsonPhase;
 use rustc_span::{Span, Symbol};
 
 #[macro_use]
@@ -224,6 +225,7 @@ fn mir_const<'tcx>(
     let mut body = tcx.mir_built(def).steal();
 
     rustc_middle::mir::dump_mir(tcx, None, "mir_map", &0, &body, |_, _| Ok(()));
+    dump_mir::record_mir_json(tcx, MirJsonPhase::Built, &body, None);
 
     pm::run_passes(
         tcx,
@@ -280,6 +282,10 @@ fn mir_promoted<'tcx>(
     );
 
     let promoted = promote_pass.promoted_fragments.into_inner();
+    dump_mir::record_mir_json(tcx, MirJsonPhase::Analysis, &body, None);
+    for (index, promoted_body) in promoted.iter_enumerated() {
+        dump_mir::record_mir_json(tcx, MirJsonPhase::Analysis, promoted_body, Some(index));
+    }
     (tcx.alloc_steal_mir(body), tcx.alloc_steal_promoted(promoted))
 }
 
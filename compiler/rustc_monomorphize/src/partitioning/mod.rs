@@ -97,12 +97,13 @@
 
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_data_structures::sync;
-use rustc_hir::def_id::DefIdSet;
+use rustc_hir::def_id::{CrateNum, DefIdSet, LOCAL_CRATE};
 use rustc_middle::mir::mono::MonoItem;
 use rustc_middle::mir::mono::{CodegenUnit, Linkage};
 use rustc_middle::ty::print::with_no_trimmed_paths;
 use rustc_middle::ty::query::Providers;
-use rustc_middle::ty::TyCtxt;
+use rustc_middle::ty::{InstanceDef, TyCtxt};
+use rustc_session::MonoItemInfo;
 use rustc_span::symbol::Symbol;
 
 use crate::collector::InliningMap;
@@ -404,6 +405,10 @@ fn collect_and_partition_mono_items<'tcx>(
         }
     }
 
+    if tcx.sess.opts.debugging_opts.mono_bloat_report.is_some() {
+        record_mono_bloat_stats(tcx, &items, &inlining_map);
+    }
+
     let mono_items: DefIdSet = items
         .iter()
         .filter_map(|mono_item| match *mono_item {
@@ -467,6 +472,58 @@ fn collect_and_partition_mono_items<'tcx>(
     (tcx.arena.alloc(mono_items), codegen_units)
 }
 
+/// Records the instantiations of generic definitions for `-Z mono-bloat-report`,
+/// along with the crates whose items use them.
+fn record_mono_bloat_stats<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    items: &FxHashSet<MonoItem<'tcx>>,
+    inlining_map: &InliningMap<'tcx>,
+) {
+    let mut instantiated_by: FxHashMap<MonoItem<'tcx>, FxHashSet<CrateNum>> = Default::default();
+    inlining_map.iter_accesses(|accessor, accessees| {
+        let krate = match accessor {
+            MonoItem::Fn(instance) => instance.def_id().krate,
+            MonoItem::Static(def_id) => def_id.krate,
+            MonoItem::GlobalAsm(_) => LOCAL_CRATE,
+        };
+        for &accessee in accessees {
+            // A recursive instance doesn't cause itself to be instantiated.
+            if accessee != accessor {
+                instantiated_by.entry(accessee).or_default().insert(krate);
+            }
+        }
+    });
+
+    for &item in items {
+        let MonoItem::Fn(instance) = item else { continue };
+        let is_generic = match instance.def {
+            InstanceDef::Item(def) => {
+                tcx.generics_of(tcx.typeck_root_def_id(def.did)).requires_monomorphization(tcx)
+            }
+            // Shims, such as drop glue, are generic over the type they are for.
+            _ => !instance.substs.is_empty(),
+        };
+        if !is_generic {
+            continue;
+        }
+
+        let mut crates: Vec<_> = instantiated_by
+            .get(&item)
+            .into_iter()
+            .flatten()
+            .map(|&krate| tcx.crate_name(krate).to_string())
+            .collect();
+        crates.sort();
+        tcx.sess.code_stats.record_mono_item(MonoItemInfo {
+            definition: with_no_trimmed_paths!(tcx.def_path_str(instance.def_id())),
+            instance: with_no_trimmed_paths!(instance.to_string()),
+            symbol_name: tcx.symbol_name(instance).name.to_string(),
+            mir_statements: item.size_estimate(tcx),
+            instantiated_by: crates,
+        });
+    }
+}
+
 fn codegened_and_inlined_items<'tcx>(tcx: TyCtxt<'tcx>, (): ()) -> &'tcx DefIdSet {
     let (items, cgus) = tcx.collect_and_partition_mono_items(());
     let mut visited = DefIdSet::default();
@@ -238,7 +238,7 @@ fn check_expr(&mut self, expr: &'tcx hir::Expr<'tcx>, span: Span) {
                 hir::intravisit::walk_expr(self, expr);
             }
 
-            ExprKind::Err => {
+            ExprKind::Hole | ExprKind::Err => {
                 self.items.push((ItemKind::Err, span));
             }
         }
@@ -42,10 +42,26 @@
 /// previous compilation session. This data will eventually include the results
 /// of a few selected queries (like `typeck` and `mir_optimized`) and
 /// any side effects that have been emitted during a query.
+///
+/// The cache of the current session starts with a copy of the data of the previous
+/// one, so that the results that are still valid don't have to be decoded and
+/// encoded again, followed by the results computed in the current session, which
+/// are appended to the copy. See `reusable_query_results` for when it is written
+/// from scratch instead.
 pub struct OnDiskCache<'sess> {
     // The complete cache data in serialized form.
     serialized_data: RwLock<Option<Mmap>>,
 
+    // The position in `serialized_data` of the first byte after the file header,
+    // and of the footer. The data in between can be copied as is into the cache
+    // of the current session, see `reusable_query_results`.
+    start_pos: usize,
+    footer_pos: usize,
+
+    // The query results of the previous session that the cache of the current
+    // session keeps, once `reusable_data_len` has decided to keep them.
+    reused_results: Lock<Option<Vec<ReusedQueryResult>>>,
+
     // Collects all `QuerySideEffects` created during the current compilation
     // session.
     current_side_effects: Lock<FxHashMap<DepNodeIndex, QuerySideEffects>>,
@@ -60,11 +76,17 @@ pub struct OnDiskCache<'sess> {
     // `serialized_data`.
     query_result_index: FxHashMap<SerializedDepNodeIndex, AbsoluteBytePos>,
 
+    // The tags of the query results that were copied from the cache of an earlier
+    // session, which are the indices of their dep-nodes in that session.
+    query_result_tags: FxHashMap<SerializedDepNodeIndex, SerializedDepNodeIndex>,
+
     // A map from dep-node to the position of any associated `QuerySideEffects` in
     // `serialized_data`.
     prev_side_effects_index: FxHashMap<SerializedDepNodeIndex, AbsoluteBytePos>,
 
     alloc_decoding_state: AllocDecodingState,
+    // The positions of all allocations, which are also those of the copied data.
+    interpret_alloc_index: Vec<u32>,
 
     // A map from syntax context ids to the position of their associated
     // `SyntaxContextData`. We use a `u32` instead of a `SyntaxContext`
@@ -96,6 +118,8 @@ pub struct OnDiskCache<'sess> {
 struct Footer {
     file_index_to_stable_id: FxHashMap<SourceFileIndex, EncodedSourceFileId>,
     query_result_index: EncodedDepNodeIndex,
+    // See `OnDiskCache.query_result_tags`
+    query_result_tags: FxHashMap<SerializedDepNodeIndex, SerializedDepNodeIndex>,
     side_effects_index: EncodedDepNodeIndex,
     // The location of all allocations.
     interpret_alloc_index: Vec<u32>,
@@ -108,6 +132,10 @@ struct Footer {
 
 pub type EncodedDepNodeIndex = Vec<(SerializedDepNodeIndex, AbsoluteBytePos)>;
 
+/// A query result of the previous session kept in the cache of the current session:
+/// the index of its dep-node in the current session, its position and its tag.
+type ReusedQueryResult = (SerializedDepNodeIndex, AbsoluteBytePos, SerializedDepNodeIndex);
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Encodable, Decodable)]
 struct SourceFileIndex(u32);
 
@@ -130,7 +158,7 @@ fn to_usize(self) -> usize {
 /// `CrateNum`. This way `EncodedSourceFileId` can be encoded and decoded
 /// without any additional context, i.e. with a simple `opaque::Decoder` (which
 /// is the only thing available when decoding the cache's [Footer].
-#[derive(Encodable, Decodable, Clone, Debug)]
+#[derive(Encodable, Decodable, Clone, Debug, Hash, Eq, PartialEq)]
 struct EncodedSourceFileId {
     file_name_hash: u64,
     stable_crate_id: StableCrateId,
@@ -157,7 +185,7 @@ fn new(sess: &'sess Session, data: Mmap, start_pos: usize) -> Self {
         debug_assert!(sess.opts.incremental.is_some());
 
         // Wrap in a scope so we can borrow `data`.
-        let footer: Footer = {
+        let (footer, footer_pos): (Footer, usize) = {
             let mut decoder = opaque::Decoder::new(&data, start_pos);
 
             // Decode the *position* of the footer, which can be found in the
@@ -168,18 +196,23 @@ fn new(sess: &'sess Session, data: Mmap, start_pos: usize) -> Self {
             // Decode the file footer, which contains all the lookup tables, etc.
             decoder.set_position(footer_pos);
 
-            decode_tagged(&mut decoder, TAG_FILE_FOOTER)
+            (decode_tagged(&mut decoder, TAG_FILE_FOOTER), footer_pos)
         };
 
         Self {
             serialized_data: RwLock::new(Some(data)),
+            start_pos,
+            footer_pos,
+            reused_results: Default::default(),
             file_index_to_stable_id: footer.file_index_to_stable_id,
             file_index_to_file: Default::default(),
             source_map: sess.source_map(),
             current_side_effects: Default::default(),
             query_result_index: footer.query_result_index.into_iter().collect(),
+            query_result_tags: footer.query_result_tags,
             prev_side_effects_index: footer.side_effects_index.into_iter().collect(),
-            alloc_decoding_state: AllocDecodingState::new(footer.interpret_alloc_index),
+            alloc_decoding_state: AllocDecodingState::new(footer.interpret_alloc_index.clone()),
+            interpret_alloc_index: footer.interpret_alloc_index,
             syntax_contexts: footer.syntax_contexts,
             expn_data: footer.expn_data,
             foreign_expn_data: footer.foreign_expn_data,
@@ -190,13 +223,18 @@ fn new(sess: &'sess Session, data: Mmap, start_pos: usize) -> Self {
     fn new_empty(source_map: &'sess SourceMap) -> Self {
         Self {
             serialized_data: RwLock::new(None),
+            start_pos: 0,
+            footer_pos: 0,
+            reused_results: Default::default(),
             file_index_to_stable_id: Default::default(),
             file_index_to_file: Default::default(),
             source_map,
             current_side_effects: Default::default(),
             query_result_index: Default::default(),
+            query_result_tags: Default::default(),
             prev_side_effects_index: Default::default(),
             alloc_decoding_state: AllocDecodingState::new(Vec::new()),
+            interpret_alloc_index: Vec::new(),
             syntax_contexts: FxHashMap::default(),
             expn_data: UnhashMap::default(),
             foreign_expn_data: UnhashMap::default(),
@@ -204,40 +242,64 @@ fn new_empty(source_map: &'sess SourceMap) -> Self {
         }
     }
 
-    /// Execute all cache promotions and release the serialized backing Mmap.
+    /// Release the serialized backing Mmap.
     ///
-    /// Cache promotions require invoking queries, which needs to read the serialized data.
-    /// In order to serialize the new on-disk cache, the former on-disk cache file needs to be
-    /// deleted, hence we won't be able to refer to its memmapped data.
-    fn drop_serialized_data(&self, tcx: TyCtxt<'_>) {
-        // Load everything into memory so we can write it out to the on-disk
-        // cache. The vast majority of cacheable query results should already
-        // be in memory, so this should be a cheap operation.
-        // Do this *before* we clone 'latest_foreign_def_path_hashes', since
-        // loading existing queries may cause us to create new DepNodes, which
-        // may in turn end up invoking `store_foreign_def_id_hash`
-        tcx.dep_graph.exec_cache_promotions(tcx);
-
+    /// The new on-disk cache may be a copy of the former on-disk cache file, so this is
+    /// done once it is serialized, in order to replace that file.
+    fn drop_serialized_data(&self) {
         *self.serialized_data.write() = None;
     }
 
+    fn reusable_data_len(&self, tcx: TyCtxt<'sess>) -> Option<usize> {
+        let results = tcx.dep_graph.with_ignore(|| self.reusable_query_results(tcx))?;
+        *self.reused_results.lock() = Some(results);
+        Some(self.footer_pos)
+    }
+
     fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileEncodeResult {
         // Serializing the `DepGraph` should not modify it.
         tcx.dep_graph.with_ignore(|| {
-            // Allocate `SourceFileIndex`es.
+            // The data of the previous session is only kept if the encoder already
+            // contains it, at the same position, see `reusable_data_len`.
+            let reused_results =
+                self.reused_results.lock().take().filter(|_| encoder.position() == self.footer_pos);
+            if reused_results.is_none() {
+                // Load everything into memory so we can write it out to the on-disk
+                // cache. The vast majority of cacheable query results should already
+                // be in memory, so this should be a cheap operation.
+                tcx.dep_graph.exec_cache_promotions(tcx);
+            }
+
+            // Allocate `SourceFileIndex`es. The files that copied data refers to keep
+            // their index.
             let (file_to_file_index, file_index_to_stable_id) = {
                 let files = tcx.sess.source_map().files();
                 let mut file_to_file_index =
                     FxHashMap::with_capacity_and_hasher(files.len(), Default::default());
                 let mut file_index_to_stable_id =
                     FxHashMap::with_capacity_and_hasher(files.len(), Default::default());
+                let mut stable_id_to_file_index = FxHashMap::default();
+                if reused_results.is_some() {
+                    for (&index, source_file_id) in &self.file_index_to_stable_id {
+                        file_index_to_stable_id.insert(index, source_file_id.clone());
+                        stable_id_to_file_index.insert(source_file_id.clone(), index);
+                    }
+                }
+                let mut next_index =
+                    file_index_to_stable_id.keys().map(|index| index.0 + 1).max().unwrap_or(0);
 
-                for (index, file) in files.iter().enumerate() {
-                    let index = SourceFileIndex(index as u32);
+                for file in files.iter() {
                     let file_ptr: *const SourceFile = &**file as *const _;
-                    file_to_file_index.insert(file_ptr, index);
                     let source_file_id = EncodedSourceFileId::new(tcx, &file);
-                    file_index_to_stable_id.insert(index, source_file_id);
+                    let index = *stable_id_to_file_index.entry(source_file_id).or_insert_with_key(
+                        |source_file_id| {
+                            let index = SourceFileIndex(next_index);
+                            next_index += 1;
+                            file_index_to_stable_id.insert(index, source_file_id.clone());
+                            index
+                        },
+                    );
+                    file_to_file_index.insert(file_ptr, index);
                 }
 
                 (file_to_file_index, file_index_to_stable_id)
@@ -251,14 +313,43 @@ fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileE
                 type_shorthands: Default::default(),
                 predicate_shorthands: Default::default(),
                 interpret_allocs: Default::default(),
+                interpret_alloc_offset: 0,
                 source_map: CachingSourceMapView::new(tcx.sess.source_map()),
                 file_to_file_index,
                 hygiene_context: &hygiene_encode_context,
+                syntax_context_ids: Default::default(),
+                syntax_context_offset: 0,
+                reused_results: Default::default(),
             };
 
-            // Encode query results.
             let mut query_result_index = EncodedDepNodeIndex::new();
+            let mut query_result_tags = FxHashMap::default();
+            let mut interpret_alloc_index = Vec::new();
+            let mut syntax_contexts = FxHashMap::default();
+            let mut expn_data = UnhashMap::default();
+            let mut foreign_expn_data = UnhashMap::default();
 
+            let reused_count = reused_results.as_ref().map(|results| results.len());
+            if let Some(reused_results) = reused_results {
+                // The data of the previous session is already in the encoder. The
+                // allocations and syntax contexts encoded below are numbered after
+                // those of that data.
+                for (dep_node_index, pos, tag) in reused_results {
+                    query_result_index.push((dep_node_index, pos));
+                    if tag != dep_node_index {
+                        query_result_tags.insert(dep_node_index, tag);
+                    }
+                    encoder.reused_results.insert(dep_node_index);
+                }
+                interpret_alloc_index = self.interpret_alloc_index.clone();
+                encoder.interpret_alloc_offset = interpret_alloc_index.len();
+                syntax_contexts = self.syntax_contexts.clone();
+                encoder.syntax_context_offset = syntax_contexts.keys().copied().max().unwrap_or(0);
+                expn_data = self.expn_data.clone();
+                foreign_expn_data = self.foreign_expn_data.clone();
+            }
+
+            // Encode query results.
             tcx.sess.time("encode_query_results", || -> FileEncodeResult {
                 let enc = &mut encoder;
                 let qri = &mut query_result_index;
@@ -281,31 +372,23 @@ fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileE
                 )
                 .collect::<Result<_, _>>()?;
 
-            let interpret_alloc_index = {
-                let mut interpret_alloc_index = Vec::new();
-                let mut n = 0;
-                loop {
-                    let new_n = encoder.interpret_allocs.len();
-                    // If we have found new IDs, serialize those too.
-                    if n == new_n {
-                        // Otherwise, abort.
-                        break;
-                    }
-                    interpret_alloc_index.reserve(new_n - n);
-                    for idx in n..new_n {
-                        let id = encoder.interpret_allocs[idx];
-                        let pos = encoder.position() as u32;
-                        interpret_alloc_index.push(pos);
-                        interpret::specialized_encode_alloc_id(&mut encoder, tcx, id)?;
-                    }
-                    n = new_n;
+            let mut n = 0;
+            loop {
+                let new_n = encoder.interpret_allocs.len();
+                // If we have found new IDs, serialize those too.
+                if n == new_n {
+                    // Otherwise, abort.
+                    break;
                 }
-                interpret_alloc_index
-            };
-
-            let mut syntax_contexts = FxHashMap::default();
-            let mut expn_data = UnhashMap::default();
-            let mut foreign_expn_data = UnhashMap::default();
+                interpret_alloc_index.reserve(new_n - n);
+                for idx in n..new_n {
+                    let id = encoder.interpret_allocs[idx];
+                    let pos = encoder.position() as u32;
+                    interpret_alloc_index.push(pos);
+                    interpret::specialized_encode_alloc_id(&mut encoder, tcx, id)?;
+                }
+                n = new_n;
+            }
 
             // Encode all hygiene data (`SyntaxContextData` and `ExpnData`) from the current
             // session.
@@ -315,7 +398,7 @@ fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileE
                 |encoder, index, ctxt_data| -> FileEncodeResult {
                     let pos = AbsoluteBytePos::new(encoder.position());
                     encoder.encode_tagged(TAG_SYNTAX_CONTEXT, ctxt_data)?;
-                    syntax_contexts.insert(index, pos);
+                    syntax_contexts.insert(encoder.syntax_context_id(index), pos);
                     Ok(())
                 },
                 |encoder, expn_id, data, hash| -> FileEncodeResult {
@@ -330,6 +413,21 @@ fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileE
                 },
             )?;
 
+            if tcx.sess.opts.debugging_opts.incremental_info {
+                match reused_count {
+                    Some(reused_count) => eprintln!(
+                        "[incremental] appended {} query results to the query cache, \
+                         {} were carried over from the previous session",
+                        query_result_index.len() - reused_count,
+                        reused_count
+                    ),
+                    None if self.footer_pos > 0 => {
+                        eprintln!("[incremental] rewrote the query cache of the previous session")
+                    }
+                    None => {}
+                }
+            }
+
             // `Encode the file footer.
             let footer_pos = encoder.position() as u64;
             encoder.encode_tagged(
@@ -337,6 +435,7 @@ fn serialize<'tcx>(&self, tcx: TyCtxt<'tcx>, encoder: &mut FileEncoder) -> FileE
                 &Footer {
                     file_index_to_stable_id,
                     query_result_index,
+                    query_result_tags,
                     side_effects_index,
                     interpret_alloc_index,
                     syntax_contexts,
@@ -362,6 +461,73 @@ pub fn as_dyn(&self) -> &dyn rustc_middle::ty::OnDiskCache<'sess> {
         self as _
     }
 
+    /// Returns the query results of the previous session that are still valid, i.e.
+    /// whose dep-nodes were marked green, with the index of their dep-node in the
+    /// current session, their position and their tag, if the data of the previous
+    /// session is to be kept in the cache of the current session.
+    ///
+    /// Keeping the data avoids decoding and encoding these results again, but keeps
+    /// the results that are no longer valid in the file, along with the allocations
+    /// and hygiene data they refer to. Once those take up more than half of the data,
+    /// the cache is written from scratch instead.
+    fn reusable_query_results(&self, tcx: TyCtxt<'_>) -> Option<Vec<ReusedQueryResult>> {
+        if self.serialized_data.read().is_none() {
+            return None;
+        }
+
+        // Each entry of the data ends where the next one starts.
+        let mut positions: Vec<usize> = self
+            .query_result_index
+            .values()
+            .chain(self.prev_side_effects_index.values())
+            .chain(self.syntax_contexts.values())
+            .chain(self.expn_data.values())
+            .map(|pos| pos.to_usize())
+            .chain(self.interpret_alloc_index.iter().map(|&pos| pos as usize))
+            .chain([self.footer_pos])
+            .collect();
+        positions.sort_unstable();
+        let len = |pos: AbsoluteBytePos| {
+            positions[positions.partition_point(|&next| next <= pos.to_usize())] - pos.to_usize()
+        };
+
+        // The side effects are encoded again, like the results of the current session.
+        let mut invalid_len: usize =
+            self.prev_side_effects_index.values().map(|&pos| len(pos)).sum();
+        let mut results_len = 0;
+        let mut invalid_results_len = 0;
+        let mut results = Vec::new();
+        for (&prev_index, &pos) in &self.query_result_index {
+            results_len += len(pos);
+            match tcx.dep_graph.green_index_of_prev_node(prev_index) {
+                Some(index) => {
+                    let tag =
+                        self.query_result_tags.get(&prev_index).copied().unwrap_or(prev_index);
+                    results.push((SerializedDepNodeIndex::new(index.index()), pos, tag));
+                }
+                None => invalid_results_len += len(pos),
+            }
+        }
+        invalid_len += invalid_results_len;
+
+        // Which allocations and hygiene data only the invalid results refer to is not
+        // known without decoding them, so they are assumed to be shared out in the
+        // same proportion as the results.
+        let aux_len: usize = self
+            .syntax_contexts
+            .values()
+            .chain(self.expn_data.values())
+            .map(|&pos| len(pos))
+            .chain(self.interpret_alloc_index.iter().map(|&pos| len(AbsoluteBytePos(pos))))
+            .sum();
+        invalid_len += (aux_len * invalid_results_len).checked_div(results_len).unwrap_or(0);
+
+        if results.is_empty() || invalid_len * 2 > self.footer_pos - self.start_pos {
+            return None;
+        }
+        Some(results)
+    }
+
     /// Loads a `QuerySideEffects` created during the previous compilation session.
     pub fn load_side_effects(
         &self,
@@ -395,7 +561,11 @@ pub fn try_load_query_result<'tcx, T>(
     where
         T: for<'a> Decodable<CacheDecoder<'a, 'tcx>>,
     {
-        self.load_indexed(tcx, dep_node_index, &self.query_result_index)
+        let pos = self.query_result_index.get(&dep_node_index).cloned()?;
+        // Results copied from the cache of an earlier session keep their tag.
+        let tag = self.query_result_tags.get(&dep_node_index).copied().unwrap_or(dep_node_index);
+
+        self.with_decoder(tcx, pos, |decoder| Some(decode_tagged(decoder, tag)))
     }
 
     /// Stores side effect emitted during computation of an anonymous query.
@@ -829,9 +999,17 @@ pub struct CacheEncoder<'a, 'tcx, E: OpaqueEncoder> {
     type_shorthands: FxHashMap<Ty<'tcx>, usize>,
     predicate_shorthands: FxHashMap<ty::PredicateKind<'tcx>, usize>,
     interpret_allocs: FxIndexSet<interpret::AllocId>,
+    // The number of allocations in the copied data, which come before `interpret_allocs`.
+    interpret_alloc_offset: usize,
     source_map: CachingSourceMapView<'tcx>,
     file_to_file_index: FxHashMap<*const SourceFile, SourceFileIndex>,
     hygiene_context: &'a HygieneEncodeContext,
+    // Maps the raw ids of `SyntaxContext`s to the ids they are encoded with, which come
+    // after `syntax_context_offset`, the largest id in the copied data.
+    syntax_context_ids: FxHashMap<u32, u32>,
+    syntax_context_offset: u32,
+    // The dep-nodes of the query results in the copied data, which aren't encoded again.
+    reused_results: FxHashSet<SerializedDepNodeIndex>,
 }
 
 impl<'a, 'tcx, E> CacheEncoder<'a, 'tcx, E>
@@ -842,6 +1020,16 @@ fn source_file_index(&mut self, source_file: Lrc<SourceFile>) -> SourceFileIndex
         self.file_to_file_index[&(&*source_file as *const SourceFile)]
     }
 
+    /// Returns the id the `SyntaxContext` with the raw id `raw_id` is encoded with.
+    fn syntax_context_id(&mut self, raw_id: u32) -> u32 {
+        // The root context is decoded without looking up its data.
+        if raw_id == 0 {
+            return 0;
+        }
+        let next_id = self.syntax_context_offset + self.syntax_context_ids.len() as u32 + 1;
+        *self.syntax_context_ids.entry(raw_id).or_insert(next_id)
+    }
+
     /// Encode something with additional information that allows to do some
     /// sanity checks when decoding the data again. This method will first
     /// encode the specified tag, then the given value, then the number of
@@ -867,7 +1055,9 @@ impl<'a, 'tcx, E> Encodable<CacheEncoder<'a, 'tcx, E>> for SyntaxContext
     E: 'a + OpaqueEncoder,
 {
     fn encode(&self, s: &mut CacheEncoder<'a, 'tcx, E>) -> Result<(), E::Error> {
-        rustc_span::hygiene::raw_encode_syntax_context(*self, s.hygiene_context, s)
+        rustc_span::hygiene::remapped_encode_syntax_context(*self, s.hygiene_context, s, |s, id| {
+            s.syntax_context_id(id)
+        })
     }
 }
 
@@ -947,7 +1137,7 @@ fn predicate_shorthands(&mut self) -> &mut FxHashMap<ty::PredicateKind<'tcx>, us
     fn encode_alloc_id(&mut self, alloc_id: &interpret::AllocId) -> Result<(), Self::Error> {
         let (index, _) = self.interpret_allocs.insert_full(*alloc_id);
 
-        index.encode(self)
+        (self.interpret_alloc_offset + index).encode(self)
     }
 }
 
@@ -1056,6 +1246,9 @@ pub fn encode_query_results<'a, 'tcx, CTX, Q>(
         }
         if Q::cache_on_disk(*tcx.dep_context(), &key) {
             let dep_node = SerializedDepNodeIndex::new(dep_node.index());
+            if encoder.reused_results.contains(&dep_node) {
+                return;
+            }
 
             // Record position of the cache entry.
             query_result_index.push((dep_node, AbsoluteBytePos::new(encoder.encoder.position())));
@@ -802,6 +802,39 @@ pub fn is_green(&self, dep_node: &DepNode<K>) -> bool {
         self.node_color(dep_node).map_or(false, |c| c.is_green())
     }
 
+    /// Returns the chain of dependencies that kept `dep_node`, which existed in the
+    /// previous compilation session, from being marked green. Each node of the chain
+    /// is followed by its first dependency that isn't green, which is where
+    /// `try_mark_previous_green` stopped. The chain ends at a node for which
+    /// `is_input` holds, at a node whose dependencies are all green, or at a
+    /// dependency without a color, which couldn't be forced.
+    ///
+    /// Returns `None` if `dep_node` didn't exist in the previous session.
+    pub fn red_dependency_chain(
+        &self,
+        dep_node: &DepNode<K>,
+        is_input: impl Fn(&DepNode<K>) -> bool,
+    ) -> Option<Vec<DepNode<K>>> {
+        let data = self.data.as_ref()?;
+        let mut prev_index = data.previous.node_to_index_opt(dep_node)?;
+        let mut chain = vec![*dep_node];
+        while !is_input(&chain[chain.len() - 1]) {
+            let Some(dep_index) =
+                data.previous.edge_targets_from(prev_index).iter().copied().find(|&dep_index| {
+                    !matches!(data.colors.get(dep_index), Some(DepNodeColor::Green(_)))
+                })
+            else {
+                break;
+            };
+            chain.push(data.previous.index_to_node(dep_index));
+            if data.colors.get(dep_index).is_none() {
+                break;
+            }
+            prev_index = dep_index;
+        }
+        Some(chain)
+    }
+
     // This method loads all on-disk cacheable query results into memory, so
     // they can be written out to the new cache file again. Most query results
     // will already be in memory but in the case where we marked something as
@@ -829,6 +862,22 @@ pub fn exec_cache_promotions<Ctxt: DepContext<DepKind = K>>(&self, tcx: Ctxt) {
         }
     }
 
+    /// Returns the index in the current session of the node `prev_index` of the
+    /// previous session, if it was marked green.
+    pub fn green_index_of_prev_node(
+        &self,
+        prev_index: SerializedDepNodeIndex,
+    ) -> Option<DepNodeIndex> {
+        let data = self.data.as_ref()?;
+        if prev_index.index() >= data.colors.values.len() {
+            return None;
+        }
+        match data.colors.get(prev_index)? {
+            DepNodeColor::Green(index) => Some(index),
+            DepNodeColor::Red => None,
+        }
+    }
+
     pub fn print_incremental_info(&self) {
         if let Some(data) = &self.data {
             data.current.encoder.borrow().print_incremental_info(
@@ -7,6 +7,7 @@
 
 mod dump_visitor;
 mod dumper;
+mod lsif;
 #[macro_use]
 mod span_utils;
 mod sig;
@@ -24,7 +25,7 @@
 use rustc_middle::middle::privacy::AccessLevels;
 use rustc_middle::ty::{self, print::with_no_trimmed_paths, DefIdTree, TyCtxt};
 use rustc_middle::{bug, span_bug};
-use rustc_session::config::{CrateType, Input, OutputType};
+use rustc_session::config::{CrateType, Input, OutputType, SaveAnalysisFormat};
 use rustc_session::cstore::ExternCrate;
 use rustc_session::output::{filename_for_metadata, out_filename};
 use rustc_span::symbol::Ident;
@@ -73,9 +74,12 @@ fn typeck_results(&self) -> &'tcx ty::TypeckResults<'tcx> {
         self.maybe_typeck_results.expect("`SaveContext::typeck_results` called outside of body")
     }
 
-    fn span_from_span(&self, span: Span) -> SpanData {
+    fn span_from_span(&self, mut span: Span) -> SpanData {
         use rls_span::{Column, Row};
 
+        if self.span_utils.indexes_macro_expansions() {
+            span = span.source_callsite();
+        }
         let sm = self.tcx.sess.source_map();
         let start = sm.lookup_char_pos(span.lo());
         let end = sm.lookup_char_pos(span.hi());
@@ -919,7 +923,10 @@ fn output_file(&self, ctx: &SaveContext<'_>) -> (BufWriter<File>, PathBuf) {
                 let mut out_name = if executable { String::new() } else { "lib".to_owned() };
                 out_name.push_str(&self.cratename);
                 out_name.push_str(&sess.opts.cg.extra_filename);
-                out_name.push_str(".json");
+                out_name.push_str(match sess.opts.debugging_opts.save_analysis_format {
+                    SaveAnalysisFormat::Json => ".json",
+                    SaveAnalysisFormat::Lsif => ".lsif",
+                });
                 root_path.push(&out_name);
 
                 root_path
@@ -940,8 +947,17 @@ impl SaveHandler for DumpHandler<'_> {
     fn save(&mut self, save_ctxt: &SaveContext<'_>, analysis: &Analysis) {
         let sess = &save_ctxt.tcx.sess;
         let (output, file_name) = self.output_file(&save_ctxt);
-        if let Err(e) = serde_json::to_writer(output, &analysis) {
-            error!("Can't serialize save-analysis: {:?}", e);
+        match sess.opts.debugging_opts.save_analysis_format {
+            SaveAnalysisFormat::Json => {
+                if let Err(e) = serde_json::to_writer(output, &analysis) {
+                    error!("Can't serialize save-analysis: {:?}", e);
+                }
+            }
+            SaveAnalysisFormat::Lsif => {
+                if let Err(e) = lsif::write_lsif(save_ctxt, analysis, output) {
+                    error!("Can't write save-analysis as LSIF: {:?}", e);
+                }
+            }
         }
 
         if sess.opts.json_artifact_notifications {
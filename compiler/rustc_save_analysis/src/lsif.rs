@@ -0,0 +1,385 @@
+//! Writes the results of save-analysis as an [LSIF] index, for code intelligence
+//! tools such as code search (`-Z save-analysis-format=lsif`).
+//!
+//! Each definition, and each item of another crate that is referred to, gets a
+//! result set to which the ranges of its definition, references and implementations
+//! are linked. Items other than local variables get a moniker identified by the
+//! name of their crate and their path in it, and attached to the package of the
+//! crate. Cargo derives the stable crate id from the name, version and source of
+//! the package, so it is used as the version of the package: the monikers exported
+//! by the index of a crate then match those imported by the indexes of its
+//! dependents.
+//!
+//! Definitions and references in macro expansions are located at the outermost
+//! macro invocation. When several of them share a range, the first one is kept.
+//!
+//! [LSIF]: https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/
+
+use crate::SaveContext;
+
+use rls_data::{Analysis, DefKind, Id, RelationKind, SpanData};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex, LOCAL_CRATE};
+use rustc_span::{BytePos, Pos};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+const LSIF_VERSION: &str = "0.6.0";
+
+type VertexId = u64;
+
+struct Document {
+    id: VertexId,
+    ranges: Vec<VertexId>,
+}
+
+/// The ranges linked to a result set, with the document they are in.
+struct ResultSet {
+    id: VertexId,
+    definitions: Vec<(VertexId, VertexId)>,
+    references: Vec<(VertexId, VertexId)>,
+    implementations: Vec<(VertexId, VertexId)>,
+}
+
+pub fn write_lsif(
+    save_ctxt: &SaveContext<'_>,
+    analysis: &Analysis,
+    out: impl Write,
+) -> io::Result<()> {
+    let mut writer = LsifWriter {
+        save_ctxt,
+        out,
+        next_id: 0,
+        documents: Vec::new(),
+        document_indices: FxHashMap::default(),
+        ranges: FxHashMap::default(),
+        result_sets: Vec::new(),
+        result_set_indices: FxHashMap::default(),
+        packages: FxHashMap::default(),
+    };
+    writer.write(analysis)?;
+    writer.out.flush()
+}
+
+struct LsifWriter<'a, 'tcx, W> {
+    save_ctxt: &'a SaveContext<'tcx>,
+    out: W,
+    next_id: VertexId,
+    documents: Vec<Document>,
+    document_indices: FxHashMap<PathBuf, usize>,
+    /// The document and range vertices of the spans, keyed on their file and
+    /// byte offsets.
+    ranges: FxHashMap<(PathBuf, u32, u32), (VertexId, VertexId)>,
+    result_sets: Vec<ResultSet>,
+    result_set_indices: FxHashMap<(u32, u32), usize>,
+    packages: FxHashMap<CrateNum, VertexId>,
+}
+
+impl<W: Write> LsifWriter<'_, '_, W> {
+    fn write(&mut self, analysis: &Analysis) -> io::Result<()> {
+        let tcx = self.save_ctxt.tcx;
+        let project_root = tcx.sess.opts.working_dir.remapped_path_if_available().to_path_buf();
+        self.vertex(
+            "metaData",
+            json!({
+                "version": LSIF_VERSION,
+                "projectRoot": file_uri(&project_root),
+                "positionEncoding": "utf-16",
+                "toolInfo": {
+                    "name": "rustc",
+                    "version": option_env!("CFG_VERSION").unwrap_or("unknown version"),
+                },
+            }),
+        )?;
+        let project = self.vertex(
+            "project",
+            json!({ "kind": "rust", "name": tcx.crate_name(LOCAL_CRATE).as_str() }),
+        )?;
+
+        for def in &analysis.defs {
+            let index = self.result_set(def.id)?;
+            let result_set = self.result_sets[index].id;
+            let (document, range, is_new) = self.range(&def.span)?;
+            if is_new {
+                self.edge("next", range, result_set)?;
+            }
+            self.result_sets[index].definitions.push((document, range));
+
+            let mut contents = Vec::new();
+            if !def.value.is_empty() {
+                contents.push(json!({ "language": "rust", "value": def.value }));
+            }
+            if !def.docs.is_empty() {
+                contents.push(json!(def.docs));
+            }
+            if !contents.is_empty() {
+                let hover =
+                    self.vertex("hoverResult", json!({ "result": { "contents": contents } }))?;
+                self.edge("textDocument/hover", result_set, hover)?;
+            }
+
+            // Local variables don't have a `DefId`.
+            if def.kind != DefKind::Local {
+                self.moniker(result_set, def_id(def.id), "export")?;
+            }
+        }
+
+        let references =
+            analysis.refs.iter().map(|reference| (reference.ref_id, &reference.span)).chain(
+                analysis.imports.iter().filter_map(|import| Some((import.ref_id?, &import.span))),
+            );
+        for (ref_id, span) in references {
+            if ref_id.krate == u32::MAX {
+                continue;
+            }
+            let (document, range, is_new) = self.range(span)?;
+            if !is_new {
+                continue;
+            }
+            let index = self.result_set(ref_id)?;
+            self.edge("next", range, self.result_sets[index].id)?;
+            self.result_sets[index].references.push((document, range));
+        }
+
+        for relation in &analysis.relations {
+            if let RelationKind::Impl { .. } = relation.kind {
+                if relation.to.krate == u32::MAX {
+                    continue;
+                }
+                let (document, range, _) = self.range(&relation.span)?;
+                let index = self.result_set(relation.to)?;
+                self.result_sets[index].implementations.push((document, range));
+            }
+        }
+
+        for index in 0..self.result_sets.len() {
+            let result_set = self.result_sets[index].id;
+            let definitions = mem::take(&mut self.result_sets[index].definitions);
+            let references = mem::take(&mut self.result_sets[index].references);
+            let implementations = mem::take(&mut self.result_sets[index].implementations);
+            if !definitions.is_empty() {
+                let result = self.vertex("definitionResult", json!({}))?;
+                self.edge("textDocument/definition", result_set, result)?;
+                self.items(result, &definitions, None)?;
+            }
+            if !definitions.is_empty() || !references.is_empty() {
+                let result = self.vertex("referenceResult", json!({}))?;
+                self.edge("textDocument/references", result_set, result)?;
+                self.items(result, &definitions, Some("definitions"))?;
+                self.items(result, &references, Some("references"))?;
+            }
+            if !implementations.is_empty() {
+                let result = self.vertex("implementationResult", json!({}))?;
+                self.edge("textDocument/implementation", result_set, result)?;
+                self.items(result, &implementations, None)?;
+            }
+        }
+
+        let mut documents = Vec::new();
+        for index in 0..self.documents.len() {
+            let document = self.documents[index].id;
+            let ranges = mem::take(&mut self.documents[index].ranges);
+            self.edges("contains", document, &ranges, json!({}))?;
+            documents.push(document);
+        }
+        self.edges("contains", project, &documents, json!({}))
+    }
+
+    fn emit(&mut self, mut element: Value) -> io::Result<VertexId> {
+        self.next_id += 1;
+        element["id"] = json!(self.next_id);
+        serde_json::to_writer(&mut self.out, &element)?;
+        self.out.write_all(b"\n")?;
+        Ok(self.next_id)
+    }
+
+    fn vertex(&mut self, label: &str, mut fields: Value) -> io::Result<VertexId> {
+        fields["type"] = json!("vertex");
+        fields["label"] = json!(label);
+        self.emit(fields)
+    }
+
+    fn edge(&mut self, label: &str, out_v: VertexId, in_v: VertexId) -> io::Result<()> {
+        self.emit(json!({ "type": "edge", "label": label, "outV": out_v, "inV": in_v }))?;
+        Ok(())
+    }
+
+    fn edges(
+        &mut self,
+        label: &str,
+        out_v: VertexId,
+        in_vs: &[VertexId],
+        mut fields: Value,
+    ) -> io::Result<()> {
+        if in_vs.is_empty() {
+            return Ok(());
+        }
+        fields["type"] = json!("edge");
+        fields["label"] = json!(label);
+        fields["outV"] = json!(out_v);
+        fields["inVs"] = json!(in_vs);
+        self.emit(fields)?;
+        Ok(())
+    }
+
+    /// Links `result` to `ranges`, with an `item` edge per document.
+    fn items(
+        &mut self,
+        result: VertexId,
+        ranges: &[(VertexId, VertexId)],
+        property: Option<&str>,
+    ) -> io::Result<()> {
+        let mut by_document: BTreeMap<VertexId, Vec<VertexId>> = BTreeMap::new();
+        for &(document, range) in ranges {
+            by_document.entry(document).or_default().push(range);
+        }
+        for (document, ranges) in by_document {
+            let mut fields = json!({ "document": document });
+            if let Some(property) = property {
+                fields["property"] = json!(property);
+            }
+            self.edges("item", result, &ranges, fields)?;
+        }
+        Ok(())
+    }
+
+    fn document(&mut self, file_name: &Path) -> io::Result<usize> {
+        if let Some(&index) = self.document_indices.get(file_name) {
+            return Ok(index);
+        }
+        let working_dir = &self.save_ctxt.tcx.sess.opts.working_dir;
+        let path = working_dir.remapped_path_if_available().join(file_name);
+        let id =
+            self.vertex("document", json!({ "uri": file_uri(&path), "languageId": "rust" }))?;
+        self.documents.push(Document { id, ranges: Vec::new() });
+        self.document_indices.insert(file_name.to_path_buf(), self.documents.len() - 1);
+        Ok(self.documents.len() - 1)
+    }
+
+    /// Returns the document and range vertices of `span`, and whether the range
+    /// is new.
+    fn range(&mut self, span: &SpanData) -> io::Result<(VertexId, VertexId, bool)> {
+        let key = (span.file_name.clone(), span.byte_start, span.byte_end);
+        if let Some(&(document, range)) = self.ranges.get(&key) {
+            return Ok((document, range, false));
+        }
+        let document = self.document(&span.file_name)?;
+        let character_start = self.character(span.byte_start, span.column_start.zero_indexed().0);
+        let character_end = self.character(span.byte_end, span.column_end.zero_indexed().0);
+        let range = self.vertex(
+            "range",
+            json!({
+                "start": {
+                    "line": span.line_start.zero_indexed().0,
+                    "character": character_start,
+                },
+                "end": {
+                    "line": span.line_end.zero_indexed().0,
+                    "character": character_end,
+                },
+            }),
+        )?;
+        self.documents[document].ranges.push(range);
+        let document = self.documents[document].id;
+        self.ranges.insert(key, (document, range));
+        Ok((document, range, true))
+    }
+
+    /// The column of the position `pos` of the source map in UTF-16 code units, as
+    /// declared by the `positionEncoding` of the index. Falls back to `column`, in
+    /// characters, if the source of the file isn't available.
+    fn character(&self, pos: u32, column: u32) -> u32 {
+        let pos = BytePos(pos);
+        let file = self.save_ctxt.tcx.sess.source_map().lookup_source_file(pos);
+        let Some(src) = &file.src else {
+            return column;
+        };
+        let line_start = (file.line_begin_pos(pos) - file.start_pos).to_usize();
+        let pos = (pos - file.start_pos).to_usize();
+        src.get(line_start..pos).map_or(column, |line| line.encode_utf16().count() as u32)
+    }
+
+    /// Returns the index of the result set of `id`, creating it the first time,
+    /// with an import moniker if it is an item of another crate.
+    fn result_set(&mut self, id: Id) -> io::Result<usize> {
+        if let Some(&index) = self.result_set_indices.get(&(id.krate, id.index)) {
+            return Ok(index);
+        }
+        let result_set = self.vertex("resultSet", json!({}))?;
+        self.result_sets.push(ResultSet {
+            id: result_set,
+            definitions: Vec::new(),
+            references: Vec::new(),
+            implementations: Vec::new(),
+        });
+        self.result_set_indices.insert((id.krate, id.index), self.result_sets.len() - 1);
+        if id.krate != LOCAL_CRATE.as_u32() {
+            self.moniker(result_set, def_id(id), "import")?;
+        }
+        Ok(self.result_sets.len() - 1)
+    }
+
+    fn moniker(&mut self, result_set: VertexId, def_id: DefId, kind: &str) -> io::Result<()> {
+        let tcx = self.save_ctxt.tcx;
+        let identifier = format!(
+            "{}{}",
+            tcx.crate_name(def_id.krate),
+            tcx.def_path(def_id).to_string_no_crate_verbose()
+        );
+        let moniker = self.vertex(
+            "moniker",
+            json!({
+                "scheme": "rustc",
+                "identifier": identifier,
+                "unique": "scheme",
+                "kind": kind,
+            }),
+        )?;
+        self.edge("moniker", result_set, moniker)?;
+        let package = self.package(def_id.krate)?;
+        self.edge("packageInformation", moniker, package)
+    }
+
+    fn package(&mut self, krate: CrateNum) -> io::Result<VertexId> {
+        if let Some(&package) = self.packages.get(&krate) {
+            return Ok(package);
+        }
+        let tcx = self.save_ctxt.tcx;
+        let stable_crate_id = tcx.def_path_hash(krate.as_def_id()).stable_crate_id();
+        let package = self.vertex(
+            "packageInformation",
+            json!({
+                "name": tcx.crate_name(krate).as_str(),
+                "manager": "cargo",
+                "version": format!("{:016x}", stable_crate_id.to_u64()),
+            }),
+        )?;
+        self.packages.insert(krate, package);
+        Ok(package)
+    }
+}
+
+fn def_id(id: Id) -> DefId {
+    DefId { krate: CrateNum::from_u32(id.krate), index: DefIndex::from_u32(id.index) }
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            b'\\' => uri.push('/'),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -1,6 +1,6 @@
-use crate::generated_code;
 use rustc_data_structures::sync::Lrc;
 use rustc_lexer::{tokenize, TokenKind};
+use rustc_session::config::SaveAnalysisFormat;
 use rustc_session::Session;
 use rustc_span::*;
 
@@ -78,15 +78,28 @@ pub fn sub_span_of_star(&self, mut span: Span) -> Option<Span> {
         Some(span.with_lo(lo).with_hi(hi))
     }
 
+    /// Whether the definitions and references in macro expansions are kept, at
+    /// the outermost macro invocation. Code intelligence indexes keep them so
+    /// that the items used by macros can be found from their invocations.
+    pub fn indexes_macro_expansions(&self) -> bool {
+        self.sess.opts.debugging_opts.save_analysis_format == SaveAnalysisFormat::Lsif
+    }
+
     /// Return true if the span is generated code, and
     /// it is not a subspan of the root callsite.
     ///
     /// Used to filter out spans of minimal value,
     /// such as references to macro internal variables.
-    pub fn filter_generated(&self, span: Span) -> bool {
-        if generated_code(span) {
+    pub fn filter_generated(&self, mut span: Span) -> bool {
+        if span.is_dummy() {
             return true;
         }
+        if span.from_expansion() {
+            if !self.indexes_macro_expansions() {
+                return true;
+            }
+            span = span.source_callsite();
+        }
 
         //If the span comes from a fake source_file, filter it.
         !self.sess.source_map().lookup_char_pos(span.lo()).file.is_real_file()
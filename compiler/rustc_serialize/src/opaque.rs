@@ -1,8 +1,8 @@
 use crate::leb128::{self, max_leb128_len};
 use crate::serialize::{self, Decoder as _, Encoder as _};
 use std::convert::TryInto;
-use std::fs::File;
-use std::io::{self, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
 use std::mem::MaybeUninit;
 use std::path::Path;
 use std::ptr;
@@ -210,6 +210,24 @@ pub fn with_capacity<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Sel
         Ok(FileEncoder { buf: Box::new_uninit_slice(capacity), buffered: 0, flushed: 0, file })
     }
 
+    /// Opens the existing file at `path` to write after its first `len` bytes,
+    /// truncating it to that length. Positions still count from the start of the
+    /// file.
+    pub fn append<P: AsRef<Path>>(path: P, len: usize) -> io::Result<Self> {
+        const DEFAULT_BUF_SIZE: usize = 8192;
+
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(len as u64)?;
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(FileEncoder {
+            buf: Box::new_uninit_slice(DEFAULT_BUF_SIZE),
+            buffered: 0,
+            flushed: len,
+            file,
+        })
+    }
+
     #[inline]
     pub fn position(&self) -> usize {
         // Tracking position this way instead of having a `self.position` field
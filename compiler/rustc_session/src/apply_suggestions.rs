@@ -0,0 +1,451 @@
+//! Applying the machine-applicable suggestions of a compilation to the source
+//! files, or printing them as a unified diff (`-Z apply-suggestions`).
+//!
+//! Suggestions are applied whole or not at all, even when they change several
+//! files. When some of them overlap, the one whose edits come first in the file
+//! wins, so the outcome doesn't depend on the order the diagnostics were emitted
+//! in, and the others can be applied by compiling again.
+
+use crate::config::ApplySuggestions;
+use crate::Session;
+use rustc_errors::{pluralize, CodeSuggestion};
+use rustc_span::{FileName, SourceFile, Span};
+
+use rustc_data_structures::sync::Lrc;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// The number of unchanged lines around each change in the diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// The replacement of a range of bytes in the original contents of a file.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Edit {
+    lo: usize,
+    hi: usize,
+    snippet: String,
+}
+
+impl Edit {
+    fn overlaps(&self, other: &Edit) -> bool {
+        // Two insertions at the same position conflict, since the text they
+        // insert would end up in an arbitrary order.
+        (self.lo < other.hi && other.lo < self.hi)
+            || (self.lo == self.hi && other.lo == other.hi && self.lo == other.lo)
+    }
+}
+
+/// The edits of the files to be changed, with the source files they were
+/// compiled from.
+#[derive(Default)]
+struct FileEdits {
+    files: BTreeMap<PathBuf, (Lrc<SourceFile>, Vec<Edit>)>,
+}
+
+impl FileEdits {
+    fn overlaps(&self, edits: &[(PathBuf, Lrc<SourceFile>, Edit)]) -> bool {
+        edits.iter().any(|(path, _, edit)| {
+            self.files
+                .get(path)
+                .map_or(false, |(_, accepted)| accepted.iter().any(|other| other.overlaps(edit)))
+        })
+    }
+
+    fn add(&mut self, edits: &[(PathBuf, Lrc<SourceFile>, Edit)]) {
+        for (path, file, edit) in edits {
+            let (_, accepted) =
+                self.files.entry(path.clone()).or_insert_with(|| (file.clone(), Vec::new()));
+            accepted.push(edit.clone());
+        }
+    }
+}
+
+pub(crate) fn apply_suggestions(sess: &Session, mode: &ApplySuggestions) {
+    let suggestions = sess.diagnostic().take_machine_applicable_suggestions();
+    if suggestions.is_empty() {
+        return;
+    }
+    if sess.has_errors().is_some() && *mode != ApplySuggestions::Diff {
+        let n = suggestions.len();
+        sess.note_without_error(&format!(
+            "{n} suggestion{s} {were} not applied because of the errors, \
+             use `-Z apply-suggestions=diff` to see {them}",
+            s = pluralize!(n),
+            were = if n == 1 { "was" } else { "were" },
+            them = if n == 1 { "it" } else { "them" },
+        ));
+        return;
+    }
+    let filter = match mode {
+        ApplySuggestions::Rewrite(Some(filter)) => Some(canonicalize(filter)),
+        ApplySuggestions::Rewrite(None) | ApplySuggestions::Diff => None,
+    };
+
+    let mut suggestions: Vec<_> = suggestions
+        .iter()
+        .filter_map(|sugg| suggestion_edits(sess, sugg, filter.as_deref()))
+        .collect();
+    suggestions.sort_by(|a, b| {
+        let key = |edits: &[(PathBuf, Lrc<SourceFile>, Edit)]| {
+            edits.iter().map(|(path, _, edit)| (path.clone(), edit.clone())).collect::<Vec<_>>()
+        };
+        key(a).cmp(&key(b))
+    });
+    // The same suggestion is often made by several diagnostics, e.g. for code
+    // that is checked once per generic instantiation.
+    suggestions.dedup_by(|a, b| {
+        a.len() == b.len()
+            && a.iter().zip(b.iter()).all(|((a_path, _, a_edit), (b_path, _, b_edit))| {
+                a_path == b_path && a_edit == b_edit
+            })
+    });
+
+    let mut accepted = FileEdits::default();
+    let mut applicable = Vec::new();
+    let mut overlapping = 0;
+    for edits in suggestions {
+        if accepted.overlaps(&edits) {
+            overlapping += 1;
+        } else {
+            accepted.add(&edits);
+            applicable.push(edits);
+        }
+    }
+
+    // The contents of the files to change, or `None` for the ones that can't be.
+    let mut contents: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+    for (path, (file, _)) in accepted.files {
+        let file_contents = match fs::read_to_string(&path) {
+            Ok(file_contents) if file.src_hash.matches(&file_contents) => Some(file_contents),
+            Ok(_) => {
+                sess.warn(&format!(
+                    "not applying suggestions to `{}`, as it was modified during compilation",
+                    path.display()
+                ));
+                None
+            }
+            Err(err) => {
+                sess.err(&format!("failed to read `{}`: {err}", path.display()));
+                None
+            }
+        };
+        contents.insert(path, file_contents);
+    }
+
+    // A suggestion is left out entirely if any of the files it changes can't be.
+    applicable.retain(|edits| {
+        edits.iter().all(|(path, _, edit)| match &contents[path] {
+            None => false,
+            Some(file_contents)
+                if !file_contents.is_char_boundary(edit.lo)
+                    || !file_contents.is_char_boundary(edit.hi) =>
+            {
+                sess.warn(&format!(
+                    "not applying a suggestion to `{}`, as it would replace bytes {}..{}, \
+                     which don't start and end at character boundaries",
+                    path.display(),
+                    edit.lo,
+                    edit.hi,
+                ));
+                false
+            }
+            Some(_) => true,
+        })
+    });
+
+    // The edits of each file, with the number of suggestions they come from.
+    let mut files: BTreeMap<PathBuf, (Vec<Edit>, usize)> = BTreeMap::new();
+    for edits in applicable {
+        let mut paths: Vec<&PathBuf> = Vec::new();
+        for (path, _, edit) in &edits {
+            let (file_edits, n) = files.entry(path.clone()).or_default();
+            file_edits.push(edit.clone());
+            if !paths.contains(&path) {
+                paths.push(path);
+                *n += 1;
+            }
+        }
+    }
+
+    if *mode == ApplySuggestions::Diff {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for (path, (mut edits, _)) in files {
+            edits.sort();
+            let file_contents = contents[&path].as_deref().unwrap();
+            if let Err(err) = write_unified_diff(&mut stdout, &path, file_contents, &edits) {
+                sess.err(&format!("failed to print the suggestions: {err}"));
+                return;
+            }
+        }
+        return;
+    }
+
+    // The new contents are written next to each file first, and only replace
+    // them once they were all written, so that the suggestions changing several
+    // files are applied to all of them or to none.
+    let mut written = Vec::new();
+    for (path, (mut edits, n)) in files {
+        edits.sort();
+        let new_contents = apply_edits(contents[&path].as_deref().unwrap(), &edits);
+        let temp_path = temp_path(&path);
+        if let Err(err) = write_file_like(&temp_path, &path, &new_contents) {
+            sess.err(&format!(
+                "failed to write `{}`: {err}, no suggestions were applied",
+                temp_path.display()
+            ));
+            let _ = fs::remove_file(&temp_path);
+            for (temp_path, ..) in written {
+                let _ = fs::remove_file(temp_path);
+            }
+            return;
+        }
+        written.push((temp_path, path, n));
+    }
+    for (temp_path, path, n) in written {
+        match fs::rename(&temp_path, &path) {
+            Ok(()) => sess.note_without_error(&format!(
+                "applied {n} suggestion{s} to `{}`",
+                path.display(),
+                s = pluralize!(n),
+            )),
+            Err(err) => {
+                sess.err(&format!("failed to write `{}`: {err}", path.display()));
+                let _ = fs::remove_file(&temp_path);
+            }
+        }
+    }
+
+    if overlapping > 0 {
+        sess.note_without_error(&format!(
+            "{overlapping} suggestion{s} overlapping with others {were} not applied, \
+             compile again to apply {them}",
+            s = pluralize!(overlapping),
+            were = if overlapping == 1 { "was" } else { "were" },
+            them = if overlapping == 1 { "it" } else { "them" },
+        ));
+    }
+}
+
+/// The edits of `sugg`, or `None` if it can't be applied: when there are several
+/// ways to apply it, or it touches macro-generated code or files that aren't
+/// local or that are outside of `filter`.
+fn suggestion_edits(
+    sess: &Session,
+    sugg: &CodeSuggestion,
+    filter: Option<&Path>,
+) -> Option<Vec<(PathBuf, Lrc<SourceFile>, Edit)>> {
+    let [substitution] = &sugg.substitutions[..] else {
+        return None;
+    };
+    substitution
+        .parts
+        .iter()
+        .map(|part| {
+            let (path, file, edit) = span_edit(sess, part.span, &part.snippet)?;
+            if filter.map_or(false, |filter| !canonicalize(&path).starts_with(filter)) {
+                return None;
+            }
+            Some((path, file, edit))
+        })
+        .collect()
+}
+
+fn span_edit(
+    sess: &Session,
+    span: Span,
+    snippet: &str,
+) -> Option<(PathBuf, Lrc<SourceFile>, Edit)> {
+    if span.from_expansion() {
+        return None;
+    }
+    let file = sess.source_map().lookup_source_file(span.lo());
+    if span.hi() > file.end_pos {
+        return None;
+    }
+    let FileName::Real(ref name) = file.name else {
+        return None;
+    };
+    let path = name.local_path()?.to_path_buf();
+    let edit = Edit {
+        lo: file.original_relative_byte_pos(span.lo()).0 as usize,
+        hi: file.original_relative_byte_pos(span.hi()).0 as usize,
+        snippet: snippet.to_string(),
+    };
+    Some((path, file, edit))
+}
+
+/// Where the new contents of `path` are written before replacing it.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".rustc-suggestions");
+    path.with_file_name(file_name)
+}
+
+/// Writes `contents` to `path`, with the permissions of `original`.
+fn write_file_like(path: &Path, original: &Path, contents: &str) -> io::Result<()> {
+    fs::write(path, contents)?;
+    fs::set_permissions(path, fs::metadata(original)?.permissions())
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Applies `edits`, which are sorted and don't overlap, to `contents`.
+fn apply_edits(contents: &str, edits: &[Edit]) -> String {
+    let mut result = String::with_capacity(contents.len());
+    let mut pos = 0;
+    for edit in edits {
+        result.push_str(&contents[pos..edit.lo]);
+        result.push_str(&edit.snippet);
+        pos = edit.hi;
+    }
+    result.push_str(&contents[pos..]);
+    result
+}
+
+/// A range of lines of the original file replaced by new lines.
+struct LineChange {
+    /// 0-based index of the first replaced line.
+    start: usize,
+    /// Number of replaced lines.
+    len: usize,
+    new_lines: Vec<String>,
+}
+
+/// Writes the changes made by `edits` to `contents` as a unified diff.
+fn write_unified_diff(
+    out: &mut dyn Write,
+    path: &Path,
+    contents: &str,
+    edits: &[Edit],
+) -> io::Result<()> {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let mut line_starts = vec![0];
+    line_starts.extend(lines.iter().scan(0, |pos, line| {
+        *pos += line.len();
+        Some(*pos)
+    }));
+    if line_starts.len() > 1 {
+        line_starts.pop();
+    }
+    let line_of = |pos: usize| line_starts.partition_point(|&start| start <= pos) - 1;
+
+    // Group the edits by the lines they touch, and turn each group into a
+    // change of whole lines.
+    let mut changes: Vec<LineChange> = Vec::new();
+    let mut i = 0;
+    while i < edits.len() {
+        let first = line_of(edits[i].lo);
+        let mut last = line_of(edits[i].hi);
+        let mut j = i + 1;
+        while j < edits.len() && line_of(edits[j].lo) <= last {
+            last = last.max(line_of(edits[j].hi));
+            j += 1;
+        }
+
+        let lo = line_starts[first];
+        let hi = line_starts.get(last + 1).copied().unwrap_or(contents.len());
+        let old = &contents[lo..hi];
+        let group: Vec<Edit> = edits[i..j]
+            .iter()
+            .map(|edit| Edit { lo: edit.lo - lo, hi: edit.hi - lo, ..edit.clone() })
+            .collect();
+        let new = apply_edits(old, &group);
+
+        // Lines that the edits leave as they were are context, not changes.
+        let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+        let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+        let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+        let suffix = old_lines[prefix..]
+            .iter()
+            .rev()
+            .zip(new_lines[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if old_lines.len() != new_lines.len() || prefix != old_lines.len() {
+            changes.push(LineChange {
+                start: first + prefix,
+                len: old_lines.len() - prefix - suffix,
+                new_lines: new_lines[prefix..new_lines.len() - suffix]
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect(),
+            });
+        }
+        i = j;
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "--- {}", path.display())?;
+    writeln!(out, "+++ {}", path.display())?;
+    // How many more lines the new file has before the current hunk.
+    let mut offset: isize = 0;
+    let mut i = 0;
+    while i < changes.len() {
+        // Changes whose context would overlap are part of the same hunk.
+        let mut j = i + 1;
+        while j < changes.len()
+            && changes[j].start
+                <= changes[j - 1].start + changes[j - 1].len + 2 * DIFF_CONTEXT_LINES
+        {
+            j += 1;
+        }
+        let hunk = &changes[i..j];
+        let start = hunk[0].start.saturating_sub(DIFF_CONTEXT_LINES);
+        let last = &hunk[hunk.len() - 1];
+        let end = (last.start + last.len + DIFF_CONTEXT_LINES).min(lines.len());
+        let old_len = end - start;
+        let new_len = hunk.iter().fold(old_len as isize, |len, change| {
+            len + change.new_lines.len() as isize - change.len as isize
+        }) as usize;
+        let new_start = (start as isize + offset) as usize;
+        // Empty ranges are numbered after the line they follow.
+        let number = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+        writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            number(start, old_len),
+            number(new_start, new_len)
+        )?;
+
+        let mut line = start;
+        for change in hunk {
+            for context in &lines[line..change.start] {
+                write_diff_line(out, ' ', context)?;
+            }
+            for removed in &lines[change.start..change.start + change.len] {
+                write_diff_line(out, '-', removed)?;
+            }
+            for added in &change.new_lines {
+                write_diff_line(out, '+', added)?;
+            }
+            line = change.start + change.len;
+        }
+        for context in &lines[line..end] {
+            write_diff_line(out, ' ', context)?;
+        }
+
+        offset += new_len as isize - old_len as isize;
+        i = j;
+    }
+    out.flush()
+}
+
+fn write_diff_line(out: &mut dyn Write, marker: char, line: &str) -> io::Result<()> {
+    write!(out, "{marker}{line}")?;
+    if !line.ends_with('\n') {
+        writeln!(out)?;
+        writeln!(out, "\\ No newline at end of file")?;
+    }
+    Ok(())
+}
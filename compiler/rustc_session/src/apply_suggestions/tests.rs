@@ -0,0 +1,84 @@
+use super::*;
+
+fn edit(lo: usize, hi: usize, snippet: &str) -> Edit {
+    Edit { lo, hi, snippet: snippet.to_string() }
+}
+
+fn diff(contents: &str, edits: &[Edit]) -> String {
+    let mut out = Vec::new();
+    write_unified_diff(&mut out, Path::new("a.rs"), contents, edits).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn overlapping_edits() {
+    assert!(edit(0, 4, "").overlaps(&edit(2, 6, "")));
+    assert!(edit(2, 2, "a").overlaps(&edit(2, 2, "b")));
+    assert!(edit(0, 4, "").overlaps(&edit(2, 2, "b")));
+    assert!(!edit(0, 4, "").overlaps(&edit(4, 6, "")));
+    assert!(!edit(0, 4, "").overlaps(&edit(4, 4, "b")));
+}
+
+#[test]
+fn apply_sorted_edits() {
+    let edits = [edit(4, 8, ""), edit(8, 9, "y"), edit(16, 16, "_")];
+    assert_eq!(apply_edits("let mut x = 1; x;", &edits), "let y = 1; x_;");
+}
+
+#[test]
+fn diff_of_one_line() {
+    let contents = "fn main() {\n    let mut x = 1;\n}\n";
+    assert_eq!(
+        diff(contents, &[edit(20, 24, "")]),
+        "--- a.rs\n+++ a.rs\n\
+         @@ -1,3 +1,3 @@\n fn main() {\n-    let mut x = 1;\n+    let x = 1;\n }\n"
+    );
+}
+
+#[test]
+fn diff_without_changes() {
+    assert_eq!(diff("let x = 1;\n", &[edit(4, 5, "x")]), "");
+}
+
+#[test]
+fn diff_of_distant_lines() {
+    let contents: String = (1..=12).map(|i| format!("{i}\n")).collect();
+    // Replaces line 1 and removes line 12.
+    let edits = [edit(0, 1, "one"), edit(contents.len() - 3, contents.len(), "")];
+    assert_eq!(
+        diff(&contents, &edits),
+        "--- a.rs\n+++ a.rs\n\
+         @@ -1,4 +1,4 @@\n-1\n+one\n 2\n 3\n 4\n\
+         @@ -9,4 +9,3 @@\n 9\n 10\n 11\n-12\n"
+    );
+}
+
+#[test]
+fn diff_of_nearby_lines() {
+    let contents: String = (1..=8).map(|i| format!("{i}\n")).collect();
+    // The context of both changes overlaps, so they are in the same hunk.
+    let edits = [edit(2, 3, "two"), edit(10, 11, "six")];
+    assert_eq!(
+        diff(&contents, &edits),
+        "--- a.rs\n+++ a.rs\n\
+         @@ -1,8 +1,8 @@\n 1\n-2\n+two\n 3\n 4\n 5\n-6\n+six\n 7\n 8\n"
+    );
+}
+
+#[test]
+fn diff_of_inserted_lines() {
+    let contents = "fn f() {}\n";
+    assert_eq!(
+        diff(contents, &[edit(0, 0, "#[inline]\n")]),
+        "--- a.rs\n+++ a.rs\n@@ -1,1 +1,2 @@\n+#[inline]\n fn f() {}\n"
+    );
+}
+
+#[test]
+fn diff_without_final_newline() {
+    assert_eq!(
+        diff("let mut x = 1;", &[edit(4, 8, "")]),
+        "--- a.rs\n+++ a.rs\n@@ -1,1 +1,1 @@\n-let mut x = 1;\n\\ No newline at end of file\n\
+         +let x = 1;\n\\ No newline at end of file\n"
+    );
+}
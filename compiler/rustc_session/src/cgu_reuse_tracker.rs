@@ -1,6 +1,6 @@
 //! Some facilities for tracking how codegen-units are reused during incremental
-//! compilation. This is used for incremental compilation tests and debug
-//! output.
+//! compilation. This is used for incremental compilation tests, debug output
+//! and `-Z incremental-explain`.
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_span::{Span, Symbol};
@@ -65,6 +65,13 @@ pub fn set_actual_reuse(&self, cgu_name: &str, kind: CguReuse) {
         }
     }
 
+    /// The reuse recorded for `cgu_name` with `set_actual_reuse`, if the tracker is
+    /// enabled and it was recorded.
+    pub fn actual_reuse(&self, cgu_name: &str) -> Option<CguReuse> {
+        let data = self.data.as_ref()?;
+        data.lock().unwrap().actual_reuse.get(cgu_name).copied()
+    }
+
     pub fn set_expectation(
         &self,
         cgu_name: Symbol,
@@ -1,5 +1,7 @@
-use rustc_data_structures::fx::FxHashSet;
+use crate::config::MonoBloatReportFormat;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_data_structures::sync::Lock;
+use rustc_serialize::json::{Json, Object};
 use rustc_target::abi::{Align, Size};
 use std::cmp::{self, Ordering};
 
@@ -45,9 +47,28 @@ pub struct TypeSizeInfo {
     pub variants: Vec<VariantInfo>,
 }
 
+/// An instantiation of a generic definition, for `-Z mono-bloat-report`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MonoItemInfo {
+    /// The path of the generic definition.
+    pub definition: String,
+    pub instance: String,
+    pub symbol_name: String,
+    /// The size of the MIR of the instance, in statements and terminators.
+    pub mir_statements: usize,
+    /// The crates defining the items that use the instance, causing this crate
+    /// to instantiate it.
+    pub instantiated_by: Vec<String>,
+}
+
 #[derive(Default)]
 pub struct CodeStats {
     type_sizes: Lock<FxHashSet<TypeSizeInfo>>,
+    mono_items: Lock<Vec<MonoItemInfo>>,
+    /// The number of LLVM instructions emitted for each symbol, summed over the
+    /// codegen units it is emitted in. Codegen units reused by incremental
+    /// compilation aren't counted.
+    llvm_instructions: Lock<FxHashMap<String, usize>>,
 }
 
 impl CodeStats {
@@ -178,4 +199,166 @@ pub fn print_type_sizes(&self) {
             }
         }
     }
+
+    pub fn record_mono_item(&self, info: MonoItemInfo) {
+        self.mono_items.borrow_mut().push(info);
+    }
+
+    pub fn record_llvm_instructions(&self, symbol_name: &str, count: usize) {
+        *self.llvm_instructions.borrow_mut().entry(symbol_name.to_string()).or_insert(0) += count;
+    }
+
+    pub fn print_mono_bloat_report(&self, format: MonoBloatReportFormat) {
+        let mono_items = self.mono_items.borrow();
+        let llvm_instructions = self.llvm_instructions.borrow();
+
+        struct Instance<'a> {
+            info: &'a MonoItemInfo,
+            llvm_instructions: Option<usize>,
+        }
+        struct Definition<'a> {
+            name: &'a str,
+            mir_statements: usize,
+            llvm_instructions: Option<usize>,
+            instances: Vec<Instance<'a>>,
+        }
+
+        let mut definitions: FxHashMap<&str, Definition<'_>> = FxHashMap::default();
+        for info in mono_items.iter() {
+            let instance = Instance {
+                info,
+                llvm_instructions: llvm_instructions.get(&info.symbol_name).copied(),
+            };
+            let definition = definitions.entry(&info.definition).or_insert_with(|| Definition {
+                name: &info.definition,
+                mir_statements: 0,
+                llvm_instructions: None,
+                instances: Vec::new(),
+            });
+            definition.mir_statements += info.mir_statements;
+            if let Some(count) = instance.llvm_instructions {
+                *definition.llvm_instructions.get_or_insert(0) += count;
+            }
+            definition.instances.push(instance);
+        }
+
+        // Primary sort: most LLVM instructions, which are only known for the
+        // codegen units that weren't reused by incremental compilation.
+        // Secondary sort: most MIR statements, then by name.
+        let mut sorted: Vec<_> = definitions.into_values().collect();
+        sorted.sort_by(|def1, def2| {
+            (def2.llvm_instructions, def2.mir_statements, def1.name).cmp(&(
+                def1.llvm_instructions,
+                def1.mir_statements,
+                def2.name,
+            ))
+        });
+        for definition in &mut sorted {
+            definition.instances.sort_by(|inst1, inst2| {
+                (inst2.llvm_instructions, inst2.info.mir_statements, &inst1.info.instance).cmp(&(
+                    inst1.llvm_instructions,
+                    inst1.info.mir_statements,
+                    &inst2.info.instance,
+                ))
+            });
+        }
+
+        match format {
+            MonoBloatReportFormat::Text => {
+                for definition in sorted {
+                    let Definition { name, mir_statements, llvm_instructions, instances } =
+                        definition;
+                    let llvm = llvm_instructions
+                        .map_or(String::new(), |count| format!(", {count} LLVM instructions"));
+                    println!(
+                        "mono-bloat-report definition: `{name}`: {} instantiations, \
+                         {mir_statements} MIR statements{llvm}",
+                        instances.len()
+                    );
+                    for Instance { info, llvm_instructions } in instances {
+                        let llvm = llvm_instructions
+                            .map_or(String::new(), |count| format!(", {count} LLVM instructions"));
+                        let instantiated_by = info
+                            .instantiated_by
+                            .iter()
+                            .map(|krate| format!("`{krate}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        println!(
+                            "mono-bloat-report     `{}`: {} MIR statements{llvm}, \
+                             instantiated by {}",
+                            info.instance,
+                            info.mir_statements,
+                            if instantiated_by.is_empty() { "none" } else { &instantiated_by },
+                        );
+                    }
+                }
+            }
+            MonoBloatReportFormat::Json => {
+                fn count(count: Option<usize>) -> Json {
+                    count.map_or(Json::Null, |count| Json::U64(count as u64))
+                }
+
+                let definitions = sorted
+                    .into_iter()
+                    .map(|definition| {
+                        let instances = definition
+                            .instances
+                            .iter()
+                            .map(|Instance { info, llvm_instructions }| {
+                                let mut instance = Object::new();
+                                instance.insert(
+                                    "instance".to_string(),
+                                    Json::String(info.instance.clone()),
+                                );
+                                instance.insert(
+                                    "symbol_name".to_string(),
+                                    Json::String(info.symbol_name.clone()),
+                                );
+                                instance.insert(
+                                    "mir_statements".to_string(),
+                                    Json::U64(info.mir_statements as u64),
+                                );
+                                instance.insert(
+                                    "llvm_instructions".to_string(),
+                                    count(*llvm_instructions),
+                                );
+                                instance.insert(
+                                    "instantiated_by".to_string(),
+                                    Json::Array(
+                                        info.instantiated_by
+                                            .iter()
+                                            .map(|krate| Json::String(krate.clone()))
+                                            .collect(),
+                                    ),
+                                );
+                                Json::Object(instance)
+                            })
+                            .collect();
+
+                        let mut object = Object::new();
+                        object.insert(
+                            "definition".to_string(),
+                            Json::String(definition.name.to_string()),
+                        );
+                        object.insert(
+                            "instantiations".to_string(),
+                            Json::U64(definition.instances.len() as u64),
+                        );
+                        object.insert(
+                            "mir_statements".to_string(),
+                            Json::U64(definition.mir_statements as u64),
+                        );
+                        object.insert(
+                            "llvm_instructions".to_string(),
+                            count(definition.llvm_instructions),
+                        );
+                        object.insert("instances".to_string(), Json::Array(instances));
+                        Json::Object(object)
+                    })
+                    .collect();
+                println!("{}", Json::Array(definitions).pretty());
+            }
+        }
+    }
 }
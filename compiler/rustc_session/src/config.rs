@@ -30,11 +30,14 @@
     Iter as BTreeMapIter, Keys as BTreeMapKeysIter, Values as BTreeMapValuesIter,
 };
 use std::collections::{BTreeMap, BTreeSet};
-use std::fmt;
 use std::hash::Hash;
 use std::iter::{self, FromIterator};
+use std::lazy::SyncOnceCell;
 use std::path::{Path, PathBuf};
 use std::str::{self, FromStr};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::{fmt, fs};
 
 /// The different settings that the `-C strip` flag can have.
 #[derive(Clone, Copy, PartialEq, Hash, Debug)]
@@ -213,6 +216,68 @@ pub fn all() -> Self {
     }
 }
 
+/// Whether `-Z diagnostic-baseline` records the lints that are emitted, or allows
+/// those that were recorded.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DiagnosticBaselineMode {
+    Record,
+    Enforce,
+}
+
+/// The output format of `-Z explain-trait-errors`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExplainTraitErrorsFormat {
+    /// A note of the error, rendering the derivation tree.
+    Human,
+    /// A JSON object on the standard output for each error.
+    Json,
+}
+
+/// The output format of `-Z incremental-explain`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IncrementalExplainFormat {
+    /// Lines on the standard error, like those of `-Z incremental-info`.
+    Human,
+    /// A JSON object on the standard output for each recompiled codegen unit.
+    Json,
+}
+
+/// The phase of the MIR written by `--emit=mir-json`, set by `-Z mir-json-phase`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MirJsonPhase {
+    /// The MIR as built, before any pass.
+    Built,
+    /// The MIR checked by the borrow checker, after promotion.
+    Analysis,
+    /// The MIR that is codegened.
+    Optimized,
+}
+
+/// The output format of `-Z mono-bloat-report`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MonoBloatReportFormat {
+    Text,
+    Json,
+}
+
+/// The format of the analysis written by `-Z save-analysis`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SaveAnalysisFormat {
+    /// The JSON format of the `rls-data` crate.
+    Json,
+    /// An LSIF index, for code intelligence tools.
+    Lsif,
+}
+
+/// What `-Z apply-suggestions` does with the machine-applicable suggestions.
+#[derive(Clone, PartialEq, Hash, Debug)]
+pub enum ApplySuggestions {
+    /// Rewrite the source files under the given path, or all source files.
+    Rewrite(Option<PathBuf>),
+    /// Print the changes as a unified diff rather than making them.
+    Diff,
+}
+
 #[derive(Clone, PartialEq, Hash, Debug)]
 pub enum SwitchWithOptPath {
     Enabled(Option<PathBuf>),
@@ -280,10 +345,12 @@ pub enum OutputType {
     Assembly,
     LlvmAssembly,
     Mir,
+    MirJson,
     Metadata,
     Object,
     Exe,
     DepInfo,
+    DepInfoJson,
 }
 
 impl<HCX: HashStableContext> ToStableHashKey<HCX> for OutputType {
@@ -297,11 +364,15 @@ fn to_stable_hash_key(&self, _: &HCX) -> Self::KeyType {
 impl OutputType {
     fn is_compatible_with_codegen_units_and_single_output_file(&self) -> bool {
         match *self {
-            OutputType::Exe | OutputType::DepInfo | OutputType::Metadata => true,
+            OutputType::Exe
+            | OutputType::DepInfo
+            | OutputType::DepInfoJson
+            | OutputType::Metadata => true,
             OutputType::Bitcode
             | OutputType::Assembly
             | OutputType::LlvmAssembly
             | OutputType::Mir
+            | OutputType::MirJson
             | OutputType::Object => false,
         }
     }
@@ -312,10 +383,12 @@ fn shorthand(&self) -> &'static str {
             OutputType::Assembly => "asm",
             OutputType::LlvmAssembly => "llvm-ir",
             OutputType::Mir => "mir",
+            OutputType::MirJson => "mir-json",
             OutputType::Object => "obj",
             OutputType::Metadata => "metadata",
             OutputType::Exe => "link",
             OutputType::DepInfo => "dep-info",
+            OutputType::DepInfoJson => "dep-info-json",
         }
     }
 
@@ -324,26 +397,30 @@ fn from_shorthand(shorthand: &str) -> Option<Self> {
             "asm" => OutputType::Assembly,
             "llvm-ir" => OutputType::LlvmAssembly,
             "mir" => OutputType::Mir,
+            "mir-json" => OutputType::MirJson,
             "llvm-bc" => OutputType::Bitcode,
             "obj" => OutputType::Object,
             "metadata" => OutputType::Metadata,
             "link" => OutputType::Exe,
             "dep-info" => OutputType::DepInfo,
+            "dep-info-json" => OutputType::DepInfoJson,
             _ => return None,
         })
     }
 
     fn shorthands_display() -> String {
         format!(
-            "`{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`",
+            "`{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`, `{}`",
             OutputType::Bitcode.shorthand(),
             OutputType::Assembly.shorthand(),
             OutputType::LlvmAssembly.shorthand(),
             OutputType::Mir.shorthand(),
+            OutputType::MirJson.shorthand(),
             OutputType::Object.shorthand(),
             OutputType::Metadata.shorthand(),
             OutputType::Exe.shorthand(),
             OutputType::DepInfo.shorthand(),
+            OutputType::DepInfoJson.shorthand(),
         )
     }
 
@@ -353,9 +430,11 @@ pub fn extension(&self) -> &'static str {
             OutputType::Assembly => "s",
             OutputType::LlvmAssembly => "ll",
             OutputType::Mir => "mir",
+            OutputType::MirJson => "mir.json",
             OutputType::Object => "o",
             OutputType::Metadata => "rmeta",
             OutputType::DepInfo => "d",
+            OutputType::DepInfoJson => "d.json",
             OutputType::Exe => "",
         }
     }
@@ -374,6 +453,8 @@ pub enum ErrorOutputType {
         /// human output.
         json_rendered: HumanReadableErrorType,
     },
+    /// A single SARIF log for the whole session, for code scanning tools.
+    Sarif,
 }
 
 impl Default for ErrorOutputType {
@@ -433,9 +514,10 @@ pub fn should_codegen(&self) -> bool {
             | OutputType::Assembly
             | OutputType::LlvmAssembly
             | OutputType::Mir
+            | OutputType::MirJson
             | OutputType::Object
             | OutputType::Exe => true,
-            OutputType::Metadata | OutputType::DepInfo => false,
+            OutputType::Metadata | OutputType::DepInfo | OutputType::DepInfoJson => false,
         })
     }
 
@@ -446,12 +528,20 @@ pub fn should_link(&self) -> bool {
             | OutputType::Assembly
             | OutputType::LlvmAssembly
             | OutputType::Mir
+            | OutputType::MirJson
             | OutputType::Metadata
             | OutputType::Object
-            | OutputType::DepInfo => false,
+            | OutputType::DepInfo
+            | OutputType::DepInfoJson => false,
             OutputType::Exe => true,
         })
     }
+
+    /// Returns `true` if only dependency information is requested, which is
+    /// available once the crate is expanded.
+    pub fn only_dep_info(&self) -> bool {
+        self.0.keys().all(|k| matches!(k, OutputType::DepInfo | OutputType::DepInfoJson))
+    }
 }
 
 /// Use tree-based collections to cheaply get a deterministic `Hash` implementation.
@@ -808,6 +898,7 @@ pub fn diagnostic_handler_flags(&self, can_emit_warnings: bool) -> HandlerFlags
             report_delayed_bugs: self.report_delayed_bugs,
             macro_backtrace: self.macro_backtrace,
             deduplicate_diagnostics: self.deduplicate_diagnostics,
+            collect_suggestions: self.apply_suggestions.is_some(),
         }
     }
 }
@@ -1190,13 +1281,64 @@ pub fn build_configuration(sess: &Session, mut user_cfg: CrateConfig) -> CrateCo
     user_cfg
 }
 
+/// The targets loaded by earlier sessions of this process, with the size and modification
+/// time of their JSON file for those given by path. See `enable_target_cache`.
+static TARGET_CACHE: SyncOnceCell<
+    Mutex<FxHashMap<(TargetTriple, PathBuf), (Option<(u64, SystemTime)>, Target)>>,
+> = SyncOnceCell::new();
+
+/// Makes the sessions of this process share the targets they load, which are then looked
+/// up and parsed only once. This is how the compile server (`rustc --server`) keeps them
+/// between compilations.
+pub fn enable_target_cache() {
+    TARGET_CACHE.get_or_init(Default::default);
+}
+
+fn search_target(
+    target_triple: &TargetTriple,
+    sysroot: &Path,
+) -> Result<(Target, TargetWarnings), String> {
+    let Some(cache) = TARGET_CACHE.get() else {
+        return Target::search(target_triple, sysroot);
+    };
+    // Only built-in targets and JSON files given by path can be cached: other targets are
+    // looked up in `RUST_TARGET_PATH`, which may differ between compilations.
+    let stamp = match target_triple {
+        TargetTriple::TargetTriple(triple) if TARGETS.contains(&triple.as_str()) => None,
+        TargetTriple::TargetPath(path) => {
+            match fs::metadata(path).and_then(|file| Ok((file.len(), file.modified()?))) {
+                Ok(stamp) => Some(stamp),
+                Err(_) => return Target::search(target_triple, sysroot),
+            }
+        }
+        TargetTriple::TargetTriple(_) => return Target::search(target_triple, sysroot),
+    };
+
+    let key = (target_triple.clone(), sysroot.to_path_buf());
+    let cached = cache
+        .lock()
+        .unwrap()
+        .get(&key)
+        .filter(|(cached_stamp, _)| *cached_stamp == stamp)
+        .map(|(_, target)| target.clone());
+    if let Some(target) = cached {
+        return Ok((target, TargetWarnings::empty()));
+    }
+    let (target, warnings) = Target::search(target_triple, sysroot)?;
+    // Targets with warnings aren't cached so that each compilation reports them.
+    if warnings.warning_messages().is_empty() {
+        cache.lock().unwrap().insert(key, (stamp, target.clone()));
+    }
+    Ok((target, warnings))
+}
+
 pub(super) fn build_target_config(
     opts: &Options,
     target_override: Option<Target>,
     sysroot: &Path,
 ) -> Target {
     let target_result = target_override.map_or_else(
-        || Target::search(&opts.target_triple, sysroot),
+        || search_target(&opts.target_triple, sysroot),
         |t| Ok((t, TargetWarnings::empty())),
     );
     let (target, target_warnings) = target_result.unwrap_or_else(|e| {
@@ -1354,7 +1496,7 @@ pub fn rustc_short_optgroups() -> Vec<RustcOptGroup> {
             "emit",
             "Comma separated list of types of output for \
              the compiler to emit",
-            "[asm|llvm-bc|llvm-ir|obj|metadata|link|dep-info|mir]",
+            "[asm|llvm-bc|llvm-ir|obj|metadata|link|dep-info|dep-info-json|mir|mir-json]",
         ),
         opt::multi_s(
             "",
@@ -1596,11 +1738,12 @@ pub fn parse_error_format(
             Some("json") => ErrorOutputType::Json { pretty: false, json_rendered },
             Some("pretty-json") => ErrorOutputType::Json { pretty: true, json_rendered },
             Some("short") => ErrorOutputType::HumanReadable(HumanReadableErrorType::Short(color)),
+            Some("sarif") => ErrorOutputType::Sarif,
 
             Some(arg) => early_error(
                 ErrorOutputType::HumanReadable(HumanReadableErrorType::Default(color)),
                 &format!(
-                    "argument for `--error-format` must be `human`, `json` or \
+                    "argument for `--error-format` must be `human`, `json`, `sarif` or \
                      `short` (instead was `{arg}`)"
                 ),
             ),
@@ -1678,6 +1821,13 @@ fn check_debug_option_stability(
                 "`--error-format=human-annotate-rs` is unstable",
             );
         }
+        if let ErrorOutputType::Sarif = error_format {
+            // Not reported as SARIF, which is what isn't available.
+            early_error(
+                ErrorOutputType::HumanReadable(json_rendered),
+                "`--error-format=sarif` is unstable",
+            );
+        }
     }
 }
 
@@ -1703,6 +1853,11 @@ fn parse_output_types(
                         ),
                     )
                 });
+                if matches!(output_type, OutputType::DepInfoJson | OutputType::MirJson)
+                    && !debugging_opts.unstable_options
+                {
+                    early_error(error_format, &format!("`--emit={shorthand}` is unstable"));
+                }
                 output_types.insert(output_type, path);
             }
         }
@@ -2274,6 +2429,18 @@ pub fn build_session_options(matches: &getopts::Matches) -> Options {
             "can't instrument with gcov profiling when compiling incrementally",
         );
     }
+
+    // The MIR of the phases before `optimized` is only written when it is built, which
+    // doesn't happen when it is reused from the incremental cache.
+    if output_types.contains_key(&OutputType::MirJson)
+        && debugging_opts.mir_json_phase != MirJsonPhase::Optimized
+        && incremental.is_some()
+    {
+        early_error(
+            error_format,
+            "can't emit the MIR of phases before `optimized` when compiling incrementally",
+        );
+    }
     if debugging_opts.profile {
         match codegen_units {
             Some(1) => {}
@@ -2775,7 +2942,7 @@ pub(crate) mod dep_tracking {
     };
     use std::collections::hash_map::DefaultHasher;
     use std::collections::BTreeMap;
-    use std::hash::Hash;
+    use std::hash::{Hash, Hasher};
     use std::num::NonZeroUsize;
     use std::path::PathBuf;
 
@@ -2947,6 +3114,27 @@ pub(crate) fn stable_hash(
             sub_hash.hash(hasher, error_format, for_crate_hash);
         }
     }
+
+    /// Hashes each option of `sub_hashes` separately, naming codegen and debugging
+    /// options by their flag, e.g. `-C opt-level`.
+    pub(crate) fn stable_hashes(
+        sub_hashes: BTreeMap<&'static str, &dyn DepTrackingHash>,
+        prefix: &str,
+        hashes: &mut BTreeMap<String, u64>,
+        error_format: ErrorOutputType,
+        for_crate_hash: bool,
+    ) {
+        for (key, sub_hash) in sub_hashes {
+            let mut hasher = DefaultHasher::new();
+            sub_hash.hash(&mut hasher, error_format, for_crate_hash);
+            let name = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("-{prefix} {}", key.replace('_', "-"))
+            };
+            hashes.insert(name, hasher.finish());
+        }
+    }
 }
 
 /// Default behavior to use in out-of-memory situations.
@@ -0,0 +1,166 @@
+//! Baselines of the lints emitted by a codebase, so that a new lint can be turned
+//! on without fixing all of its existing violations first (`-Z diagnostic-baseline`).
+//!
+//! The baseline has one line per lint diagnostic with its crate, the name of the
+//! lint, the path of the item it was emitted in and the code it points at, with
+//! whitespace collapsed. Warnings that aren't emitted by lints have their error
+//! code, or `warning`, in place of the lint name, and their message in place of
+//! the item path. Line numbers are left out, so that the baseline isn't
+//! invalidated by unrelated changes to the file.
+//!
+//! Recording replaces the lines of the crate being compiled with the lints it
+//! emits, and keeps those of the other crates, so a workspace can share a single
+//! baseline. Enforcing allows each lint matching a line of the baseline, as many
+//! times as the line occurs.
+
+use crate::config::{DiagnosticBaselineMode, Options};
+
+use rustc_data_structures::flock;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::Lock;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The number of characters of the snippet kept in the baseline.
+const MAX_SNIPPET_LEN: usize = 100;
+
+const HEADER: &str = "\
+# Diagnostic baseline written by `-Z diagnostic-baseline=record:<file>`.
+# Each line is `crate<TAB>lint<TAB>item path<TAB>code`.
+";
+
+pub struct DiagnosticBaseline {
+    mode: DiagnosticBaselineMode,
+    path: PathBuf,
+    /// The crate being compiled, as written in the baseline.
+    crate_key: String,
+    /// When enforcing, how many more times each fingerprint may be allowed.
+    remaining: Lock<FxHashMap<String, usize>>,
+    /// When recording, the fingerprints of the lints emitted so far.
+    recorded: Lock<Vec<String>>,
+}
+
+impl DiagnosticBaseline {
+    pub fn load(
+        mode: DiagnosticBaselineMode,
+        path: &Path,
+        opts: &Options,
+        local_crate_source_file: Option<&Path>,
+    ) -> io::Result<DiagnosticBaseline> {
+        let crate_key = crate_key(opts, local_crate_source_file);
+        let mut remaining = FxHashMap::default();
+        if mode == DiagnosticBaselineMode::Enforce {
+            for line in fs::read_to_string(path)?.lines() {
+                if let Some(fingerprint) =
+                    line.strip_prefix(&crate_key).and_then(|line| line.strip_prefix('\t'))
+                {
+                    *remaining.entry(fingerprint.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(DiagnosticBaseline {
+            mode,
+            path: path.to_path_buf(),
+            crate_key,
+            remaining: Lock::new(remaining),
+            recorded: Lock::new(Vec::new()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks a lint about to be emitted at a warning or error level against the
+    /// baseline, returning whether it should be allowed instead. When recording,
+    /// the lint is added to the baseline.
+    pub fn allows(&self, lint_name: &str, item_path: &str, snippet: &str) -> bool {
+        let fingerprint = format!("{lint_name}\t{item_path}\t{}", normalize_snippet(snippet));
+        match self.mode {
+            DiagnosticBaselineMode::Record => {
+                self.recorded.lock().push(fingerprint);
+                false
+            }
+            DiagnosticBaselineMode::Enforce => match self.remaining.lock().get_mut(&fingerprint) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// Like `allows`, for a warning that isn't emitted by a lint, such as a warning
+    /// about a codegen option. These are emitted without knowing which item
+    /// they are in, so they are told apart by their message instead.
+    pub fn allows_warning(&self, code: &str, message: &str, snippet: &str) -> bool {
+        let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.allows(code, &message, snippet)
+    }
+
+    /// Writes the recorded lints to the baseline, if recording one.
+    pub fn save(&self) -> io::Result<()> {
+        if self.mode != DiagnosticBaselineMode::Record {
+            return Ok(());
+        }
+
+        // Crates of a workspace can be compiled in parallel, each updating its
+        // own lines of the same baseline.
+        let mut lock_path = self.path.clone().into_os_string();
+        lock_path.push(".lock");
+        let _lock = flock::Lock::new(Path::new(&lock_path), true, true, true)?;
+
+        let previous = match fs::read_to_string(&self.path) {
+            Ok(previous) => previous,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut lines: Vec<String> = previous
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter(|line| line.split('\t').next() != Some(self.crate_key.as_str()))
+            .map(|line| line.to_string())
+            .collect();
+        lines.extend(
+            self.recorded
+                .lock()
+                .iter()
+                .map(|fingerprint| format!("{}\t{fingerprint}", self.crate_key)),
+        );
+        lines.sort();
+
+        let mut contents = HEADER.to_string();
+        for line in lines {
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents)
+    }
+}
+
+/// Identifies the crate in the baseline: by its name, and whether it is built
+/// as a test harness, since that enables more code.
+fn crate_key(opts: &Options, local_crate_source_file: Option<&Path>) -> String {
+    let name = opts.crate_name.clone().unwrap_or_else(|| {
+        local_crate_source_file
+            .and_then(|path| path.file_stem())
+            .map_or_else(String::new, |stem| stem.to_string_lossy().replace('-', "_"))
+    });
+    if opts.test {
+        format!("{name} --test")
+    } else {
+        name
+    }
+}
+
+/// Collapses whitespace, so that reformatting the code doesn't invalidate the
+/// baseline, and truncates long snippets such as whole items.
+fn normalize_snippet(snippet: &str) -> String {
+    let mut normalized = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((end, _)) = normalized.char_indices().nth(MAX_SNIPPET_LEN) {
+        normalized.truncate(end);
+    }
+    normalized
+}
@@ -17,10 +17,12 @@
 pub use rustc_lint_defs as lint;
 pub mod parse;
 
+mod apply_suggestions;
 mod code_stats;
 #[macro_use]
 pub mod config;
 pub mod cstore;
+pub mod diagnostic_baseline;
 pub mod filesearch;
 mod options;
 pub mod search_paths;
@@ -4,6 +4,7 @@
 use crate::lint;
 use crate::search_paths::SearchPath;
 use crate::utils::NativeLib;
+use rustc_errors::emitter::HyperlinkConfig;
 use rustc_errors::LanguageIdentifier;
 use rustc_target::spec::{CodeModel, LinkerFlavor, MergeFunctions, PanicStrategy, SanitizerSet};
 use rustc_target::spec::{
@@ -59,6 +60,15 @@ macro_rules! hash_substruct {
     };
 }
 
+macro_rules! hashes_substruct {
+    ($opt_name:ident, $opt_expr:expr, $error_format:expr, $for_crate_hash:expr, $hashes:expr, [UNTRACKED]) => {{}};
+    ($opt_name:ident, $opt_expr:expr, $error_format:expr, $for_crate_hash:expr, $hashes:expr, [TRACKED]) => {{}};
+    ($opt_name:ident, $opt_expr:expr, $error_format:expr, $for_crate_hash:expr, $hashes:expr, [TRACKED_NO_CRATE_HASH]) => {{}};
+    ($opt_name:ident, $opt_expr:expr, $error_format:expr, $for_crate_hash:expr, $hashes:expr, [SUBSTRUCT]) => {
+        $opt_expr.dep_tracking_hashes($for_crate_hash, $error_format, $hashes);
+    };
+}
+
 macro_rules! top_level_options {
     ( $( #[$top_level_attr:meta] )* pub struct Options { $(
         $( #[$attr:meta] )*
@@ -98,6 +108,34 @@ pub fn dep_tracking_hash(&self, for_crate_hash: bool) -> u64 {
                 })*
                 hasher.finish()
             }
+
+            /// Returns the hash of each option covered by `dep_tracking_hash`, by name,
+            /// to tell which options changed between two incremental sessions.
+            pub fn dep_tracking_hashes(&self, for_crate_hash: bool) -> BTreeMap<String, u64> {
+                let mut sub_hashes = BTreeMap::new();
+                $({
+                    hash_opt!($opt,
+                                &self.$opt,
+                                &mut sub_hashes,
+                                for_crate_hash,
+                                [$dep_tracking_marker]);
+                })*
+                let mut hashes = BTreeMap::new();
+                dep_tracking::stable_hashes(sub_hashes,
+                                            "",
+                                            &mut hashes,
+                                            self.error_format,
+                                            for_crate_hash);
+                $({
+                    hashes_substruct!($opt,
+                        &self.$opt,
+                        self.error_format,
+                        for_crate_hash,
+                        &mut hashes,
+                        [$dep_tracking_marker]);
+                })*
+                hashes
+            }
         }
     );
 }
@@ -284,6 +322,27 @@ fn dep_tracking_hash(&self, for_crate_hash: bool, error_format: ErrorOutputType)
                                         );
             hasher.finish()
         }
+
+        fn dep_tracking_hashes(
+            &self,
+            for_crate_hash: bool,
+            error_format: ErrorOutputType,
+            hashes: &mut BTreeMap<String, u64>,
+        ) {
+            let mut sub_hashes = BTreeMap::new();
+            $({
+                hash_opt!($opt,
+                            &self.$opt,
+                            &mut sub_hashes,
+                            for_crate_hash,
+                            [$dep_tracking_marker]);
+            })*
+            dep_tracking::stable_hashes(sub_hashes,
+                                        $prefix,
+                                        hashes,
+                                        error_format,
+                                        for_crate_hash);
+        }
     }
 
     pub const $stat: OptionDescrs<$struct_name> =
@@ -398,6 +457,17 @@ mod desc {
         "either a boolean (`yes`, `no`, `on`, `off`, etc), or the path to the linker plugin";
     pub const parse_location_detail: &str =
         "comma separated list of location details to track: `file`, `line`, or `column`";
+    pub const parse_apply_suggestions: &str = "either no value, `diff`, or a path";
+    pub const parse_diagnostic_baseline: &str =
+        "a path, optionally prefixed with `record:` or `enforce:` (default)";
+    pub const parse_explain_trait_errors: &str = "either no value or `json`";
+    pub const parse_incremental_explain: &str = "either no value or `json`";
+    pub const parse_mir_json_phase: &str = "one of: `built`, `analysis`, or `optimized`";
+    pub const parse_mono_bloat_report: &str = "either no value or `json`";
+    pub const parse_opt_byte_size: &str =
+        "a number of bytes, optionally followed by a `K`, `M`, `G` or `T` multiplier";
+    pub const parse_save_analysis_format: &str = "either `json` or `lsif`";
+    pub const parse_terminal_hyperlinks: &str = "one of: `auto`, `always`, or `never`";
     pub const parse_switch_with_opt_path: &str =
         "an optional path to the profiling data output directory";
     pub const parse_merge_functions: &str = "one of: `disabled`, `trampolines`, or `aliases`";
@@ -907,6 +977,119 @@ pub(crate) fn parse_linker_plugin_lto(slot: &mut LinkerPluginLto, v: Option<&str
         true
     }
 
+    pub(crate) fn parse_apply_suggestions(
+        slot: &mut Option<ApplySuggestions>,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = Some(match v {
+            None => ApplySuggestions::Rewrite(None),
+            Some("diff") => ApplySuggestions::Diff,
+            Some("") => return false,
+            Some(path) => ApplySuggestions::Rewrite(Some(PathBuf::from(path))),
+        });
+        true
+    }
+
+    pub(crate) fn parse_mir_json_phase(slot: &mut MirJsonPhase, v: Option<&str>) -> bool {
+        *slot = match v {
+            Some("built") => MirJsonPhase::Built,
+            Some("analysis") => MirJsonPhase::Analysis,
+            Some("optimized") => MirJsonPhase::Optimized,
+            _ => return false,
+        };
+        true
+    }
+
+    pub(crate) fn parse_explain_trait_errors(
+        slot: &mut Option<ExplainTraitErrorsFormat>,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = Some(match v {
+            None => ExplainTraitErrorsFormat::Human,
+            Some("json") => ExplainTraitErrorsFormat::Json,
+            Some(_) => return false,
+        });
+        true
+    }
+
+    pub(crate) fn parse_incremental_explain(
+        slot: &mut Option<IncrementalExplainFormat>,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = Some(match v {
+            None => IncrementalExplainFormat::Human,
+            Some("json") => IncrementalExplainFormat::Json,
+            Some(_) => return false,
+        });
+        true
+    }
+
+    pub(crate) fn parse_mono_bloat_report(
+        slot: &mut Option<MonoBloatReportFormat>,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = Some(match v {
+            None => MonoBloatReportFormat::Text,
+            Some("json") => MonoBloatReportFormat::Json,
+            Some(_) => return false,
+        });
+        true
+    }
+
+    pub(crate) fn parse_opt_byte_size(slot: &mut Option<u64>, v: Option<&str>) -> bool {
+        let Some(v) = v else { return false };
+        let (number, shift) = match v.as_bytes().last() {
+            Some(b'K') => (&v[..v.len() - 1], 10),
+            Some(b'M') => (&v[..v.len() - 1], 20),
+            Some(b'G') => (&v[..v.len() - 1], 30),
+            Some(b'T') => (&v[..v.len() - 1], 40),
+            _ => (v, 0),
+        };
+        *slot = number.parse::<u64>().ok().and_then(|number| number.checked_mul(1 << shift));
+        slot.is_some()
+    }
+
+    pub(crate) fn parse_save_analysis_format(
+        slot: &mut SaveAnalysisFormat,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = match v {
+            Some("json") => SaveAnalysisFormat::Json,
+            Some("lsif") => SaveAnalysisFormat::Lsif,
+            _ => return false,
+        };
+        true
+    }
+
+    pub(crate) fn parse_terminal_hyperlinks(slot: &mut HyperlinkConfig, v: Option<&str>) -> bool {
+        *slot = match v {
+            None | Some("auto") => HyperlinkConfig::Auto,
+            Some("always") => HyperlinkConfig::Always,
+            Some("never") => HyperlinkConfig::Never,
+            _ => return false,
+        };
+        true
+    }
+
+    pub(crate) fn parse_diagnostic_baseline(
+        slot: &mut Option<(DiagnosticBaselineMode, PathBuf)>,
+        v: Option<&str>,
+    ) -> bool {
+        let (mode, path) = match v {
+            None | Some("") => return false,
+            Some(v) => match v.split_once(':') {
+                Some(("record", path)) => (DiagnosticBaselineMode::Record, path),
+                Some(("enforce", path)) => (DiagnosticBaselineMode::Enforce, path),
+                _ => (DiagnosticBaselineMode::Enforce, v),
+            },
+        };
+        if path.is_empty() {
+            return false;
+        }
+        *slot = Some((mode, PathBuf::from(path)));
+        true
+    }
+
     pub(crate) fn parse_switch_with_opt_path(
         slot: &mut SwitchWithOptPath,
         v: Option<&str>,
@@ -1202,6 +1385,9 @@ pub(crate) fn parse_branch_protection(
         "encode MIR of all functions into the crate metadata (default: no)"),
     assume_incomplete_release: bool = (false, parse_bool, [TRACKED],
         "make cfg(version) treat the current version as incomplete (default: no)"),
+    apply_suggestions: Option<ApplySuggestions> = (None, parse_apply_suggestions, [UNTRACKED],
+        "apply the machine-applicable suggestions to the source files under the given path, \
+        or to all of them if no path is given, or print them as a unified diff with `diff`"),
     asm_comments: bool = (false, parse_bool, [TRACKED],
         "generate comments into the assembly (may change behavior) (default: no)"),
     assert_incr_state: Option<String> = (None, parse_opt_string, [UNTRACKED],
@@ -1242,6 +1428,10 @@ pub(crate) fn parse_branch_protection(
     dep_tasks: bool = (false, parse_bool, [UNTRACKED],
         "print tasks that execute and the color their dep node gets (requires debug build) \
         (default: no)"),
+    diagnostic_baseline: Option<(DiagnosticBaselineMode, PathBuf)> = (None,
+        parse_diagnostic_baseline, [UNTRACKED],
+        "allow the lints recorded in the given baseline file, or record the emitted lints \
+        in it with `record:<file>`"),
     dlltool: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
         "import library generation tool (windows-gnu only)"),
     dont_buffer_diagnostics: bool = (false, parse_bool, [UNTRACKED],
@@ -1280,6 +1470,11 @@ pub(crate) fn parse_branch_protection(
         an additional `.html` file showing the computed coverage spans."),
     emit_stack_sizes: bool = (false, parse_bool, [UNTRACKED],
         "emit a section containing stack size metadata (default: no)"),
+    explain_trait_errors: Option<ExplainTraitErrorsFormat> = (None,
+        parse_explain_trait_errors, [UNTRACKED],
+        "explain unsatisfied trait bounds with the tree of obligations that required them, and \
+        the impls that were considered and rejected (`-Zexplain-trait-errors=json` prints the \
+        trees as JSON)"),
     fewer_names: Option<bool> = (None, parse_opt_bool, [TRACKED],
         "reduce memory use by retaining fewer names within compilation artifacts (LLVM-IR) \
         (default: no)"),
@@ -1303,6 +1498,15 @@ pub(crate) fn parse_branch_protection(
         "generate human-readable, predictable names for codegen units (default: no)"),
     identify_regions: bool = (false, parse_bool, [UNTRACKED],
         "display unnamed regions as `'<id>`, using a non-ident unique id (default: no)"),
+    incremental_cache_limit: Option<u64> = (None, parse_opt_byte_size, [UNTRACKED],
+        "once the incremental compilation directory takes up more than this many bytes \
+        (`K`, `M`, `G` and `T` multipliers are accepted), evict the least recently used \
+        sessions of all the crates in it"),
+    incremental_explain: Option<IncrementalExplainFormat> = (None,
+        parse_incremental_explain, [UNTRACKED],
+        "explain why each codegen unit that isn't reused from the previous incremental session \
+        is recompiled, by the chain of changed queries leading to it from the changed input \
+        (`-Zincremental-explain=json` prints the explanations as JSON)"),
     incremental_ignore_spans: bool = (false, parse_bool, [UNTRACKED],
         "ignore spans during ICH computation -- used for testing (default: no)"),
     incremental_info: bool = (false, parse_bool, [UNTRACKED],
@@ -1361,8 +1565,14 @@ pub(crate) fn parse_branch_protection(
         "use like `-Zmir-enable-passes=+DestProp,-InstCombine`. Forces the specified passes to be \
         enabled, overriding all other checks. Passes that are not specified are enabled or \
         disabled by other flags as usual."),
+    mir_json_phase: MirJsonPhase = (MirJsonPhase::Optimized, parse_mir_json_phase, [UNTRACKED],
+        "the phase of the MIR written by `--emit=mir-json`: `built`, `analysis` (the MIR checked \
+        by the borrow checker), or `optimized` (default)"),
     mir_opt_level: Option<usize> = (None, parse_opt_number, [TRACKED],
         "MIR optimization level (0-4; default: 1 in non optimized builds and 2 in optimized builds)"),
+    mono_bloat_report: Option<MonoBloatReportFormat> = (None, parse_mono_bloat_report, [UNTRACKED],
+        "print the instantiations of each generic definition, with their sizes and the crates \
+        that caused them (`-Zmono-bloat-report=json` prints them as JSON)"),
     move_size_limit: Option<usize> = (None, parse_opt_number, [TRACKED],
         "the size at which the `large_assignments` lint starts to be emitted"),
     mutable_noalias: Option<bool> = (None, parse_opt_bool, [TRACKED],
@@ -1471,8 +1681,12 @@ pub(crate) fn parse_branch_protection(
         "make float->int casts UB-free: numbers outside the integer type's range are clipped to \
         the max/min integer respectively, and NaN is mapped to 0 (default: yes)"),
     save_analysis: bool = (false, parse_bool, [UNTRACKED],
-        "write syntax and type analysis (in JSON format) information, in \
-        addition to normal output (default: no)"),
+        "write syntax and type analysis information (in the format set by \
+        `-Z save-analysis-format`), in addition to normal output (default: no)"),
+    save_analysis_format: SaveAnalysisFormat = (SaveAnalysisFormat::Json,
+        parse_save_analysis_format, [UNTRACKED],
+        "the format of the analysis written by `-Z save-analysis`: `json` or `lsif` \
+        (default: `json`)"),
     self_profile: SwitchWithOptPath = (SwitchWithOptPath::Disabled,
         parse_switch_with_opt_path, [UNTRACKED],
         "run the self profiler and output the raw event data"),
@@ -1484,6 +1698,9 @@ pub(crate) fn parse_branch_protection(
                      query-blocked, incr-cache-load, incr-result-hashing, query-keys, function-args, args, llvm, artifact-sizes"),
     share_generics: Option<bool> = (None, parse_opt_bool, [TRACKED],
         "make the current crate share its generic instantiations"),
+    shared_object_cache: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "a directory of object files shared by the incremental compilation sessions of \
+        several checkouts, reused when the inputs of a codegen unit are identical"),
     show_span: Option<String> = (None, parse_opt_string, [TRACKED],
         "show spans for compiler debugging (expr|pat|ty)"),
     span_debug: bool = (false, parse_bool, [UNTRACKED],
@@ -1517,6 +1734,17 @@ pub(crate) fn parse_branch_protection(
         "show extended diagnostic help (default: no)"),
     temps_dir: Option<String> = (None, parse_opt_string, [UNTRACKED],
         "the directory the intermediate files are written to"),
+    terminal_hyperlinks: HyperlinkConfig = (HyperlinkConfig::Never,
+        parse_terminal_hyperlinks, [UNTRACKED],
+        "wrap the file locations and error codes of diagnostics in terminal hyperlinks: \
+        `auto` (or no value) when the terminal is known to support them, `always`, or \
+        `never` (default: never)"),
+    terminal_hyperlinks_error_code_url: Option<String> = (None, parse_opt_string, [UNTRACKED],
+        "the URL that `-Z terminal-hyperlinks` links error codes to, with a `{code}` \
+        placeholder (default: the error index)"),
+    terminal_hyperlinks_file_url: Option<String> = (None, parse_opt_string, [UNTRACKED],
+        "the URL that `-Z terminal-hyperlinks` links file locations to, with `{path}`, \
+        `{line}` and `{col}` placeholders (default: `file://{path}`)"),
     terminal_width: Option<usize> = (None, parse_opt_number, [UNTRACKED],
         "set the current terminal width"),
     // Diagnostics are considered side-effects of a query (see `QuerySideEffects`) and are saved
@@ -16,7 +16,7 @@
 use rustc_span::edition::Edition;
 use rustc_span::hygiene::ExpnId;
 use rustc_span::source_map::{FilePathMapping, SourceMap};
-use rustc_span::{Span, Symbol};
+use rustc_span::{SourceFileHash, Span, Symbol};
 
 use std::str;
 
@@ -160,8 +160,11 @@ pub struct ParseSess {
     pub reached_eof: Lock<bool>,
     /// Environment variables accessed during the build and their values when they exist.
     pub env_depinfo: Lock<FxHashSet<(Symbol, Option<Symbol>)>>,
-    /// File paths accessed during the build.
-    pub file_depinfo: Lock<FxHashSet<Symbol>>,
+    /// File paths accessed during the build, and the hashes of the contents they
+    /// had when they were tracked, if they could be read.
+    pub file_depinfo: Lock<FxHashMap<Symbol, Option<SourceFileHash>>>,
+    /// Files included with `include_bytes!` and the hashes of their contents.
+    pub binary_depinfo: Lock<FxHashMap<Symbol, SourceFileHash>>,
     /// All the type ascriptions expressions that have had a suggestion for likely path typo.
     pub type_ascription_path_suggestions: Lock<FxHashSet<Span>>,
     /// Whether cfg(version) should treat the current release as incomplete
@@ -204,6 +207,7 @@ pub fn with_span_handler(handler: Handler, source_map: Lrc<SourceMap>) -> Self {
             reached_eof: Lock::new(false),
             env_depinfo: Default::default(),
             file_depinfo: Default::default(),
+            binary_depinfo: Default::default(),
             type_ascription_path_suggestions: Default::default(),
             assume_incomplete_release: false,
             proc_macro_quoted_spans: Default::default(),
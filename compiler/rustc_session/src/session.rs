@@ -1,7 +1,9 @@
+use crate::apply_suggestions;
 use crate::cgu_reuse_tracker::CguReuseTracker;
 use crate::code_stats::CodeStats;
-pub use crate::code_stats::{DataTypeKind, FieldInfo, SizeKind, VariantInfo};
+pub use crate::code_stats::{DataTypeKind, FieldInfo, MonoItemInfo, SizeKind, VariantInfo};
 use crate::config::{self, CrateType, OutputType, SwitchWithOptPath};
+use crate::diagnostic_baseline::DiagnosticBaseline;
 use crate::parse::ParseSess;
 use crate::search_paths::{PathKind, SearchPath};
 use crate::{filesearch, lint};
@@ -16,14 +18,18 @@
     self, AtomicU64, AtomicUsize, Lock, Lrc, OnceCell, OneThread, Ordering, Ordering::SeqCst,
 };
 use rustc_errors::annotate_snippet_emitter_writer::AnnotateSnippetEmitterWriter;
-use rustc_errors::emitter::{Emitter, EmitterWriter, HumanReadableErrorType};
+use rustc_errors::emitter::{
+    Emitter, EmitterWriter, HumanReadableErrorType, HyperlinkConfig, Hyperlinks,
+};
 use rustc_errors::json::JsonEmitter;
 use rustc_errors::registry::Registry;
+use rustc_errors::sarif::SarifEmitter;
 use rustc_errors::{
     fallback_fluent_bundle, DiagnosticBuilder, DiagnosticId, DiagnosticMessage, EmissionGuarantee,
     ErrorGuaranteed, FluentBundle, LazyFallbackBundle, MultiSpan,
 };
 use rustc_macros::HashStable_Generic;
+use rustc_serialize::json::Json;
 pub use rustc_span::def_id::StableCrateId;
 use rustc_span::edition::Edition;
 use rustc_span::source_map::{FileLoader, RealFileLoader, SourceMap, Span};
@@ -149,8 +155,12 @@ pub struct Session {
     features: OnceCell<rustc_feature::Features>,
 
     incr_comp_session: OneThread<RefCell<IncrCompSession>>,
-    /// Used for incremental compilation tests. Will only be populated if
-    /// `-Zquery-dep-graph` is specified.
+    /// The tracked options that changed since the previous incremental session,
+    /// discarding its dependency graph. Used by `-Z incremental-explain`.
+    pub incr_comp_changed_options: OnceCell<Vec<String>>,
+    /// Used for incremental compilation tests and `-Zincremental-explain`. Will
+    /// only be populated if `-Zquery-dep-graph` or `-Zincremental-explain` is
+    /// specified.
     pub cgu_reuse_tracker: CguReuseTracker,
 
     /// Used by `-Z self-profile`.
@@ -162,6 +172,10 @@ pub struct Session {
     /// Data about code being compiled, gathered during compilation.
     pub code_stats: CodeStats,
 
+    /// The MIR bodies written by `--emit=mir-json` for the phases before `optimized`,
+    /// recorded as they are built since they are stolen by the next phase.
+    pub mir_json_bodies: Lock<Vec<Json>>,
+
     /// Tracks fuel info if `-zfuel=crate=n` is specified.
     optimization_fuel: Lock<OptimizationFuel>,
 
@@ -194,6 +208,9 @@ pub struct Session {
 
     /// Set of enabled features for the current target.
     pub target_features: FxHashSet<Symbol>,
+
+    /// The lints to allow, or the emitted lints, with `-Z diagnostic-baseline`.
+    pub diagnostic_baseline: Option<Lrc<DiagnosticBaseline>>,
 }
 
 pub struct PerfStats {
@@ -251,6 +268,17 @@ fn check_miri_unleashed_features(&self) {
     /// Invoked all the way at the end to finish off diagnostics printing.
     pub fn finish_diagnostics(&self, registry: &Registry) {
         self.check_miri_unleashed_features();
+        if let Some(mode) = &self.opts.debugging_opts.apply_suggestions {
+            apply_suggestions::apply_suggestions(self, mode);
+        }
+        if let Some(baseline) = &self.diagnostic_baseline {
+            if let Err(err) = baseline.save() {
+                self.err(&format!(
+                    "failed to write diagnostic baseline `{}`: {err}",
+                    baseline.path().display()
+                ));
+            }
+        }
         self.diagnostic().print_error_count(registry);
         self.emit_future_breakage();
     }
@@ -498,6 +526,7 @@ pub fn delay_good_path_bug(&self, msg: impl Into<DiagnosticMessage>) {
             || self.opts.debugging_opts.dump_mir.is_some()
             || self.opts.debugging_opts.unpretty.is_some()
             || self.opts.output_types.contains_key(&OutputType::Mir)
+            || self.opts.output_types.contains_key(&OutputType::MirJson)
             || std::env::var_os("RUSTC_LOG").is_some()
         {
             return;
@@ -1101,6 +1130,19 @@ fn default_emitter(
     emitter_dest: Option<Box<dyn Write + Send>>,
 ) -> Box<dyn Emitter + sync::Send> {
     let macro_backtrace = sopts.debugging_opts.macro_backtrace;
+    let hyperlinks = |config| {
+        Hyperlinks::new(
+            config,
+            sopts.debugging_opts.terminal_hyperlinks_file_url.clone(),
+            sopts.debugging_opts.terminal_hyperlinks_error_code_url.clone(),
+        )
+    };
+    // Only stderr can be detected as a terminal: `auto` disables the hyperlinks
+    // of buffers and of the `rendered` field of JSON diagnostics.
+    let other_hyperlinks = match sopts.debugging_opts.terminal_hyperlinks {
+        HyperlinkConfig::Auto => None,
+        config => hyperlinks(config),
+    };
     match (sopts.error_format, emitter_dest) {
         (config::ErrorOutputType::HumanReadable(kind), dst) => {
             let (short, color_config) = kind.unzip();
@@ -1125,7 +1167,8 @@ fn default_emitter(
                         sopts.debugging_opts.teach,
                         sopts.debugging_opts.terminal_width,
                         macro_backtrace,
-                    ),
+                    )
+                    .hyperlinks(hyperlinks(sopts.debugging_opts.terminal_hyperlinks)),
                     Some(dst) => EmitterWriter::new(
                         dst,
                         Some(source_map),
@@ -1136,7 +1179,8 @@ fn default_emitter(
                         false, // no colors when writing to a buffer
                         None,  // no terminal width
                         macro_backtrace,
-                    ),
+                    )
+                    .hyperlinks(other_hyperlinks),
                 };
                 Box::new(emitter.ui_testing(sopts.debugging_opts.ui_testing))
             }
@@ -1152,7 +1196,8 @@ fn default_emitter(
                 sopts.debugging_opts.terminal_width,
                 macro_backtrace,
             )
-            .ui_testing(sopts.debugging_opts.ui_testing),
+            .ui_testing(sopts.debugging_opts.ui_testing)
+            .hyperlinks(other_hyperlinks),
         ),
         (config::ErrorOutputType::Json { pretty, json_rendered }, Some(dst)) => Box::new(
             JsonEmitter::new(
@@ -1166,8 +1211,15 @@ fn default_emitter(
                 sopts.debugging_opts.terminal_width,
                 macro_backtrace,
             )
-            .ui_testing(sopts.debugging_opts.ui_testing),
+            .ui_testing(sopts.debugging_opts.ui_testing)
+            .hyperlinks(other_hyperlinks),
         ),
+        (config::ErrorOutputType::Sarif, None) => {
+            Box::new(SarifEmitter::stderr(Some(registry), source_map, bundle, fallback_bundle))
+        }
+        (config::ErrorOutputType::Sarif, Some(dst)) => {
+            Box::new(SarifEmitter::new(dst, Some(registry), source_map, bundle, fallback_bundle))
+        }
     }
 }
 
@@ -1290,7 +1342,9 @@ pub fn build_session(
     });
     let print_fuel = AtomicU64::new(0);
 
-    let cgu_reuse_tracker = if sopts.debugging_opts.query_dep_graph {
+    let cgu_reuse_tracker = if sopts.debugging_opts.query_dep_graph
+        || sopts.debugging_opts.incremental_explain.is_some()
+    {
         CguReuseTracker::new()
     } else {
         CguReuseTracker::new_disabled()
@@ -1311,6 +1365,44 @@ pub fn build_session(
     let asm_arch =
         if target_cfg.allow_asm { InlineAsmArch::from_str(&target_cfg.arch).ok() } else { None };
 
+    let diagnostic_baseline = match &sopts.debugging_opts.diagnostic_baseline {
+        Some((mode, path)) => {
+            let source_file = local_crate_source_file.as_deref();
+            match DiagnosticBaseline::load(*mode, path, &sopts, source_file) {
+                Ok(baseline) => Some(Lrc::new(baseline)),
+                Err(err) => early_error(
+                    sopts.error_format,
+                    &format!("failed to read diagnostic baseline `{}`: {err}", path.display()),
+                ),
+            }
+        }
+        None => None,
+    };
+    if let Some(baseline) = &diagnostic_baseline {
+        let baseline = baseline.clone();
+        let source_map = parse_sess.clone_source_map();
+        parse_sess.span_diagnostic.set_warning_filter(Box::new(move |diagnostic| {
+            let code = match &diagnostic.code {
+                Some(DiagnosticId::Error(code)) => code.as_str(),
+                _ => "warning",
+            };
+            let message: String = diagnostic
+                .message
+                .iter()
+                .map(|(message, _)| match message {
+                    DiagnosticMessage::Str(message) => message.as_str(),
+                    DiagnosticMessage::FluentIdentifier(id, _) => &**id,
+                })
+                .collect();
+            let snippet = diagnostic
+                .span
+                .primary_span()
+                .and_then(|span| source_map.span_to_snippet(span).ok())
+                .unwrap_or_default();
+            baseline.allows_warning(code, &message, &snippet)
+        }));
+    }
+
     let sess = Session {
         target: target_cfg,
         host,
@@ -1324,6 +1416,7 @@ pub fn build_session(
         stable_crate_id: OnceCell::new(),
         features: OnceCell::new(),
         incr_comp_session: OneThread::new(RefCell::new(IncrCompSession::NotInitialized)),
+        incr_comp_changed_options: OnceCell::new(),
         cgu_reuse_tracker,
         prof,
         perf_stats: PerfStats {
@@ -1333,6 +1426,7 @@ pub fn build_session(
             normalize_projection_ty: AtomicUsize::new(0),
         },
         code_stats: Default::default(),
+        mir_json_bodies: Default::default(),
         optimization_fuel,
         print_fuel,
         jobserver: jobserver::client(),
@@ -1341,6 +1435,7 @@ pub fn build_session(
         miri_unleashed_features: Lock::new(Default::default()),
         asm_arch,
         target_features: FxHashSet::default(),
+        diagnostic_baseline,
     };
 
     validate_commandline_args_with_session_available(&sess);
@@ -1488,6 +1583,7 @@ fn early_error_handler(output: config::ErrorOutputType) -> rustc_errors::Handler
         config::ErrorOutputType::Json { pretty, json_rendered } => {
             Box::new(JsonEmitter::basic(pretty, json_rendered, None, fallback_bundle, None, false))
         }
+        config::ErrorOutputType::Sarif => Box::new(SarifEmitter::basic(None, fallback_bundle)),
     };
     rustc_errors::Handler::with_emitter(true, None, emitter)
 }
@@ -1432,11 +1432,24 @@ pub fn raw_encode_syntax_context<E: Encoder>(
     ctxt: SyntaxContext,
     context: &HygieneEncodeContext,
     e: &mut E,
+) -> Result<(), E::Error> {
+    remapped_encode_syntax_context(ctxt, context, e, |_, raw_id| raw_id)
+}
+
+/// Like `raw_encode_syntax_context`, but encodes the id that `remap` returns for the
+/// raw id of `ctxt` instead. The same mapping must be applied to the ids passed to the
+/// `encode_ctxt` closure of `HygieneEncodeContext::encode`.
+pub fn remapped_encode_syntax_context<E: Encoder>(
+    ctxt: SyntaxContext,
+    context: &HygieneEncodeContext,
+    e: &mut E,
+    remap: impl FnOnce(&mut E, u32) -> u32,
 ) -> Result<(), E::Error> {
     if !context.serialized_ctxts.lock().contains(&ctxt) {
         context.latest_ctxts.lock().insert(ctxt);
     }
-    ctxt.0.encode(e)
+    let id = remap(e, ctxt.0);
+    id.encode(e)
 }
 
 impl<E: Encoder> Encodable<E> for SyntaxContext {
@@ -207,6 +207,10 @@ pub fn path_mapping(&self) -> &FilePathMapping {
         &self.path_mapping
     }
 
+    pub fn hash_kind(&self) -> SourceFileHashAlgorithm {
+        self.hash_kind
+    }
+
     pub fn file_exists(&self, path: &Path) -> bool {
         self.file_loader.file_exists(path)
     }
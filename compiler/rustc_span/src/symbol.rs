@@ -1408,6 +1408,7 @@
         test_case,
         test_removed_feature,
         test_runner,
+        test_timeout,
         test_unstable_lint,
         then_with,
         thread,
@@ -1448,6 +1449,7 @@
         type_length_limit,
         type_macros,
         type_name,
+        typed_holes,
         u128,
         u16,
         u32,
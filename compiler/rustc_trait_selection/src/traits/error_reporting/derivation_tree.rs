@@ -0,0 +1,345 @@
+//! Derivation trees of unsatisfied trait obligations (`-Z explain-trait-errors`).
+//!
+//! The tree starts at the obligation that was originally required, and follows
+//! the impls through which it required the unsatisfied obligation. For each
+//! unsatisfied trait obligation, it lists the impls that selection considered
+//! and why each of them was rejected. An impl rejected because one of its
+//! where-clauses doesn't hold explains these where-clauses in turn. Each
+//! obligation is only explained once per tree, its other occurrences refer to
+//! that explanation, so that obligations required through many impls don't
+//! make the tree grow exponentially.
+
+use crate::infer::InferCtxt;
+use crate::traits::query::evaluate_obligation::InferCtxtExt as _;
+use crate::traits::specialize::to_pretty_impl_header;
+use crate::traits::{Obligation, ObligationCause, ObligationCauseCode, PredicateObligation};
+use rustc_data_structures::fx::FxHashSet;
+use rustc_errors::Diagnostic;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::subst::Subst;
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_serialize::json::as_json;
+use rustc_session::config::ExplainTraitErrorsFormat;
+
+/// How many levels of where-clauses of rejected impls are explained.
+const MAX_DEPTH: usize = 4;
+
+#[derive(Encodable)]
+struct Node {
+    /// The obligation, e.g. `Vec<Foo>: Builder`.
+    predicate: String,
+    /// The impl selected for this obligation, whose where-clauses required
+    /// `requirements`. Requirements of built-in impls have no selected impl.
+    selected_impl: Option<Impl>,
+    /// The obligations this one required that aren't satisfied.
+    requirements: Vec<Node>,
+    /// For an unsatisfied trait obligation, the impls considered for it. `None`
+    /// for other obligations, and for those too deep to be explained.
+    candidates: Option<Vec<Candidate>>,
+    /// Whether the obligation was already explained earlier in the tree, in
+    /// which case its candidates aren't listed again.
+    explained_above: bool,
+}
+
+#[derive(Encodable)]
+struct Impl {
+    header: String,
+    span: String,
+}
+
+#[derive(Encodable)]
+struct Candidate {
+    header: String,
+    span: String,
+    /// Why the impl was rejected: `unification`, `where_clause` or `negative_impl`.
+    rejection: String,
+    /// For `unification`, the trait reference the impl provides.
+    impl_trait_ref: Option<String>,
+    /// For `where_clause`, the where-clauses of the impl that don't hold.
+    unsatisfied: Vec<Node>,
+}
+
+pub trait InferCtxtExt<'tcx> {
+    /// With `-Z explain-trait-errors`, explains why `obligation` isn't satisfied:
+    /// as a note of `err`, or as JSON on the standard output.
+    fn note_derivation_tree(&self, err: &mut Diagnostic, obligation: &PredicateObligation<'tcx>);
+}
+
+impl<'a, 'tcx> InferCtxtExt<'tcx> for InferCtxt<'a, 'tcx> {
+    fn note_derivation_tree(&self, err: &mut Diagnostic, obligation: &PredicateObligation<'tcx>) {
+        let Some(format) = self.tcx.sess.opts.debugging_opts.explain_trait_errors else {
+            return;
+        };
+        let tree = self.derivation_tree(obligation);
+        match format {
+            ExplainTraitErrorsFormat::Human => {
+                let mut rendered = String::new();
+                render(&tree, "", &mut rendered);
+                err.note(&format!("derivation tree of the unsatisfied obligation:\n{rendered}"));
+            }
+            ExplainTraitErrorsFormat::Json => println!("{}", as_json(&tree)),
+        }
+    }
+}
+
+trait InferCtxtPrivExt<'tcx> {
+    fn derivation_tree(&self, obligation: &PredicateObligation<'tcx>) -> Node;
+
+    fn explain_unsatisfied(
+        &self,
+        obligation: &PredicateObligation<'tcx>,
+        depth: usize,
+        explained: &mut FxHashSet<ty::Predicate<'tcx>>,
+    ) -> Node;
+
+    fn explain_candidate(
+        &self,
+        obligation: &PredicateObligation<'tcx>,
+        trait_pred: ty::PolyTraitPredicate<'tcx>,
+        impl_def_id: DefId,
+        depth: usize,
+        explained: &mut FxHashSet<ty::Predicate<'tcx>>,
+    ) -> Option<Candidate>;
+}
+
+impl<'a, 'tcx> InferCtxtPrivExt<'tcx> for InferCtxt<'a, 'tcx> {
+    fn derivation_tree(&self, obligation: &PredicateObligation<'tcx>) -> Node {
+        let mut node = self.explain_unsatisfied(obligation, 0, &mut FxHashSet::default());
+        // Each derived obligation records the obligation it was derived from, so
+        // the chain is walked from the unsatisfied obligation up to the root.
+        let mut code = obligation.cause.code();
+        loop {
+            let (parent_trait_pred, impl_def_id, parent_code) = match code {
+                ObligationCauseCode::ImplDerivedObligation(cause) => (
+                    cause.derived.parent_trait_pred,
+                    Some(cause.impl_def_id),
+                    &*cause.derived.parent_code,
+                ),
+                ObligationCauseCode::BuiltinDerivedObligation(derived)
+                | ObligationCauseCode::DerivedObligation(derived) => {
+                    (derived.parent_trait_pred, None, &*derived.parent_code)
+                }
+                ObligationCauseCode::FunctionArgumentObligation { parent_code, .. } => {
+                    code = parent_code;
+                    continue;
+                }
+                _ => break,
+            };
+            node = Node {
+                predicate: self.resolve_vars_if_possible(parent_trait_pred).to_string(),
+                selected_impl: impl_def_id.map(|impl_def_id| Impl {
+                    header: impl_header(self.tcx, impl_def_id),
+                    span: impl_span(self.tcx, impl_def_id),
+                }),
+                requirements: vec![node],
+                candidates: None,
+                explained_above: false,
+            };
+            code = parent_code;
+        }
+        node
+    }
+
+    fn explain_unsatisfied(
+        &self,
+        obligation: &PredicateObligation<'tcx>,
+        depth: usize,
+        explained: &mut FxHashSet<ty::Predicate<'tcx>>,
+    ) -> Node {
+        let predicate = self.resolve_vars_if_possible(obligation.predicate);
+        let mut node = Node {
+            predicate: predicate.to_string(),
+            selected_impl: None,
+            requirements: Vec::new(),
+            candidates: None,
+            explained_above: false,
+        };
+        let Some(trait_pred) = predicate.to_opt_poly_trait_pred() else {
+            return node;
+        };
+        if !explained.insert(predicate) {
+            node.explained_above = true;
+            return node;
+        }
+
+        // The impls selection considers, see `assemble_candidates_from_impls`.
+        let mut impls = Vec::new();
+        self.tcx.for_each_relevant_impl(
+            trait_pred.def_id(),
+            trait_pred.skip_binder().self_ty(),
+            |impl_def_id| impls.push(impl_def_id),
+        );
+        node.candidates = Some(
+            impls
+                .into_iter()
+                .filter_map(|impl_def_id| {
+                    self.explain_candidate(obligation, trait_pred, impl_def_id, depth, explained)
+                })
+                .collect(),
+        );
+        node
+    }
+
+    /// Returns why the impl doesn't apply to `trait_pred`, or `None` if it may.
+    fn explain_candidate(
+        &self,
+        obligation: &PredicateObligation<'tcx>,
+        trait_pred: ty::PolyTraitPredicate<'tcx>,
+        impl_def_id: DefId,
+        depth: usize,
+        explained: &mut FxHashSet<ty::Predicate<'tcx>>,
+    ) -> Option<Candidate> {
+        let tcx = self.tcx;
+        let candidate = |rejection: &str| Candidate {
+            header: impl_header(tcx, impl_def_id),
+            span: impl_span(tcx, impl_def_id),
+            rejection: rejection.to_string(),
+            impl_trait_ref: None,
+            unsatisfied: Vec::new(),
+        };
+
+        self.probe(|_| {
+            let cause = ObligationCause::dummy();
+            let impl_substs = self.fresh_substs_for_item(obligation.cause.span, impl_def_id);
+            let impl_trait_ref = tcx.impl_trait_ref(impl_def_id)?.subst(tcx, impl_substs);
+            let trait_ref = self.replace_bound_vars_with_placeholders(trait_pred).trait_ref;
+            if self.at(&cause, obligation.param_env).eq(trait_ref, impl_trait_ref).is_err() {
+                let impl_trait_ref = tcx.impl_trait_ref(impl_def_id)?;
+                return Some(Candidate {
+                    impl_trait_ref: Some(format!(
+                        "{}: {}",
+                        impl_trait_ref.self_ty(),
+                        impl_trait_ref.print_only_trait_path()
+                    )),
+                    ..candidate("unification")
+                });
+            }
+
+            if tcx.impl_polarity(impl_def_id) == ty::ImplPolarity::Negative {
+                return Some(candidate("negative_impl"));
+            }
+
+            let unsatisfied: Vec<_> = tcx
+                .predicates_of(impl_def_id)
+                .instantiate(tcx, impl_substs)
+                .predicates
+                .into_iter()
+                .map(|predicate| {
+                    Obligation::new(
+                        obligation.cause.clone(),
+                        obligation.param_env,
+                        self.resolve_vars_if_possible(predicate),
+                    )
+                })
+                .filter(|where_clause| !self.predicate_may_hold(where_clause))
+                .map(|where_clause| {
+                    if depth < MAX_DEPTH {
+                        self.explain_unsatisfied(&where_clause, depth + 1, explained)
+                    } else {
+                        Node {
+                            predicate: where_clause.predicate.to_string(),
+                            selected_impl: None,
+                            requirements: Vec::new(),
+                            candidates: None,
+                            explained_above: false,
+                        }
+                    }
+                })
+                .collect();
+            if unsatisfied.is_empty() {
+                return None;
+            }
+            Some(Candidate { unsatisfied, ..candidate("where_clause") })
+        })
+    }
+}
+
+fn impl_header(tcx: TyCtxt<'_>, impl_def_id: DefId) -> String {
+    let Some(header) = to_pretty_impl_header(tcx, impl_def_id) else {
+        return tcx.def_path_str(impl_def_id);
+    };
+    // Keep the header on a single line of the tree. It leaves out the polarity.
+    let header = header.trim_end_matches(';').replace("\n  where ", " where ");
+    match tcx.impl_trait_ref(impl_def_id) {
+        Some(trait_ref) if tcx.impl_polarity(impl_def_id) == ty::ImplPolarity::Negative => {
+            let path = trait_ref.print_only_trait_path().to_string();
+            header.replacen(&format!(" {path} for "), &format!(" !{path} for "), 1)
+        }
+        _ => header,
+    }
+}
+
+fn impl_span(tcx: TyCtxt<'_>, impl_def_id: DefId) -> String {
+    tcx.sess.source_map().span_to_diagnostic_string(tcx.def_span(impl_def_id))
+}
+
+enum Child<'a> {
+    Node(&'a Node),
+    /// A line followed by the nodes it introduces.
+    Group(String, &'a [Node]),
+    Line(String),
+}
+
+/// Renders `node` and its descendants as a tree, one line per node.
+fn render(node: &Node, prefix: &str, out: &mut String) {
+    out.push_str(&format!("`{}`\n", node.predicate));
+
+    let mut children = Vec::new();
+    match &node.selected_impl {
+        Some(selected_impl) => children.push(Child::Group(
+            format!("via `{}` at {}", selected_impl.header, selected_impl.span),
+            &node.requirements,
+        )),
+        None => children.extend(node.requirements.iter().map(Child::Node)),
+    }
+    if node.explained_above {
+        children.push(Child::Line("explained above".to_string()));
+    }
+    match &node.candidates {
+        Some(candidates) if candidates.is_empty() && node.requirements.is_empty() => {
+            children.push(Child::Line("no impls were considered".to_string()));
+        }
+        Some(candidates) => {
+            for candidate in candidates {
+                let rejected = format!("rejected `{}` at {}", candidate.header, candidate.span);
+                children.push(match candidate.rejection.as_str() {
+                    "unification" => Child::Line(format!(
+                        "{rejected}: `{}` doesn't unify with `{}`",
+                        node.predicate,
+                        candidate.impl_trait_ref.as_deref().unwrap_or_default(),
+                    )),
+                    "negative_impl" => Child::Line(format!("{rejected}: the impl is negative")),
+                    _ => Child::Group(
+                        format!("{rejected}: its where-clauses don't hold"),
+                        &candidate.unsatisfied,
+                    ),
+                });
+            }
+        }
+        None => {}
+    }
+    render_children(children, prefix, out);
+}
+
+fn render_children(children: Vec<Child<'_>>, prefix: &str, out: &mut String) {
+    let len = children.len();
+    for (i, child) in children.into_iter().enumerate() {
+        let (branch, indent) =
+            if i + 1 == len { ("└── ", "    ") } else { ("├── ", "│   ") };
+        let child_prefix = format!("{prefix}{indent}");
+        out.push_str(prefix);
+        out.push_str(branch);
+        match child {
+            Child::Node(node) => render(node, &child_prefix, out),
+            Child::Group(line, nodes) => {
+                out.push_str(&line);
+                out.push('\n');
+                render_children(nodes.iter().map(Child::Node).collect(), &child_prefix, out);
+            }
+            Child::Line(line) => {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+mod derivation_tree;
 pub mod on_unimplemented;
 pub mod suggestions;
 
@@ -40,6 +41,7 @@
 use crate::traits::query::evaluate_obligation::InferCtxtExt as _;
 use crate::traits::query::normalize::AtExt as _;
 use crate::traits::specialize::to_pretty_impl_header;
+use derivation_tree::InferCtxtExt as _;
 use on_unimplemented::InferCtxtExt as _;
 use suggestions::InferCtxtExt as _;
 
@@ -467,6 +469,7 @@ fn report_selection_error(
                             have_alt_message,
                         ) {
                             self.note_obligation_cause(&mut err, &obligation);
+                            self.note_derivation_tree(&mut err, &obligation);
                             err.emit();
                             return;
                         }
@@ -560,6 +563,7 @@ fn report_selection_error(
                         }
 
                         if self.suggest_impl_trait(&mut err, span, &obligation, trait_predicate) {
+                            self.note_derivation_tree(&mut err, &obligation);
                             err.emit();
                             return;
                         }
@@ -1070,6 +1074,7 @@ fn report_selection_error(
         };
 
         self.note_obligation_cause(&mut err, &obligation);
+        self.note_derivation_tree(&mut err, &obligation);
         self.point_at_returns_when_relevant(&mut err, &obligation);
 
         err.emit();
@@ -351,6 +351,7 @@ pub(super) fn check_expr_kind(
             ExprKind::Field(base, field) => self.check_field(expr, &base, field),
             ExprKind::Index(base, idx) => self.check_expr_index(base, idx, expr),
             ExprKind::Yield(value, ref src) => self.check_expr_yield(value, expr, src),
+            ExprKind::Hole => self.check_expr_hole(expr, expected),
             hir::ExprKind::Err => tcx.ty_error(),
         }
     }
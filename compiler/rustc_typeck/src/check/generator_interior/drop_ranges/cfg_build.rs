@@ -201,6 +201,7 @@ fn reinit_expr(&mut self, expr: &hir::Expr<'_>) {
             | ExprKind::Struct(..)
             | ExprKind::Repeat(..)
             | ExprKind::Yield(..)
+            | ExprKind::Hole
             | ExprKind::Err => (),
         }
     }
@@ -448,6 +449,7 @@ fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
             | ExprKind::DropTemps(..)
             | ExprKind::Err
             | ExprKind::Field(..)
+            | ExprKind::Hole
             | ExprKind::Index(..)
             | ExprKind::InlineAsm(..)
             | ExprKind::Let(..)
@@ -89,6 +89,7 @@
 mod region;
 mod regionck;
 pub mod rvalue_scopes;
+mod typed_holes;
 mod upvar;
 mod wfcheck;
 pub mod writeback;
@@ -490,6 +491,8 @@ fn typeck_with_fallback<'tcx>(
 
         fcx.select_all_obligations_or_error();
 
+        fcx.report_typed_holes(def_id.to_def_id());
+
         if !fcx.infcx.is_tainted_by_errors() {
             fcx.check_transmutes();
         }
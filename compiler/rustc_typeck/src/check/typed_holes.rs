@@ -0,0 +1,161 @@
+//! Typed holes (`#![feature(typed_holes)]`): `_` used as an expression is a
+//! placeholder whose type is reported once it is inferred, along with the locals
+//! in scope and the functions that produce a value of that type.
+
+use crate::check::{Expectation, FnCtxt};
+use crate::collect::get_infer_ret_ty;
+
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_infer::infer::type_variable::{TypeVariableOrigin, TypeVariableOriginKind};
+use rustc_middle::middle::region::{Scope, ScopeData};
+use rustc_middle::ty::{self, Ty, TypeFoldable};
+use std::mem;
+
+/// The maximum number of locals or functions listed for a hole.
+const MAX_CANDIDATES: usize = 8;
+
+impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
+    pub(super) fn check_expr_hole(
+        &self,
+        expr: &'tcx hir::Expr<'tcx>,
+        expected: Expectation<'tcx>,
+    ) -> Ty<'tcx> {
+        // A hole is always an error, reported by `report_typed_holes` once the types
+        // are inferred. Until then, don't report the ambiguities it may cause.
+        self.set_tainted_by_errors();
+        let ty = expected.only_has_type(self).unwrap_or_else(|| {
+            self.next_ty_var(TypeVariableOrigin {
+                kind: TypeVariableOriginKind::MiscVariable,
+                span: expr.span,
+            })
+        });
+        self.deferred_typed_holes.borrow_mut().push((expr, ty));
+        ty
+    }
+
+    pub(super) fn report_typed_holes(&self, def_id: DefId) {
+        let holes = mem::take(&mut *self.deferred_typed_holes.borrow_mut());
+        for (expr, ty) in holes {
+            let ty = self.resolve_vars_if_possible(ty);
+            let mut err = self.tcx.sess.struct_span_err(expr.span, "found a typed hole");
+            if ty.is_ty_var() || ty.references_error() {
+                err.span_label(expr.span, "the type of this hole can't be inferred");
+                err.emit();
+                continue;
+            }
+            err.span_label(expr.span, format!("expected a value of type `{ty}`"));
+            // Candidates of partially inferred types would be guesses.
+            if !ty.has_infer_types_or_consts() {
+                let locals = self.typed_hole_locals(def_id, expr, ty);
+                if !locals.is_empty() {
+                    err.note(format!("locals of type `{ty}` in scope: {}", list(locals)));
+                }
+                let functions = self.typed_hole_functions(expr, ty);
+                if !functions.is_empty() {
+                    err.note(format!("functions returning `{ty}`: {}", list(functions)));
+                }
+            }
+            err.emit();
+        }
+    }
+
+    /// Returns the names of the locals of type `ty` that are in scope at `hole`.
+    fn typed_hole_locals(&self, def_id: DefId, hole: &hir::Expr<'_>, ty: Ty<'tcx>) -> Vec<String> {
+        let scope_tree = self.tcx.region_scope_tree(def_id);
+        let hole_scope = Scope { id: hole.hir_id.local_id, data: ScopeData::Node };
+        let mut locals: Vec<_> = self
+            .locals
+            .borrow()
+            .iter()
+            .filter_map(|(&hir_id, local_ty)| {
+                let var_scope = scope_tree.var_scope(hir_id.local_id)?;
+                if !scope_tree.is_subscope_of(hole_scope, var_scope) {
+                    return None;
+                }
+                let local_ty = self.resolve_vars_if_possible(local_ty.decl_ty);
+                self.can_eq(self.param_env, local_ty, ty).ok()?;
+                Some(format!("`{}`", self.tcx.hir().name(hir_id)))
+            })
+            .collect();
+        locals.sort();
+        locals.dedup();
+        locals
+    }
+
+    /// Returns the names of the non-generic functions returning `ty` that are in
+    /// scope at `hole`: those of its module or of the bodies enclosing it, and those
+    /// imported into its module by name. The associated functions of `ty` are
+    /// included too, with their path.
+    fn typed_hole_functions(&self, hole: &hir::Expr<'_>, ty: Ty<'tcx>) -> Vec<String> {
+        let tcx = self.tcx;
+        let hir = tcx.hir();
+        let ty = tcx.erase_regions(ty);
+        let returns_ty = |def_id: DefId| {
+            // Inferring the signature of a function with a placeholder return type
+            // would type-check it, possibly cyclically.
+            if let Some(local_def_id) = def_id.as_local() {
+                let hir_id = tcx.hir().local_def_id_to_hir_id(local_def_id);
+                let decl = tcx.hir().fn_decl_by_hir_id(hir_id);
+                if decl.map_or(true, |decl| get_infer_ret_ty(&decl.output).is_some()) {
+                    return false;
+                }
+            }
+            // The output of generic functions would have to be instantiated.
+            tcx.generics_of(def_id).count() == 0
+                && tcx.erase_regions(tcx.erase_late_bound_regions(tcx.fn_sig(def_id).output()))
+                    == ty
+        };
+
+        // Besides the items of the module, those declared in the bodies enclosing
+        // the hole are in scope.
+        let module = tcx.parent_module(hole.hir_id);
+        let mut scopes: FxHashSet<LocalDefId> =
+            hir.parent_owner_iter(hole.hir_id).map(|(def_id, _)| def_id).collect();
+        scopes.insert(module);
+
+        let mut functions = Vec::new();
+        for item_id in hir.module_items(module) {
+            let item = hir.item(item_id);
+            if !scopes.contains(&hir.get_parent_item(item.hir_id())) {
+                continue;
+            }
+            let def_id = match item.kind {
+                hir::ItemKind::Fn(..) => item.def_id.to_def_id(),
+                hir::ItemKind::Use(path, hir::UseKind::Single) => match path.res {
+                    Res::Def(DefKind::Fn, def_id) => def_id,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if returns_ty(def_id) {
+                functions.push(format!("`{}`", item.ident));
+            }
+        }
+        if let ty::Adt(adt, _) = ty.kind() {
+            for &impl_def_id in tcx.inherent_impls(adt.did()) {
+                functions.extend(
+                    tcx.associated_items(impl_def_id)
+                        .in_definition_order()
+                        .filter(|item| item.kind == ty::AssocKind::Fn && returns_ty(item.def_id))
+                        .map(|item| format!("`{}`", tcx.def_path_str(item.def_id))),
+                );
+            }
+        }
+
+        functions.sort();
+        functions.dedup();
+        functions
+    }
+}
+
+fn list(mut candidates: Vec<String>) -> String {
+    if candidates.len() > MAX_CANDIDATES {
+        let others = candidates.len() - MAX_CANDIDATES;
+        candidates.truncate(MAX_CANDIDATES);
+        candidates.push(format!("and {others} others"));
+    }
+    candidates.join(", ")
+}
@@ -386,6 +386,7 @@ pub fn walk_expr(&mut self, expr: &hir::Expr<'_>) {
             hir::ExprKind::Continue(..)
             | hir::ExprKind::Lit(..)
             | hir::ExprKind::ConstBlock(..)
+            | hir::ExprKind::Hole
             | hir::ExprKind::Err => {}
 
             hir::ExprKind::Loop(blk, ..) => {
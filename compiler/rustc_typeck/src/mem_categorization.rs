@@ -382,6 +382,7 @@ pub(crate) fn cat_expr_unadjusted(
             | hir::ExprKind::Repeat(..)
             | hir::ExprKind::InlineAsm(..)
             | hir::ExprKind::Box(..)
+            | hir::ExprKind::Hole
             | hir::ExprKind::Err => Ok(self.cat_rvalue(expr.hir_id, expr.span, expr_ty)),
         }
     }
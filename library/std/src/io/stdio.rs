@@ -14,6 +14,7 @@
 use crate::sync::{Arc, Mutex, MutexGuard};
 use crate::sys::stdio;
 use crate::sys_common::remutex::{ReentrantMutex, ReentrantMutexGuard};
+use crate::thread::LocalKey;
 
 type LocalStream = Arc<Mutex<Vec<u8>>>;
 
@@ -21,10 +22,16 @@
     /// Used by the test crate to capture the output of the print macros and panics.
     static OUTPUT_CAPTURE: Cell<Option<LocalStream>> = {
         Cell::new(None)
+    };
+
+    /// Used by the test crate to capture the output of the eprint macros and
+    /// panics apart from `OUTPUT_CAPTURE`, which captures it when this is unset.
+    static ERROR_CAPTURE: Cell<Option<LocalStream>> = {
+        Cell::new(None)
     }
 }
 
-/// Flag to indicate OUTPUT_CAPTURE is used.
+/// Flag to indicate OUTPUT_CAPTURE or ERROR_CAPTURE is used.
 ///
 /// If it is None and was never set on any thread, this flag is set to false,
 /// and OUTPUT_CAPTURE can be safely ignored on all threads, saving some time
@@ -983,8 +990,28 @@ pub fn set_output_capture(sink: Option<LocalStream>) -> Option<LocalStream> {
     OUTPUT_CAPTURE.with(move |slot| slot.replace(sink))
 }
 
-/// Write `args` to the capture buffer if enabled and possible, or `global_s`
-/// otherwise. `label` identifies the stream in a panic message.
+/// Sets the thread-local capture buffer of the standard error stream and
+/// returns the old one. While it is unset, the standard error stream is
+/// captured by the buffer set with `set_output_capture`.
+#[unstable(
+    feature = "internal_output_capture",
+    reason = "this function is meant for use in the test crate \
+        and may disappear in the future",
+    issue = "none"
+)]
+#[doc(hidden)]
+pub fn set_error_capture(sink: Option<LocalStream>) -> Option<LocalStream> {
+    if sink.is_none() && !OUTPUT_CAPTURE_USED.load(Ordering::Relaxed) {
+        // ERROR_CAPTURE is definitely None since OUTPUT_CAPTURE_USED is false.
+        return None;
+    }
+    OUTPUT_CAPTURE_USED.store(true, Ordering::Relaxed);
+    ERROR_CAPTURE.with(move |slot| slot.replace(sink))
+}
+
+/// Write `args` to the first of the `captures` buffers that is enabled and
+/// possible, or `global_s` otherwise. `label` identifies the stream in a panic
+/// message.
 ///
 /// This function is used to print error messages, so it takes extra
 /// care to avoid causing a panic when `local_s` is unusable.
@@ -993,20 +1020,26 @@ pub fn set_output_capture(sink: Option<LocalStream>) -> Option<LocalStream> {
 /// thread, it will just fall back to the global stream.
 ///
 /// However, if the actual I/O causes an error, this function does panic.
-fn print_to<T>(args: fmt::Arguments<'_>, global_s: fn() -> T, label: &str)
-where
+fn print_to<T>(
+    args: fmt::Arguments<'_>,
+    global_s: fn() -> T,
+    label: &str,
+    captures: &[&'static LocalKey<Cell<Option<LocalStream>>>],
+) where
     T: Write,
 {
     if OUTPUT_CAPTURE_USED.load(Ordering::Relaxed)
-        && OUTPUT_CAPTURE.try_with(|s| {
-            // Note that we completely remove a local sink to write to in case
-            // our printing recursively panics/prints, so the recursive
-            // panic/print goes to the global sink instead of our local sink.
-            s.take().map(|w| {
-                let _ = w.lock().unwrap_or_else(|e| e.into_inner()).write_fmt(args);
-                s.set(Some(w));
-            })
-        }) == Ok(Some(()))
+        && captures.iter().any(|capture| {
+            capture.try_with(|s| {
+                // Note that we completely remove a local sink to write to in case
+                // our printing recursively panics/prints, so the recursive
+                // panic/print goes to the global sink instead of our local sink.
+                s.take().map(|w| {
+                    let _ = w.lock().unwrap_or_else(|e| e.into_inner()).write_fmt(args);
+                    s.set(Some(w));
+                })
+            }) == Ok(Some(()))
+        })
     {
         // Successfully wrote to capture buffer.
         return;
@@ -1025,7 +1058,7 @@ fn print_to<T>(args: fmt::Arguments<'_>, global_s: fn() -> T, label: &str)
 #[doc(hidden)]
 #[cfg(not(test))]
 pub fn _print(args: fmt::Arguments<'_>) {
-    print_to(args, stdout, "stdout");
+    print_to(args, stdout, "stdout", &[&OUTPUT_CAPTURE]);
 }
 
 #[unstable(
@@ -1036,7 +1069,7 @@ pub fn _print(args: fmt::Arguments<'_>) {
 #[doc(hidden)]
 #[cfg(not(test))]
 pub fn _eprint(args: fmt::Arguments<'_>) {
-    print_to(args, stderr, "stderr");
+    print_to(args, stderr, "stderr", &[&ERROR_CAPTURE, &OUTPUT_CAPTURE]);
 }
 
 #[cfg(test)]
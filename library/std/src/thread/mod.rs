@@ -485,6 +485,8 @@ unsafe fn spawn_unchecked_<'a, 'scope, F, T>(
 
         let output_capture = crate::io::set_output_capture(None);
         crate::io::set_output_capture(output_capture.clone());
+        let error_capture = crate::io::set_error_capture(None);
+        crate::io::set_error_capture(error_capture.clone());
 
         let main = move || {
             if let Some(name) = their_thread.cname() {
@@ -492,6 +494,7 @@ unsafe fn spawn_unchecked_<'a, 'scope, F, T>(
             }
 
             crate::io::set_output_capture(output_capture);
+            crate::io::set_error_capture(error_capture);
 
             // SAFETY: the stack guard passed is the one for the current thread.
             // This means the current thread's stack and the new thread's stack
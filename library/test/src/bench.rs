@@ -9,11 +9,27 @@
 
 use crate::stats;
 use std::cmp;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
 use std::io;
 use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// Number of samples the summary of a benchmark is computed from.
+const SAMPLE_COUNT: usize = 50;
+
+/// Significance level below which a change against a baseline is considered real.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// Environment variable overriding the directory benchmark baselines are stored in.
+pub const BASELINE_DIR_VAR: &str = "RUST_TEST_BASELINE_DIR";
+
+/// Default for `--baseline-threshold`, in percent.
+pub const DEFAULT_BASELINE_THRESHOLD: f64 = 5.0;
+
 /// An identity function that *__hints__* to the compiler to be maximally pessimistic about what
 /// `black_box` could do.
 ///
@@ -61,6 +77,8 @@ pub fn bench<F>(&mut self, mut f: F) -> Option<stats::Summary>
 #[derive(Debug, Clone, PartialEq)]
 pub struct BenchSamples {
     pub ns_iter_summ: stats::Summary,
+    /// Number of samples `ns_iter_summ` was computed from.
+    pub sample_count: usize,
     pub mb_s: usize,
 }
 
@@ -139,7 +157,7 @@ pub fn iter<T, F>(inner: &mut F) -> stats::Summary
     n = cmp::max(1, n);
 
     let mut total_run = Duration::new(0, 0);
-    let samples: &mut [f64] = &mut [0.0_f64; 50];
+    let samples: &mut [f64] = &mut [0.0_f64; SAMPLE_COUNT];
     loop {
         let loop_start = Instant::now();
 
@@ -215,14 +233,18 @@ pub fn benchmark<F>(
             let ns_iter = cmp::max(ns_iter_summ.median as u64, 1);
             let mb_s = bs.bytes * 1000 / ns_iter;
 
-            let bs = BenchSamples { ns_iter_summ, mb_s: mb_s as usize };
+            let bs = BenchSamples { ns_iter_summ, sample_count: SAMPLE_COUNT, mb_s: mb_s as usize };
             TestResult::TrBench(bs)
         }
         Ok(None) => {
             // iter not called, so no data.
             // FIXME: error in this case?
             let samples: &mut [f64] = &mut [0.0_f64; 1];
-            let bs = BenchSamples { ns_iter_summ: stats::Summary::new(samples), mb_s: 0 };
+            let bs = BenchSamples {
+                ns_iter_summ: stats::Summary::new(samples),
+                sample_count: samples.len(),
+                mb_s: 0,
+            };
             TestResult::TrBench(bs)
         }
         Err(_) => TestResult::TrFailed,
@@ -240,3 +262,158 @@ pub fn run_once<F>(f: F)
     let mut bs = Bencher { mode: BenchMode::Single, summary: None, bytes: 0 };
     bs.bench(f);
 }
+
+/// Options for saving benchmark results as a named baseline and for comparing them
+/// against one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BaselineOptions {
+    /// Baseline to save the results of this run under.
+    pub save: Option<String>,
+    /// Baseline to compare the results of this run against.
+    pub compare: Option<String>,
+    /// Slowdown in percent beyond which a significant change is reported as a regression.
+    pub threshold: f64,
+}
+
+/// Returns the file the baseline `name` is stored in. Baselines are kept in
+/// `RUST_TEST_BASELINE_DIR`, or next to the benchmark binary, and are specific to
+/// that binary.
+pub fn baseline_path(name: &str) -> PathBuf {
+    let exe = env::current_exe().unwrap_or_else(|_| PathBuf::from("bench"));
+    let dir = match env::var_os(BASELINE_DIR_VAR) {
+        Some(dir) => PathBuf::from(dir),
+        None => exe.parent().map_or_else(PathBuf::new, Path::to_path_buf).join("baselines"),
+    };
+    let exe_name = exe.file_name().map_or_else(|| "bench".into(), |n| n.to_string_lossy());
+    dir.join(format!("{exe_name}.{name}"))
+}
+
+/// What is kept of the results of a benchmark in a baseline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BaselineEntry {
+    median: f64,
+    mean: f64,
+    var: f64,
+    sample_count: usize,
+}
+
+/// Saved results of a set of benchmarks, keyed by benchmark name.
+///
+/// It is stored as one `<median>\t<mean>\t<variance>\t<sample count>\t<name>` line
+/// per benchmark.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchBaseline {
+    pub name: String,
+    entries: BTreeMap<String, BaselineEntry>,
+}
+
+impl BenchBaseline {
+    pub fn new(name: &str) -> BenchBaseline {
+        BenchBaseline { name: name.to_string(), entries: BTreeMap::new() }
+    }
+
+    pub fn load(name: &str, path: &Path) -> io::Result<BenchBaseline> {
+        let contents = fs::read_to_string(path)?;
+        let mut entries = BTreeMap::new();
+        for line in contents.lines() {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed benchmark baseline {}: {:?}", path.display(), line),
+                )
+            };
+            let mut fields = line.splitn(5, '\t');
+            let mut number =
+                || fields.next().and_then(|f| f.parse::<f64>().ok()).ok_or_else(invalid);
+            let (median, mean, var) = (number()?, number()?, number()?);
+            let sample_count = fields.next().and_then(|f| f.parse().ok()).ok_or_else(invalid)?;
+            let name = fields.next().ok_or_else(invalid)?;
+            entries.insert(name.to_string(), BaselineEntry { median, mean, var, sample_count });
+        }
+        Ok(BenchBaseline { name: name.to_string(), entries })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        use std::fmt::Write;
+        let mut contents = String::new();
+        for (name, e) in &self.entries {
+            // `Display` for floats round-trips, so nothing is lost.
+            writeln!(contents, "{}\t{}\t{}\t{}\t{}", e.median, e.mean, e.var, e.sample_count, name)
+                .unwrap();
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn record(&mut self, name: &str, bs: &BenchSamples) {
+        // Such names can't be stored on a single line.
+        if name.contains(&['\n', '\r'][..]) {
+            return;
+        }
+        let summ = &bs.ns_iter_summ;
+        let entry = BaselineEntry {
+            median: summ.median,
+            mean: summ.mean,
+            var: summ.var,
+            sample_count: bs.sample_count,
+        };
+        self.entries.insert(name.to_string(), entry);
+    }
+
+    /// Compares the results of the benchmark `name` against this baseline, if it
+    /// has results for it.
+    pub fn compare(
+        &self,
+        name: &str,
+        bs: &BenchSamples,
+        threshold: f64,
+    ) -> Option<BenchComparison> {
+        let entry = self.entries.get(name)?;
+        let summ = &bs.ns_iter_summ;
+        let p_value = stats::welch_t_test(
+            (entry.mean, entry.var, entry.sample_count),
+            (summ.mean, summ.var, bs.sample_count),
+        );
+        let change_pct =
+            if entry.mean == 0.0 { 0.0 } else { (summ.mean - entry.mean) / entry.mean * 100.0 };
+        let significant = p_value < SIGNIFICANCE_LEVEL;
+        Some(BenchComparison {
+            baseline: self.name.clone(),
+            baseline_median: entry.median,
+            median: summ.median,
+            change_pct,
+            p_value,
+            regression: significant && change_pct > threshold,
+            improvement: significant && change_pct < -threshold,
+        })
+    }
+}
+
+/// Change of a benchmark against its baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchComparison {
+    /// Name of the baseline compared against.
+    pub baseline: String,
+    pub baseline_median: f64,
+    pub median: f64,
+    /// Change of the mean time per iteration, in percent.
+    pub change_pct: f64,
+    /// Two-sided p-value of Welch's t-test on the (winsorized) samples.
+    pub p_value: f64,
+    /// Whether the benchmark got significantly slower, by more than the threshold.
+    pub regression: bool,
+    /// Whether the benchmark got significantly faster, by more than the threshold.
+    pub improvement: bool,
+}
+
+pub fn fmt_bench_comparison(cmp: &BenchComparison) -> String {
+    format!(
+        "{:+.2}% against `{}` ({} ns/iter, p = {:.3})",
+        cmp.change_pct,
+        cmp.baseline,
+        fmt_thousands_sep(cmp.baseline_median as usize, ','),
+        cmp.p_value
+    )
+}
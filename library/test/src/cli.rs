@@ -2,9 +2,12 @@
 
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use super::bench::{baseline_path, BaselineOptions, DEFAULT_BASELINE_THRESHOLD};
 use super::helpers::isatty;
-use super::options::{ColorConfig, Options, OutputFormat, RunIgnored};
+use super::options::{ColorConfig, Options, OutputFormat, Partition, RerunFailed, RunIgnored};
+use super::run_state::STATE_FILE_VAR;
 use super::time::TestTimeOptions;
 
 #[derive(Debug)]
@@ -13,6 +16,7 @@ pub struct TestOpts {
     pub filters: Vec<String>,
     pub filter_exact: bool,
     pub force_run_in_process: bool,
+    pub isolate: bool,
     pub exclude_should_panic: bool,
     pub run_ignored: RunIgnored,
     pub run_tests: bool,
@@ -25,7 +29,13 @@ pub struct TestOpts {
     pub shuffle_seed: Option<u64>,
     pub test_threads: Option<usize>,
     pub skip: Vec<String>,
+    pub partition: Option<Partition>,
+    pub rerun_failed: RerunFailed,
+    pub state_file: Option<PathBuf>,
+    pub retries: usize,
     pub time_options: Option<TestTimeOptions>,
+    pub baseline_options: Option<BaselineOptions>,
+    pub test_timeout: Option<Duration>,
     pub options: Options,
 }
 
@@ -49,6 +59,13 @@ fn optgroups() -> getopts::Options {
     opts.optflag("", "include-ignored", "Run ignored and not ignored tests")
         .optflag("", "ignored", "Run only ignored tests")
         .optflag("", "force-run-in-process", "Forces tests to run in-process when panic=abort")
+        .optflag(
+            "",
+            "isolate",
+            "Run each test in a separate process, so that tests cannot affect each
+            other through global state, leaked threads or by exiting the process.
+            Tests generated at runtime, such as doctests, still run in-process.",
+        )
         .optflag("", "exclude-should-panic", "Excludes tests marked as should_panic")
         .optflag("", "test", "Run tests and not benchmarks")
         .optflag("", "bench", "Run benchmarks instead of tests")
@@ -98,8 +115,9 @@ fn optgroups() -> getopts::Options {
             pretty = Print verbose output;
             terse  = Display one character per test;
             json   = Output a json document;
-            junit  = Output a JUnit document",
-            "pretty|terse|json|junit",
+            junit  = Output a JUnit document;
+            tap    = Output a Test Anything Protocol (version 14) stream",
+            "pretty|terse|json|junit|tap",
         )
         .optflag("", "show-output", "Show captured stdout of successful tests")
         .optopt(
@@ -138,6 +156,77 @@ fn optgroups() -> getopts::Options {
             `CRITICAL_TIME` here means the limit that should not be exceeded by test.
             ",
         )
+        .optopt(
+            "",
+            "test-timeout",
+            "Stop waiting for tests that are still running after SECS seconds and
+            report them as timed out. Tests run in their own process, with panic=abort
+            or `--isolate`, are killed; others are left running in the background.
+
+            The limit can be overridden for a single test with the
+            `#[test_timeout = \"SECS\"]` attribute.",
+            "SECS",
+        )
+        .optopt(
+            "",
+            "partition",
+            "Split the tests into COUNT partitions and only run the INDEX-th one
+            (counting from 1). Tests are assigned to partitions after filtering,
+            so running every partition with the same arguments runs every test
+            exactly once.",
+            "INDEX/COUNT",
+        )
+        .optflag(
+            "",
+            "failed-first",
+            "Run the tests that failed in the previous run before all others.
+            With --shuffle, both groups of tests are shuffled separately.
+
+            The outcome of every run is recorded in a state file stored next to
+            the test binary, or at the path given by the `RUST_TEST_STATE_FILE`
+            environment variable, which also enables recording without this flag.",
+        )
+        .optflag(
+            "",
+            "only-failed",
+            "Only run the tests that failed in the previous run, or all tests if
+            none did. See --failed-first for where the outcomes are recorded.",
+        )
+        .optopt(
+            "",
+            "retries",
+            "Run failing tests again up to N times, and report the tests that pass
+            on a later attempt as flaky rather than failed. Only #[test] functions
+            are retried: tests generated at runtime, such as doctests, and
+            benchmarks run as tests are not.",
+            "N",
+        )
+        .optopt(
+            "",
+            "save-baseline",
+            "Save the results of the benchmarks as the baseline NAME.
+
+            Baselines are stored next to the benchmark binary, or in the directory
+            given by the `RUST_TEST_BASELINE_DIR` environment variable.",
+            "NAME",
+        )
+        .optopt(
+            "",
+            "baseline",
+            "Compare the results of the benchmarks against the baseline NAME, and
+            fail if any benchmark got significantly slower.
+
+            A change is significant if Welch's t-test rejects that the mean time
+            per iteration is unchanged at the 5% level, and it exceeds the
+            threshold set by --baseline-threshold.",
+            "NAME",
+        )
+        .optopt(
+            "",
+            "baseline-threshold",
+            "Slowdown in percent that is tolerated by --baseline (default: 5)",
+            "PERCENT",
+        )
         .optflag("", "shuffle", "Run tests in random order")
         .optopt(
             "",
@@ -252,10 +341,22 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
 
     // Unstable flags
     let force_run_in_process = unstable_optflag!(matches, allow_unstable, "force-run-in-process");
+    let isolate = unstable_optflag!(matches, allow_unstable, "isolate");
+    if force_run_in_process && isolate {
+        return Err(
+            "the options --force-run-in-process and --isolate are mutually exclusive".into()
+        );
+    }
     let exclude_should_panic = unstable_optflag!(matches, allow_unstable, "exclude-should-panic");
     let time_options = get_time_options(&matches, allow_unstable)?;
+    let baseline_options = get_baseline_options(&matches, allow_unstable)?;
+    let test_timeout = get_test_timeout(&matches, allow_unstable)?;
     let shuffle = get_shuffle(&matches, allow_unstable)?;
     let shuffle_seed = get_shuffle_seed(&matches, allow_unstable)?;
+    let partition = get_partition(&matches, allow_unstable)?;
+    let rerun_failed = get_rerun_failed(&matches, allow_unstable)?;
+    let state_file = get_state_file(rerun_failed, allow_unstable)?;
+    let retries = get_retries(&matches, allow_unstable)?;
 
     let include_ignored = matches.opt_present("include-ignored");
     let quiet = matches.opt_present("quiet");
@@ -281,6 +382,7 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
         filters,
         filter_exact: exact,
         force_run_in_process,
+        isolate,
         exclude_should_panic,
         run_ignored,
         run_tests,
@@ -293,7 +395,13 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
         shuffle_seed,
         test_threads,
         skip,
+        partition,
+        rerun_failed,
+        state_file,
+        retries,
         time_options,
+        baseline_options,
+        test_timeout,
         options,
     };
 
@@ -329,6 +437,72 @@ fn get_time_options(
     Ok(options)
 }
 
+// Gets the CLI options associated with benchmark baselines.
+fn get_baseline_options(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<BaselineOptions>> {
+    let save = unstable_optopt!(matches, allow_unstable, "save-baseline");
+    let compare = unstable_optopt!(matches, allow_unstable, "baseline");
+    let threshold = unstable_optopt!(matches, allow_unstable, "baseline-threshold");
+
+    for name in save.iter().chain(&compare) {
+        if name.is_empty() || name.starts_with('.') || name.contains(&['/', '\\'][..]) {
+            return Err(format!("invalid baseline name `{name}`"));
+        }
+    }
+    if let Some(ref name) = compare {
+        let path = baseline_path(name);
+        if !path.exists() {
+            return Err(format!(
+                "baseline `{}` not found at {}, save it with --save-baseline first",
+                name,
+                path.display()
+            ));
+        }
+    }
+    let threshold = match threshold {
+        Some(t_str) => match t_str.parse::<f64>() {
+            Ok(t) if t >= 0.0 => t,
+            _ => {
+                return Err(format!(
+                    "argument for --baseline-threshold must be a non-negative number \
+                     (was {})",
+                    t_str
+                ));
+            }
+        },
+        None => DEFAULT_BASELINE_THRESHOLD,
+    };
+
+    if save.is_none() && compare.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(BaselineOptions { save, compare, threshold }))
+}
+
+fn get_test_timeout(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<Duration>> {
+    let test_timeout = match unstable_optopt!(matches, allow_unstable, "test-timeout") {
+        Some(n_str) => match n_str.parse::<u64>() {
+            Ok(0) => return Err("argument for --test-timeout must not be 0".to_string()),
+            Ok(n) => Some(Duration::from_secs(n)),
+            Err(e) => {
+                return Err(format!(
+                    "argument for --test-timeout must be a number > 0 \
+                     (error: {})",
+                    e
+                ));
+            }
+        },
+        None => None,
+    };
+
+    Ok(test_timeout)
+}
+
 fn get_shuffle(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<bool> {
     let mut shuffle = unstable_optflag!(matches, allow_unstable, "shuffle");
     if !shuffle && allow_unstable {
@@ -369,6 +543,94 @@ fn get_shuffle_seed(matches: &getopts::Matches, allow_unstable: bool) -> OptPart
     Ok(shuffle_seed)
 }
 
+fn get_partition(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<Partition>> {
+    let partition = match unstable_optopt!(matches, allow_unstable, "partition") {
+        Some(p_str) => {
+            let parsed = p_str
+                .split_once('/')
+                .and_then(|(index, count)| Some((index.parse().ok()?, count.parse().ok()?)));
+            match parsed {
+                Some((index, count)) if 0 < index && index <= count => {
+                    Some(Partition { index, count })
+                }
+                _ => {
+                    return Err(format!(
+                        "argument for --partition must be of the form INDEX/COUNT \
+                         with 1 <= INDEX <= COUNT (was {})",
+                        p_str
+                    ));
+                }
+            }
+        }
+        None => None,
+    };
+
+    Ok(partition)
+}
+
+fn get_rerun_failed(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<RerunFailed> {
+    let failed_first = unstable_optflag!(matches, allow_unstable, "failed-first");
+    let only_failed = unstable_optflag!(matches, allow_unstable, "only-failed");
+    let rerun_failed = match (failed_first, only_failed) {
+        (true, true) => {
+            return Err(
+                "the options --failed-first and --only-failed are mutually exclusive".into()
+            );
+        }
+        (true, false) => RerunFailed::First,
+        (false, true) => RerunFailed::Only,
+        (false, false) => RerunFailed::No,
+    };
+
+    Ok(rerun_failed)
+}
+
+fn get_state_file(rerun_failed: RerunFailed, allow_unstable: bool) -> OptPartRes<Option<PathBuf>> {
+    if allow_unstable {
+        if let Some(path) = env::var_os(STATE_FILE_VAR) {
+            return Ok(Some(PathBuf::from(path)));
+        }
+    }
+    if rerun_failed == RerunFailed::No {
+        return Ok(None);
+    }
+
+    // The test binary is specific to the crate under test, so its path makes
+    // for a default that separate test suites don't share.
+    match env::current_exe() {
+        Ok(exe) => {
+            let mut path = exe.into_os_string();
+            path.push(".test-state");
+            Ok(Some(PathBuf::from(path)))
+        }
+        Err(e) => Err(format!(
+            "failed to determine where to store the test state, \
+             set {STATE_FILE_VAR} to choose a path (error: {e})"
+        )),
+    }
+}
+
+fn get_retries(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<usize> {
+    let retries = match unstable_optopt!(matches, allow_unstable, "retries") {
+        Some(n_str) => match n_str.parse::<usize>() {
+            Ok(n) => n,
+            Err(e) => {
+                return Err(format!(
+                    "argument for --retries must be a non-negative number \
+                     (error: {})",
+                    e
+                ));
+            }
+        },
+        None => 0,
+    };
+
+    Ok(retries)
+}
+
 fn get_test_threads(matches: &getopts::Matches) -> OptPartRes<Option<usize>> {
     let test_threads = match matches.opt_str("test-threads") {
         Some(n_str) => match n_str.parse::<usize>() {
@@ -409,9 +671,15 @@ fn get_format(
             }
             OutputFormat::Junit
         }
+        Some("tap") => {
+            if !allow_unstable {
+                return Err("The \"tap\" format is only accepted on the nightly compiler".into());
+            }
+            OutputFormat::Tap
+        }
         Some(v) => {
             return Err(format!(
-                "argument for --format must be pretty, terse, json or junit (was \
+                "argument for --format must be pretty, terse, json, junit or tap (was \
                  {})",
                 v
             ));
@@ -1,21 +1,27 @@
 //! Module providing interface for running tests in the console.
 
+use std::collections::HashSet;
 use std::fs::File;
 use std::io;
 use std::io::prelude::Write;
 use std::time::Instant;
 
 use super::{
-    bench::fmt_bench_samples,
+    bench::{baseline_path, fmt_bench_samples, BenchBaseline, BenchComparison},
     cli::TestOpts,
     event::{CompletedTest, TestEvent},
     filter_tests,
-    formatters::{JsonFormatter, JunitFormatter, OutputFormatter, PrettyFormatter, TerseFormatter},
+    formatters::{
+        JsonFormatter, JunitFormatter, OutputFormatter, PrettyFormatter, TapFormatter,
+        TerseFormatter,
+    },
     helpers::{concurrency::get_concurrency, metrics::MetricMap},
     options::{Options, OutputFormat},
+    record_panic_locations,
+    run_state::TestRunState,
     run_tests, term,
     test_result::TestResult,
-    time::{TestExecTime, TestSuiteExecTime},
+    time::{self, TestExecTime, TestSuiteExecTime},
     types::{NamePadding, TestDesc, TestDescAndFn},
 };
 
@@ -46,6 +52,8 @@ pub struct ConsoleTestState {
     pub total: usize,
     pub passed: usize,
     pub failed: usize,
+    /// Tests that passed after being retried. They are not counted as passed.
+    pub flaky: usize,
     pub ignored: usize,
     pub filtered_out: usize,
     pub measured: usize,
@@ -54,6 +62,16 @@ pub struct ConsoleTestState {
     pub failures: Vec<(TestDesc, Vec<u8>)>,
     pub not_failures: Vec<(TestDesc, Vec<u8>)>,
     pub time_failures: Vec<(TestDesc, Vec<u8>)>,
+    /// Flaky tests, with the output of their failed attempts.
+    pub flaky_tests: Vec<(TestDesc, Vec<u8>)>,
+    /// Outcomes of this and previous runs, if they are being recorded.
+    pub run_state: Option<TestRunState>,
+    /// Baseline the benchmark results are compared against.
+    pub baseline: Option<BenchBaseline>,
+    /// Baseline the benchmark results are saved to.
+    pub new_baseline: Option<BenchBaseline>,
+    /// Benchmarks that got significantly slower than in `baseline`.
+    pub bench_regressions: Vec<(TestDesc, BenchComparison)>,
     pub options: Options,
 }
 
@@ -69,6 +87,7 @@ pub fn new(opts: &TestOpts) -> io::Result<ConsoleTestState> {
             total: 0,
             passed: 0,
             failed: 0,
+            flaky: 0,
             ignored: 0,
             filtered_out: 0,
             measured: 0,
@@ -77,6 +96,11 @@ pub fn new(opts: &TestOpts) -> io::Result<ConsoleTestState> {
             failures: Vec::new(),
             not_failures: Vec::new(),
             time_failures: Vec::new(),
+            flaky_tests: Vec::new(),
+            run_state: None,
+            baseline: None,
+            new_baseline: None,
+            bench_regressions: Vec::new(),
             options: opts.options,
         })
     }
@@ -119,6 +143,8 @@ pub fn write_log_result(
                     }
                     TestResult::TrBench(ref bs) => fmt_bench_samples(bs),
                     TestResult::TrTimedFail => "failed (time limit exceeded)".to_owned(),
+                    TestResult::TrTimedOut => "timeout".to_owned(),
+                    TestResult::TrFlaky => "flaky".to_owned(),
                 },
                 name,
             )
@@ -129,13 +155,22 @@ pub fn write_log_result(
         self.write_log(|| "\n")
     }
 
+    /// Whether the run succeeded: no test failed and no benchmark regressed.
+    pub fn success(&self) -> bool {
+        self.failed == 0 && self.bench_regressions.is_empty()
+    }
+
     fn current_test_count(&self) -> usize {
-        self.passed + self.failed + self.ignored + self.measured
+        self.passed + self.failed + self.flaky + self.ignored + self.measured
     }
 }
 
 // List the tests to console, and optionally to logfile. Filters are honored.
 pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<()> {
+    if opts.format == OutputFormat::Json {
+        return list_tests_json(opts, tests);
+    }
+
     let mut output = match term::stdout() {
         None => OutputLocation::Raw(io::stdout()),
         Some(t) => OutputLocation::Pretty(t),
@@ -150,7 +185,8 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
     for test in filter_tests(&opts, tests) {
         use crate::TestFn::*;
 
-        let TestDescAndFn { desc: TestDesc { name, .. }, testfn } = test;
+        let TestDescAndFn { desc, testfn } = test;
+        let name = &desc.name;
 
         let fntype = match testfn {
             StaticTestFn(..) | DynTestFn(..) => {
@@ -163,7 +199,11 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
             }
         };
 
-        writeln!(output, "{name}: {fntype}")?;
+        // The terse listing is parsed by tools, so it keeps its format.
+        match desc.source_location() {
+            Some(location) if !quiet => writeln!(output, "{name}: {fntype} ({location})")?,
+            _ => writeln!(output, "{name}: {fntype}")?,
+        }
         st.write_log(|| format!("{fntype} {name}\n"))?;
     }
 
@@ -185,10 +225,41 @@ fn plural(count: u32, s: &str) -> String {
     Ok(())
 }
 
+// List the tests as JSON events that also carry their source location.
+fn list_tests_json(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<()> {
+    use crate::TestFn::*;
+
+    let mut out = JsonFormatter::new(OutputLocation::Raw(io::stdout()));
+    let mut st = ConsoleTestState::new(opts)?;
+
+    for test in filter_tests(&opts, tests) {
+        let fntype = match test.testfn {
+            StaticTestFn(..) | DynTestFn(..) => "test",
+            StaticBenchFn(..) | DynBenchFn(..) => "bench",
+        };
+
+        out.write_test_discovered(&test.desc, fntype)?;
+        st.write_log(|| format!("{fntype} {}\n", test.desc.name))?;
+    }
+
+    Ok(())
+}
+
 // Updates `ConsoleTestState` depending on result of the test execution.
-fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest) {
+fn handle_test_result(st: &mut ConsoleTestState, mut completed_test: CompletedTest) {
+    if completed_test.result == TestResult::TrTimedOut {
+        // Keep the note out of the captured stderr block of the test.
+        let out = &mut completed_test.stdout;
+        if !out.is_empty() && !out.ends_with(b"\n") {
+            out.push(b'\n');
+        }
+        out.extend_from_slice(b"note: test did not finish before its timeout\n");
+    }
+    let stdout = completed_test.output().into_owned();
     let test = completed_test.desc;
-    let stdout = completed_test.stdout;
+    if let Some(ref mut run_state) = st.run_state {
+        run_state.record(test.name.as_slice(), &completed_test.result);
+    }
     match completed_test.result {
         TestResult::TrOk => {
             st.passed += 1;
@@ -196,6 +267,9 @@ fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest)
         }
         TestResult::TrIgnored => st.ignored += 1,
         TestResult::TrBench(bs) => {
+            if let Some(ref mut new_baseline) = st.new_baseline {
+                new_baseline.record(test.name.as_slice(), &bs);
+            }
             st.metrics.insert_metric(
                 test.name.as_slice(),
                 bs.ns_iter_summ.median,
@@ -217,6 +291,31 @@ fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest)
             st.failed += 1;
             st.time_failures.push((test, stdout));
         }
+        TestResult::TrTimedOut => {
+            st.failed += 1;
+            st.failures.push((test, stdout));
+        }
+        TestResult::TrFlaky => {
+            st.flaky += 1;
+            let mut attempts_out = Vec::new();
+            for (i, attempt) in completed_test.failed_attempts.iter().enumerate() {
+                attempts_out.extend_from_slice(&attempt.output);
+                if !attempts_out.is_empty() && !attempts_out.ends_with(b"\n") {
+                    attempts_out.push(b'\n');
+                }
+                let reason = match attempt.result {
+                    TestResult::TrFailedMsg(ref msg) => format!(": {msg}"),
+                    TestResult::TrTimedFail => ": time limit exceeded".to_owned(),
+                    TestResult::TrTimedOut => ": test did not finish before its timeout".to_owned(),
+                    _ => String::new(),
+                };
+                attempts_out.extend_from_slice(
+                    format!("note: attempt {} failed{reason}\n", i + 1).as_bytes(),
+                );
+            }
+            st.not_failures.push((test.clone(), stdout));
+            st.flaky_tests.push((test, attempts_out));
+        }
     }
 }
 
@@ -226,6 +325,7 @@ fn on_test_event(
     event: &TestEvent,
     st: &mut ConsoleTestState,
     out: &mut dyn OutputFormatter,
+    opts: &TestOpts,
 ) -> io::Result<()> {
     match (*event).clone() {
         TestEvent::TeFiltered(ref filtered_tests, shuffle_seed) => {
@@ -241,10 +341,20 @@ fn on_test_event(
             let test = &completed_test.desc;
             let result = &completed_test.result;
             let exec_time = &completed_test.exec_time;
-            let stdout = &completed_test.stdout;
 
             st.write_log_result(test, result, exec_time.as_ref())?;
-            out.write_result(test, result, exec_time.as_ref(), &*stdout, st)?;
+            out.write_result(&completed_test, st)?;
+            if let (TestResult::TrBench(bs), Some(baseline), Some(baseline_opts)) =
+                (result, &st.baseline, &opts.baseline_options)
+            {
+                let name = test.name.as_slice();
+                if let Some(cmp) = baseline.compare(name, bs, baseline_opts.threshold) {
+                    out.write_bench_comparison(test, &cmp)?;
+                    if cmp.regression {
+                        st.bench_regressions.push((test.clone(), cmp));
+                    }
+                }
+            }
             handle_test_result(st, completed_test);
         }
     }
@@ -268,6 +378,9 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
 
     let is_multithreaded = opts.test_threads.unwrap_or_else(get_concurrency) > 1;
 
+    // Every format is given the location of the panics of failed tests.
+    record_panic_locations();
+
     let mut out: Box<dyn OutputFormatter> = match opts.format {
         OutputFormat::Pretty => Box::new(PrettyFormatter::new(
             output,
@@ -281,20 +394,44 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
         }
         OutputFormat::Json => Box::new(JsonFormatter::new(output)),
         OutputFormat::Junit => Box::new(JunitFormatter::new(output)),
+        OutputFormat::Tap => Box::new(TapFormatter::new(output)),
     };
     let mut st = ConsoleTestState::new(opts)?;
+    if let Some(ref path) = opts.state_file {
+        // Outcomes of tests that weren't run this time are kept, as long as the
+        // tests still exist.
+        let mut run_state = TestRunState::load(path);
+        let names: HashSet<&str> = tests.iter().map(|t| t.desc.name.as_slice()).collect();
+        run_state.retain(|name| names.contains(name));
+        st.run_state = Some(run_state);
+    }
+    if let Some(ref baseline_opts) = opts.baseline_options {
+        if let Some(ref name) = baseline_opts.compare {
+            st.baseline = Some(BenchBaseline::load(name, &baseline_path(name))?);
+        }
+        if let Some(ref name) = baseline_opts.save {
+            // Results of benchmarks that aren't run this time are kept.
+            let new_baseline = BenchBaseline::load(name, &baseline_path(name))
+                .unwrap_or_else(|_| BenchBaseline::new(name));
+            st.new_baseline = Some(new_baseline);
+        }
+    }
 
-    // Prevent the usage of `Instant` in some cases:
-    // - It's currently not supported for wasm targets.
-    // - We disable it for miri because it's not available when isolation is enabled.
-    let is_instant_supported = !cfg!(target_family = "wasm") && !cfg!(miri);
-
-    let start_time = is_instant_supported.then(Instant::now);
-    run_tests(opts, tests, |x| on_test_event(&x, &mut st, &mut *out))?;
+    let start_time = time::is_instant_supported().then(Instant::now);
+    run_tests(opts, tests, |x| on_test_event(&x, &mut st, &mut *out, opts))?;
     st.exec_time = start_time.map(|t| TestSuiteExecTime(t.elapsed()));
 
     assert!(st.current_test_count() == st.total);
 
+    if let (Some(path), Some(run_state)) = (&opts.state_file, &st.run_state) {
+        if let Err(e) = run_state.save(path) {
+            eprintln!("warning: failed to write the test state to {}: {e}", path.display());
+        }
+    }
+    if let Some(ref new_baseline) = st.new_baseline {
+        new_baseline.save(&baseline_path(&new_baseline.name))?;
+    }
+
     out.write_run_finish(&st)
 }
 
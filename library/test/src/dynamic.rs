@@ -0,0 +1,154 @@
+//! Tests registered at runtime, for harnesses that generate their test cases,
+//! such as one test per file in a directory of test data.
+//!
+//! ```no_run
+//! #![feature(test)]
+//! extern crate test;
+//!
+//! use test::DynamicTest;
+//!
+//! fn main() {
+//!     let tests = std::fs::read_dir("tests/data")
+//!         .unwrap()
+//!         .map(|entry| {
+//!             let path = entry.unwrap().path();
+//!             let name = format!("data::{}", path.display());
+//!             DynamicTest::new(name, move || {
+//!                 assert!(!std::fs::read(&path).unwrap().is_empty());
+//!             })
+//!         })
+//!         .collect();
+//!     test::test_main_dynamic(tests);
+//! }
+//! ```
+//!
+//! Harnesses built with `-C panic=abort` call `test_main_dynamic_abort`
+//! instead, which runs every test in a subprocess.
+
+#[cfg(not(bootstrap))]
+use std::borrow::Cow;
+use std::env;
+
+use super::options::ShouldPanic;
+#[cfg(not(bootstrap))]
+use super::types::TestLocation;
+use super::types::{DynTestFn, DynTestName, TestDesc, TestDescAndFn, TestType};
+use super::Options;
+
+/// A test created at runtime.
+///
+/// It is configured with the equivalents of the `#[ignore]` and
+/// `#[should_panic]` attributes, and run with `test_main_dynamic`.
+#[derive(Debug)]
+pub struct DynamicTest {
+    test: TestDescAndFn,
+}
+
+impl DynamicTest {
+    /// Creates a test named `name` that runs `testfn`, which fails the test
+    /// by panicking.
+    pub fn new<F>(name: impl Into<String>, testfn: F) -> DynamicTest
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let desc = TestDesc {
+            name: DynTestName(name.into()),
+            ignore: false,
+            ignore_message: None,
+            should_panic: ShouldPanic::No,
+            compile_fail: false,
+            no_run: false,
+            test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
+        };
+        DynamicTest { test: TestDescAndFn { desc, testfn: DynTestFn(Box::new(testfn)) } }
+    }
+
+    /// Only runs the test with `--ignored` or `--include-ignored`, like `#[ignore]`.
+    pub fn ignore(mut self) -> DynamicTest {
+        self.test.desc.ignore = true;
+        self
+    }
+
+    /// Like `ignore`, also giving the reason, like `#[ignore = "reason"]`.
+    #[cfg(not(bootstrap))]
+    pub fn ignore_with_reason(mut self, reason: impl Into<String>) -> DynamicTest {
+        self.test.desc.ignore = true;
+        self.test.desc.ignore_message = Some(Cow::Owned(reason.into()));
+        self
+    }
+
+    /// Only passes the test if it panics, like `#[should_panic]`.
+    pub fn should_panic(mut self) -> DynamicTest {
+        self.test.desc.should_panic = ShouldPanic::Yes;
+        self
+    }
+
+    /// Only passes the test if it panics with a message containing `expected`,
+    /// like `#[should_panic(expected = "...")]`.
+    #[cfg(not(bootstrap))]
+    pub fn should_panic_with(mut self, expected: impl Into<String>) -> DynamicTest {
+        self.test.desc.should_panic = ShouldPanic::YesWithMessage(Cow::Owned(expected.into()));
+        self
+    }
+
+    /// Overrides `--test-timeout` for this test, like `#[test_timeout = "SECS"]`.
+    #[cfg(not(bootstrap))]
+    pub fn timeout(mut self, secs: u64) -> DynamicTest {
+        self.test.desc.timeout = Some(secs);
+        self
+    }
+
+    /// Sets the file and one-based line the test comes from, such as the data
+    /// file it was generated for. It is reported by the JSON, JUnit and TAP
+    /// output formats.
+    #[cfg(not(bootstrap))]
+    pub fn location(mut self, file: impl Into<String>, line: usize) -> DynamicTest {
+        self.test.desc.location = TestLocation {
+            source_file: file.into().into(),
+            start_line: line,
+            start_col: 1,
+            end_line: line,
+            end_col: 1,
+        };
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.test.desc.name.as_slice()
+    }
+
+    pub fn into_test(self) -> TestDescAndFn {
+        self.test
+    }
+}
+
+/// Runs `tests` according to the command-line arguments of the process, like
+/// the harness generated by `rustc --test`, and exits with an error code if
+/// any of them failed.
+///
+/// The tests are filtered, run concurrently and reported by the same runner
+/// as `#[test]` functions. Since their closures can only be called once,
+/// they are run in-process, and are not retried by `--retries`.
+///
+/// A panicking test aborts the whole run if the harness is built with
+/// `-C panic=abort`; use `test_main_dynamic_abort` there.
+pub fn test_main_dynamic(tests: Vec<DynamicTest>) {
+    let args = env::args().collect::<Vec<_>>();
+    let tests = tests.into_iter().map(DynamicTest::into_test).collect();
+    super::test_main(&args, tests, None)
+}
+
+/// Like `test_main_dynamic`, for harnesses built with `-C panic=abort`.
+///
+/// Each test is run in a subprocess, which runs the harness again and picks
+/// the test by its name. The harness must therefore register the same tests,
+/// under the same names, every time it is run.
+pub fn test_main_dynamic_abort(tests: Vec<DynamicTest>) {
+    let args = env::args().collect::<Vec<_>>();
+    let tests = tests.into_iter().map(DynamicTest::into_test).collect();
+    super::test_main(&args, tests, Some(Options::new().panic_abort(true)))
+}
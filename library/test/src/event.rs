@@ -1,17 +1,46 @@
 //! Module containing different events that can occur
 //! during tests execution process.
 
+use std::borrow::Cow;
+
+use super::formatters::write_stderr_delimiter;
 use super::test_result::TestResult;
 use super::time::TestExecTime;
 use super::types::{TestDesc, TestId};
 
+/// The panic that failed a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestPanic {
+    /// The panic payload, if it is a string.
+    pub message: Option<String>,
+    /// File, line and column the test panicked at, if known.
+    pub location: Option<(String, u32, u32)>,
+}
+
+/// A failed run of a test that was then retried.
+#[derive(Debug, Clone)]
+pub struct FailedAttempt {
+    pub result: TestResult,
+    /// All of the output captured during the attempt.
+    pub output: Vec<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct CompletedTest {
     pub id: TestId,
     pub desc: TestDesc,
     pub result: TestResult,
     pub exec_time: Option<TestExecTime>,
+    /// Captured standard output.
     pub stdout: Vec<u8>,
+    /// Captured standard error, if it was captured apart from standard output.
+    /// Tests run in-process only do so for the formats that report both
+    /// streams separately; otherwise, their standard error is part of `stdout`.
+    pub stderr: Option<Vec<u8>>,
+    /// The panic that failed the test, for tests run in-process.
+    pub panic: Option<TestPanic>,
+    /// Earlier runs of the test that failed, when it was retried.
+    pub failed_attempts: Vec<FailedAttempt>,
 }
 
 impl CompletedTest {
@@ -22,7 +51,29 @@ pub fn new(
         exec_time: Option<TestExecTime>,
         stdout: Vec<u8>,
     ) -> Self {
-        Self { id, desc, result, exec_time, stdout }
+        Self {
+            id,
+            desc,
+            result,
+            exec_time,
+            stdout,
+            stderr: None,
+            panic: None,
+            failed_attempts: Vec::new(),
+        }
+    }
+
+    /// All of the captured output, with standard error after standard output.
+    pub fn output(&self) -> Cow<'_, [u8]> {
+        match self.stderr {
+            None => Cow::Borrowed(&self.stdout),
+            Some(ref stderr) => {
+                let mut output = self.stdout.clone();
+                write_stderr_delimiter(&mut output, &self.desc.name);
+                output.extend_from_slice(stderr);
+                Cow::Owned(output)
+            }
+        }
     }
 }
 
@@ -1,20 +1,35 @@
-use std::{borrow::Cow, io, io::prelude::Write};
+use std::{io, io::prelude::Write, time::Instant};
 
 use super::OutputFormatter;
 use crate::{
+    bench::BenchComparison,
     console::{ConsoleTestState, OutputLocation},
+    event::{CompletedTest, TestPanic},
     test_result::TestResult,
     time,
     types::TestDesc,
 };
 
+/// Version of the format of the events, reported when the suite starts. It is
+/// bumped whenever existing fields change meaning or are removed.
+const FORMAT_VERSION: u32 = 1;
+
 pub(crate) struct JsonFormatter<T> {
     out: OutputLocation<T>,
+    /// When the run started, which test start timestamps are relative to.
+    run_start: Option<Instant>,
+    /// How long each test that was run took, in seconds, in order of completion.
+    exec_times: Vec<(String, f64)>,
 }
 
 impl<T: Write> JsonFormatter<T> {
     pub fn new(out: OutputLocation<T>) -> Self {
-        Self { out }
+        Self { out, run_start: None, exec_times: Vec::new() }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
     }
 
     fn writeln_message(&mut self, s: &str) -> io::Result<()> {
@@ -30,13 +45,30 @@ fn write_message(&mut self, s: &str) -> io::Result<()> {
         self.out.write_all(s.as_ref())
     }
 
+    /// Writes an event describing a test found by `--list`.
+    pub fn write_test_discovered(&mut self, desc: &TestDesc, test_type: &str) -> io::Result<()> {
+        let ignore_message = desc
+            .ignore_message
+            .as_deref()
+            .map(|msg| format!(r#", "ignore_message": "{}""#, EscapedString(msg)))
+            .unwrap_or_default();
+        self.writeln_message(&*format!(
+            r#"{{ "type": "{}", "event": "discovered", "name": "{}", "ignore": {}{}{} }}"#,
+            test_type,
+            EscapedString(desc.name.as_slice()),
+            desc.ignore,
+            ignore_message,
+            source_location_json(desc),
+        ))
+    }
+
     fn write_event(
         &mut self,
         ty: &str,
         name: &str,
         evt: &str,
         exec_time: Option<&time::TestExecTime>,
-        stdout: Option<Cow<'_, str>>,
+        output: &str,
         extra: Option<&str>,
     ) -> io::Result<()> {
         // A doc test's name includes a filename which must be escaped for correct json.
@@ -49,9 +81,7 @@ fn write_event(
         if let Some(exec_time) = exec_time {
             self.write_message(&*format!(r#", "exec_time": {}"#, exec_time.0.as_secs_f64()))?;
         }
-        if let Some(stdout) = stdout {
-            self.write_message(&*format!(r#", "stdout": "{}""#, EscapedString(stdout)))?;
-        }
+        self.write_message(output)?;
         if let Some(extra) = extra {
             self.write_message(&*format!(r#", {}"#, extra))?;
         }
@@ -66,40 +96,54 @@ fn write_run_start(&mut self, test_count: usize, shuffle_seed: Option<u64>) -> i
         } else {
             String::new()
         };
+        self.run_start = time::is_instant_supported().then(Instant::now);
         self.writeln_message(&*format!(
-            r#"{{ "type": "suite", "event": "started", "test_count": {}{} }}"#,
-            test_count, shuffle_seed_json
+            r#"{{ "type": "suite", "event": "started", "format_version": {}, "test_count": {}{} }}"#,
+            FORMAT_VERSION, test_count, shuffle_seed_json
         ))
     }
 
     fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()> {
+        let timestamp = self
+            .run_start
+            .map(|start| format!(r#", "timestamp": {}"#, start.elapsed().as_secs_f64()))
+            .unwrap_or_default();
         self.writeln_message(&*format!(
-            r#"{{ "type": "test", "event": "started", "name": "{}" }}"#,
-            EscapedString(desc.name.as_slice())
+            r#"{{ "type": "test", "event": "started", "name": "{}"{}{} }}"#,
+            EscapedString(desc.name.as_slice()),
+            timestamp,
+            source_location_json(desc),
         ))
     }
 
-    fn write_result(
-        &mut self,
-        desc: &TestDesc,
-        result: &TestResult,
-        exec_time: Option<&time::TestExecTime>,
-        stdout: &[u8],
-        state: &ConsoleTestState,
-    ) -> io::Result<()> {
-        let display_stdout = state.options.display_output || *result != TestResult::TrOk;
-        let stdout = if display_stdout && !stdout.is_empty() {
-            Some(String::from_utf8_lossy(stdout))
-        } else {
-            None
-        };
+    fn write_result(&mut self, test: &CompletedTest, state: &ConsoleTestState) -> io::Result<()> {
+        let desc = &test.desc;
+        let result = &test.result;
+        let exec_time = test.exec_time.as_ref();
+        if let Some(exec_time) = exec_time {
+            self.exec_times.push((desc.name.as_slice().to_string(), exec_time.0.as_secs_f64()));
+        }
+
+        let mut output = String::new();
+        let passed = matches!(result, TestResult::TrOk | TestResult::TrFlaky);
+        let display_output = state.options.display_output || !passed;
+        if display_output {
+            output.push_str(&output_json("stdout", &test.stdout));
+            if let Some(ref stderr) = test.stderr {
+                output.push_str(&output_json("stderr", stderr));
+            }
+        }
+        if let (Some(panic), false) = (&test.panic, passed) {
+            output.push_str(&panic_json(panic));
+        }
+        let output = &*output;
         match *result {
             TestResult::TrOk => {
-                self.write_event("test", desc.name.as_slice(), "ok", exec_time, stdout, None)
+                self.write_event("test", desc.name.as_slice(), "ok", exec_time, output, None)
             }
 
             TestResult::TrFailed => {
-                self.write_event("test", desc.name.as_slice(), "failed", exec_time, stdout, None)
+                self.write_event("test", desc.name.as_slice(), "failed", exec_time, output, None)
             }
 
             TestResult::TrTimedFail => self.write_event(
@@ -107,26 +151,45 @@ fn write_result(
                 desc.name.as_slice(),
                 "failed",
                 exec_time,
-                stdout,
+                output,
                 Some(r#""reason": "time limit exceeded""#),
             ),
 
+            TestResult::TrTimedOut => self.write_event(
+                "test",
+                desc.name.as_slice(),
+                "failed",
+                exec_time,
+                output,
+                Some(r#""reason": "test did not finish before its timeout""#),
+            ),
+
             TestResult::TrFailedMsg(ref m) => self.write_event(
                 "test",
                 desc.name.as_slice(),
                 "failed",
                 exec_time,
-                stdout,
+                output,
                 Some(&*format!(r#""message": "{}""#, EscapedString(m))),
             ),
 
+            TestResult::TrFlaky => self.write_event(
+                "test",
+                desc.name.as_slice(),
+                "flaky",
+                exec_time,
+                output,
+                Some(&*format!(r#""failed_attempts": {}"#, test.failed_attempts.len())),
+            ),
+
             TestResult::TrIgnored => self.write_event(
                 "test",
                 desc.name.as_slice(),
                 "ignored",
                 exec_time,
-                stdout,
+                output,
                 desc.ignore_message
+                    .as_deref()
                     .map(|msg| format!(r#""message": "{}""#, EscapedString(msg)))
                     .as_deref(),
             ),
@@ -164,18 +227,41 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_bench_comparison(&mut self, desc: &TestDesc, cmp: &BenchComparison) -> io::Result<()> {
+        self.writeln_message(&*format!(
+            "{{ \"type\": \"bench\", \
+             \"event\": \"compared\", \
+             \"name\": \"{}\", \
+             \"baseline\": \"{}\", \
+             \"baseline_median\": {}, \
+             \"median\": {}, \
+             \"change\": {:.2}, \
+             \"p_value\": {:.6}, \
+             \"regression\": {} }}",
+            EscapedString(desc.name.as_slice()),
+            EscapedString(&cmp.baseline),
+            cmp.baseline_median as usize,
+            cmp.median as usize,
+            cmp.change_pct,
+            cmp.p_value,
+            cmp.regression,
+        ))
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         self.write_message(&*format!(
             "{{ \"type\": \"suite\", \
              \"event\": \"{}\", \
              \"passed\": {}, \
              \"failed\": {}, \
+             \"flaky\": {}, \
              \"ignored\": {}, \
              \"measured\": {}, \
              \"filtered_out\": {}",
-            if state.failed == 0 { "ok" } else { "failed" },
+            if state.success() { "ok" } else { "failed" },
             state.passed,
             state.failed,
+            state.flaky,
             state.ignored,
             state.measured,
             state.filtered_out,
@@ -186,15 +272,70 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
             self.write_message(&time_str)?;
         }
 
+        if !self.exec_times.is_empty() {
+            let exec_times = self
+                .exec_times
+                .iter()
+                .map(|(name, secs)| format!(r#""{}": {}"#, EscapedString(name), secs))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.write_message(&format!(r#", "exec_times": {{ {} }}"#, exec_times))?;
+        }
+
         self.writeln_message(" }")?;
 
-        Ok(state.failed == 0)
+        Ok(state.success())
     }
 }
 
+/// Formats the source location of the test as additional JSON fields,
+/// or nothing if the location is unknown.
+fn source_location_json(desc: &TestDesc) -> String {
+    let location = match desc.source_location() {
+        Some(location) => location,
+        None => return String::new(),
+    };
+    format!(
+        r#", "source_path": "{}", "start_line": {}, "start_col": {}, "end_line": {}, "end_col": {}"#,
+        EscapedString(&location.source_file),
+        location.start_line,
+        location.start_col,
+        location.end_line,
+        location.end_col,
+    )
+}
+
+/// Formats captured output as an additional JSON field, or nothing if there
+/// was no output.
+fn output_json(field: &str, output: &[u8]) -> String {
+    if output.is_empty() {
+        return String::new();
+    }
+    format!(r#", "{}": "{}""#, field, EscapedString(String::from_utf8_lossy(output)))
+}
+
+/// Formats the panic that failed a test as an additional JSON field. Either
+/// part of it is `null` when unknown, such as for non-string payloads.
+fn panic_json(panic: &TestPanic) -> String {
+    let message = match panic.message {
+        Some(ref message) => format!(r#""{}""#, EscapedString(message)),
+        None => "null".to_string(),
+    };
+    let location = match panic.location {
+        Some((ref file, line, column)) => format!(
+            r#", "file": "{}", "line": {}, "column": {}"#,
+            EscapedString(file),
+            line,
+            column
+        ),
+        None => r#", "file": null, "line": null, "column": null"#.to_string(),
+    };
+    format!(r#", "panic": {{ "message": {}{} }}"#, message, location)
+}
+
 /// A formatting utility used to print strings with characters in need of escaping.
 /// Base code taken form `libserialize::json::escape_str`
-struct EscapedString<S: AsRef<str>>(S);
+pub(super) struct EscapedString<S: AsRef<str>>(pub S);
 
 impl<S: AsRef<str>> std::fmt::Display for EscapedString<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> ::std::fmt::Result {
@@ -1,17 +1,18 @@
+use std::fmt;
 use std::io::{self, prelude::Write};
 use std::time::Duration;
 
 use super::OutputFormatter;
 use crate::{
     console::{ConsoleTestState, OutputLocation},
+    event::{CompletedTest, FailedAttempt},
     test_result::TestResult,
-    time,
     types::{TestDesc, TestType},
 };
 
 pub struct JunitFormatter<T> {
     out: OutputLocation<T>,
-    results: Vec<(TestDesc, TestResult, Duration)>,
+    results: Vec<(TestDesc, TestResult, Duration, Vec<FailedAttempt>)>,
 }
 
 impl<T: Write> JunitFormatter<T> {
@@ -19,6 +20,11 @@ pub fn new(out: OutputLocation<T>) -> Self {
         Self { out, results: Vec::new() }
     }
 
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
     fn write_message(&mut self, s: &str) -> io::Result<()> {
         assert!(!s.contains('\n'));
 
@@ -46,19 +52,17 @@ fn write_timeout(&mut self, _desc: &TestDesc) -> io::Result<()> {
         Ok(())
     }
 
-    fn write_result(
-        &mut self,
-        desc: &TestDesc,
-        result: &TestResult,
-        exec_time: Option<&time::TestExecTime>,
-        _stdout: &[u8],
-        _state: &ConsoleTestState,
-    ) -> io::Result<()> {
+    fn write_result(&mut self, test: &CompletedTest, _state: &ConsoleTestState) -> io::Result<()> {
         // Because the testsuite node holds some of the information as attributes, we can't write it
         // until all of the tests have finished. Instead of writing every result as they come in, we add
         // them to a Vec and write them all at once when run is complete.
-        let duration = exec_time.map(|t| t.0).unwrap_or_default();
-        self.results.push((desc.clone(), result.clone(), duration));
+        let duration = test.exec_time.as_ref().map(|t| t.0).unwrap_or_default();
+        self.results.push((
+            test.desc.clone(),
+            test.result.clone(),
+            duration,
+            test.failed_attempts.clone(),
+        ));
         Ok(())
     }
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
@@ -73,17 +77,28 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
              >",
             state.failed, state.total, state.ignored
         ))?;
-        for (desc, result, duration) in std::mem::replace(&mut self.results, Vec::new()) {
+        for (desc, result, duration, failed_attempts) in
+            std::mem::replace(&mut self.results, Vec::new())
+        {
             let (class_name, test_name) = parse_class_name(&desc);
+            let location = match desc.source_location() {
+                Some(location) => format!(
+                    " file=\"{}\" line=\"{}\"",
+                    XmlEscaped(&location.source_file),
+                    location.start_line
+                ),
+                None => String::new(),
+            };
             match result {
                 TestResult::TrIgnored => { /* no-op */ }
                 TestResult::TrFailed => {
                     self.write_message(&*format!(
                         "<testcase classname=\"{}\" \
-                         name=\"{}\" time=\"{}\">",
+                         name=\"{}\" time=\"{}\"{}>",
                         class_name,
                         test_name,
-                        duration.as_secs_f64()
+                        duration.as_secs_f64(),
+                        location,
                     ))?;
                     self.write_message("<failure type=\"assert\"/>")?;
                     self.write_message("</testcase>")?;
@@ -92,27 +107,48 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                 TestResult::TrFailedMsg(ref m) => {
                     self.write_message(&*format!(
                         "<testcase classname=\"{}\" \
-                         name=\"{}\" time=\"{}\">",
+                         name=\"{}\" time=\"{}\"{}>",
                         class_name,
                         test_name,
-                        duration.as_secs_f64()
+                        duration.as_secs_f64(),
+                        location,
+                    ))?;
+                    self.write_message(&*format!(
+                        "<failure message=\"{}\" type=\"assert\"/>",
+                        XmlEscaped(m)
                     ))?;
-                    self.write_message(&*format!("<failure message=\"{m}\" type=\"assert\"/>"))?;
                     self.write_message("</testcase>")?;
                 }
 
                 TestResult::TrTimedFail => {
                     self.write_message(&*format!(
                         "<testcase classname=\"{}\" \
-                         name=\"{}\" time=\"{}\">",
+                         name=\"{}\" time=\"{}\"{}>",
                         class_name,
                         test_name,
-                        duration.as_secs_f64()
+                        duration.as_secs_f64(),
+                        location,
                     ))?;
                     self.write_message("<failure type=\"timeout\"/>")?;
                     self.write_message("</testcase>")?;
                 }
 
+                TestResult::TrTimedOut => {
+                    self.write_message(&*format!(
+                        "<testcase classname=\"{}\" \
+                         name=\"{}\" time=\"{}\"{}>",
+                        class_name,
+                        test_name,
+                        duration.as_secs_f64(),
+                        location,
+                    ))?;
+                    self.write_message(
+                        "<failure message=\"test did not finish before its timeout\" \
+                         type=\"timeout\"/>",
+                    )?;
+                    self.write_message("</testcase>")?;
+                }
+
                 TestResult::TrBench(ref b) => {
                     self.write_message(&*format!(
                         "<testcase classname=\"benchmark::{}\" \
@@ -121,13 +157,39 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                     ))?;
                 }
 
+                TestResult::TrFlaky => {
+                    self.write_message(&*format!(
+                        "<testcase classname=\"{}\" \
+                         name=\"{}\" time=\"{}\"{}>",
+                        class_name,
+                        test_name,
+                        duration.as_secs_f64(),
+                        location,
+                    ))?;
+                    // Reported the way Maven Surefire reports reruns of flaky tests.
+                    for attempt in failed_attempts {
+                        let (message, ty) = match attempt.result {
+                            TestResult::TrFailedMsg(ref m) => {
+                                (format!(" message=\"{}\"", XmlEscaped(m)), "assert")
+                            }
+                            TestResult::TrTimedFail | TestResult::TrTimedOut => {
+                                (String::new(), "timeout")
+                            }
+                            _ => (String::new(), "assert"),
+                        };
+                        self.write_message(&*format!("<flakyFailure{message} type=\"{ty}\"/>"))?;
+                    }
+                    self.write_message("</testcase>")?;
+                }
+
                 TestResult::TrOk => {
                     self.write_message(&*format!(
                         "<testcase classname=\"{}\" \
-                         name=\"{}\" time=\"{}\"/>",
+                         name=\"{}\" time=\"{}\"{}/>",
                         class_name,
                         test_name,
-                        duration.as_secs_f64()
+                        duration.as_secs_f64(),
+                        location,
                     ))?;
                 }
             }
@@ -139,7 +201,33 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
 
         self.out.write_all(b"\n")?;
 
-        Ok(state.failed == 0)
+        Ok(state.success())
+    }
+}
+
+/// Escapes the characters that are special in XML attribute values.
+struct XmlEscaped<'a>(&'a str);
+
+impl fmt::Display for XmlEscaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut start = 0;
+        for (i, c) in self.0.char_indices() {
+            let escaped = match c {
+                '&' => "&amp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '"' => "&quot;",
+                '\'' => "&apos;",
+                // Line breaks would be normalized to spaces in attribute values.
+                '\n' => "&#10;",
+                '\r' => "&#13;",
+                _ => continue,
+            };
+            f.write_str(&self.0[start..i])?;
+            f.write_str(escaped)?;
+            start = i + 1;
+        }
+        f.write_str(&self.0[start..])
     }
 }
 
@@ -1,34 +1,39 @@
 use std::{io, io::prelude::Write};
 
 use crate::{
+    bench::{fmt_bench_comparison, BenchComparison},
     console::ConsoleTestState,
-    test_result::TestResult,
-    time,
+    event::CompletedTest,
+    term,
     types::{TestDesc, TestName},
 };
 
 mod json;
 mod junit;
 mod pretty;
+mod tap;
 mod terse;
 
 pub(crate) use self::json::JsonFormatter;
 pub(crate) use self::junit::JunitFormatter;
 pub(crate) use self::pretty::PrettyFormatter;
+pub(crate) use self::tap::TapFormatter;
 pub(crate) use self::terse::TerseFormatter;
 
 pub(crate) trait OutputFormatter {
     fn write_run_start(&mut self, test_count: usize, shuffle_seed: Option<u64>) -> io::Result<()>;
     fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()>;
     fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()>;
-    fn write_result(
+    fn write_result(&mut self, test: &CompletedTest, state: &ConsoleTestState) -> io::Result<()>;
+    /// Reports how a benchmark compares to the baseline given by `--baseline`.
+    /// Regressions fail the run whether or not the format reports them.
+    fn write_bench_comparison(
         &mut self,
-        desc: &TestDesc,
-        result: &TestResult,
-        exec_time: Option<&time::TestExecTime>,
-        stdout: &[u8],
-        state: &ConsoleTestState,
-    ) -> io::Result<()>;
+        _desc: &TestDesc,
+        _cmp: &BenchComparison,
+    ) -> io::Result<()> {
+        Ok(())
+    }
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool>;
 }
 
@@ -40,3 +45,25 @@ pub(crate) fn write_stderr_delimiter(test_output: &mut Vec<u8>, test_name: &Test
     }
     writeln!(test_output, "---- {} stderr ----", test_name).unwrap();
 }
+
+/// The verdict of a comparison to the baseline, with the color to highlight it in.
+pub(crate) fn bench_verdict(cmp: &BenchComparison) -> (&'static str, Option<term::color::Color>) {
+    if cmp.regression {
+        ("regressed", Some(term::color::RED))
+    } else if cmp.improvement {
+        ("improved", Some(term::color::GREEN))
+    } else {
+        ("no significant change", None)
+    }
+}
+
+/// Lists the benchmarks that regressed from the baseline, sorted by name.
+pub(crate) fn fmt_bench_regressions(state: &ConsoleTestState) -> String {
+    let mut regressions: Vec<_> = state
+        .bench_regressions
+        .iter()
+        .map(|(desc, cmp)| format!("    {}: {}\n", desc.name, fmt_bench_comparison(cmp)))
+        .collect();
+    regressions.sort();
+    format!("\nbench regressions:\n{}", regressions.concat())
+}
@@ -1,9 +1,10 @@
 use std::{io, io::prelude::Write};
 
-use super::OutputFormatter;
+use super::{bench_verdict, fmt_bench_regressions, OutputFormatter};
 use crate::{
-    bench::fmt_bench_samples,
+    bench::{fmt_bench_comparison, fmt_bench_samples, BenchComparison},
     console::{ConsoleTestState, OutputLocation},
+    event::CompletedTest,
     term,
     test_result::TestResult,
     time,
@@ -45,7 +46,7 @@ pub fn write_failed(&mut self) -> io::Result<()> {
         self.write_short_result("FAILED", term::color::RED)
     }
 
-    pub fn write_ignored(&mut self, message: Option<&'static str>) -> io::Result<()> {
+    pub fn write_ignored(&mut self, message: Option<&str>) -> io::Result<()> {
         if let Some(message) = message {
             self.write_short_result(&format!("ignored, {}", message), term::color::YELLOW)
         } else {
@@ -53,10 +54,19 @@ pub fn write_ignored(&mut self, message: Option<&'static str>) -> io::Result<()>
         }
     }
 
+    pub fn write_flaky(&mut self, failed_attempts: usize) -> io::Result<()> {
+        let result = format!("flaky, passed on attempt {}", failed_attempts + 1);
+        self.write_short_result(&result, term::color::YELLOW)
+    }
+
     pub fn write_time_failed(&mut self) -> io::Result<()> {
         self.write_short_result("FAILED (time limit exceeded)", term::color::RED)
     }
 
+    pub fn write_timed_out(&mut self) -> io::Result<()> {
+        self.write_short_result("TIMEOUT", term::color::RED)
+    }
+
     pub fn write_bench(&mut self) -> io::Result<()> {
         self.write_pretty("bench", term::color::CYAN)
     }
@@ -168,6 +178,10 @@ pub fn write_time_failures(&mut self, state: &ConsoleTestState) -> io::Result<()
         self.write_results(&state.time_failures, "failures (time limit exceeded)")
     }
 
+    pub fn write_flaky_tests(&mut self, state: &ConsoleTestState) -> io::Result<()> {
+        self.write_results(&state.flaky_tests, "flaky tests")
+    }
+
     fn write_test_name(&mut self, desc: &TestDesc) -> io::Result<()> {
         let name = desc.padded_name(self.max_name_len, desc.name.padding());
         if let Some(test_mode) = desc.test_mode() {
@@ -203,30 +217,26 @@ fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()> {
         Ok(())
     }
 
-    fn write_result(
-        &mut self,
-        desc: &TestDesc,
-        result: &TestResult,
-        exec_time: Option<&time::TestExecTime>,
-        _: &[u8],
-        _: &ConsoleTestState,
-    ) -> io::Result<()> {
+    fn write_result(&mut self, test: &CompletedTest, _: &ConsoleTestState) -> io::Result<()> {
+        let desc = &test.desc;
         if self.is_multithreaded {
             self.write_test_name(desc)?;
         }
 
-        match *result {
+        match test.result {
             TestResult::TrOk => self.write_ok()?,
             TestResult::TrFailed | TestResult::TrFailedMsg(_) => self.write_failed()?,
-            TestResult::TrIgnored => self.write_ignored(desc.ignore_message)?,
+            TestResult::TrIgnored => self.write_ignored(desc.ignore_message.as_deref())?,
             TestResult::TrBench(ref bs) => {
                 self.write_bench()?;
                 self.write_plain(&format!(": {}", fmt_bench_samples(bs)))?;
             }
             TestResult::TrTimedFail => self.write_time_failed()?,
+            TestResult::TrTimedOut => self.write_timed_out()?,
+            TestResult::TrFlaky => self.write_flaky(test.failed_attempts.len())?,
         }
 
-        self.write_time(desc, exec_time)?;
+        self.write_time(desc, test.exec_time.as_ref())?;
         self.write_plain("\n")
     }
 
@@ -238,12 +248,31 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_bench_comparison(
+        &mut self,
+        _desc: &TestDesc,
+        cmp: &BenchComparison,
+    ) -> io::Result<()> {
+        self.write_plain("    change: ")?;
+        match bench_verdict(cmp) {
+            (verdict, Some(color)) => self.write_pretty(verdict, color)?,
+            (verdict, None) => self.write_plain(verdict)?,
+        }
+        self.write_plain(&format!(", {}\n", fmt_bench_comparison(cmp)))
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         if state.options.display_output {
             self.write_successes(state)?;
         }
-        let success = state.failed == 0;
-        if !success {
+        let success = state.success();
+        if !state.flaky_tests.is_empty() {
+            self.write_flaky_tests(state)?;
+        }
+        if !state.bench_regressions.is_empty() {
+            self.write_plain(&fmt_bench_regressions(state))?;
+        }
+        if state.failed != 0 {
             if !state.failures.is_empty() {
                 self.write_failures(state)?;
             }
@@ -262,9 +291,11 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
             self.write_pretty("FAILED", term::color::RED)?;
         }
 
+        let flaky =
+            if state.flaky != 0 { format!("{} flaky; ", state.flaky) } else { String::new() };
         let s = format!(
-            ". {} passed; {} failed; {} ignored; {} measured; {} filtered out",
-            state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+            ". {} passed; {} failed; {}{} ignored; {} measured; {} filtered out",
+            state.passed, state.failed, flaky, state.ignored, state.measured, state.filtered_out
         );
 
         self.write_plain(&s)?;
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::io::{self, prelude::Write};
+
+use super::json::EscapedString;
+use super::{bench_verdict, OutputFormatter};
+use crate::{
+    bench::{fmt_bench_comparison, fmt_bench_samples, BenchComparison},
+    console::{ConsoleTestState, OutputLocation},
+    event::{CompletedTest, TestPanic},
+    test_result::TestResult,
+    time,
+    types::{TestDesc, TestType},
+};
+
+/// A test result kept around until it can be written out.
+struct TapResult {
+    desc: TestDesc,
+    result: TestResult,
+    exec_time: Option<time::TestExecTime>,
+    stdout: Vec<u8>,
+    panic: Option<TestPanic>,
+    failed_attempts: usize,
+}
+
+/// Writes test results in the [Test Anything Protocol](https://testanything.org/tap-version-14-specification.html).
+///
+/// Each test is reported as a test point as soon as it finishes, followed by a
+/// YAML diagnostic block when there is something to report. Doctests are grouped
+/// into one subtest per source file, which is written once the run is complete.
+pub(crate) struct TapFormatter<T> {
+    out: OutputLocation<T>,
+    /// Number of top-level test points written so far.
+    test_number: usize,
+    /// Doctest results, grouped by the file they were extracted from.
+    doctests: BTreeMap<String, Vec<TapResult>>,
+}
+
+impl<T: Write> TapFormatter<T> {
+    pub fn new(out: OutputLocation<T>) -> Self {
+        Self { out, test_number: 0, doctests: BTreeMap::new() }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
+    fn writeln_message(&mut self, s: &str) -> io::Result<()> {
+        assert!(!s.contains('\n'));
+
+        self.out.write_all(s.as_ref())?;
+        self.out.write_all(b"\n")
+    }
+
+    fn write_test_point(
+        &mut self,
+        indent: &str,
+        number: usize,
+        name: &str,
+        result: &TapResult,
+        state: &ConsoleTestState,
+    ) -> io::Result<()> {
+        let TapResult { desc, result, exec_time, stdout, panic, failed_attempts } = result;
+        let status = if is_failure(result) { "not ok" } else { "ok" };
+        let directive = match *result {
+            TestResult::TrIgnored => match desc.ignore_message.as_deref() {
+                Some(msg) => format!(" # SKIP {}", msg.replace('\n', " ")),
+                None => " # SKIP".to_string(),
+            },
+            _ => String::new(),
+        };
+        let name = escape_description(name);
+        self.writeln_message(&format!("{indent}{status} {number} - {name}{directive}"))?;
+
+        let mut diagnostics = Vec::new();
+        match *result {
+            TestResult::TrFailedMsg(ref msg) => diagnostics.push(("message", yaml_str(msg))),
+            TestResult::TrTimedFail => {
+                diagnostics.push(("message", yaml_str("time limit exceeded")))
+            }
+            TestResult::TrTimedOut => {
+                diagnostics.push(("message", yaml_str("test did not finish before its timeout")))
+            }
+            TestResult::TrIgnored => {
+                if let Some(msg) = desc.ignore_message.as_deref() {
+                    diagnostics.push(("reason", yaml_str(msg)));
+                }
+            }
+            TestResult::TrBench(ref bs) => {
+                diagnostics.push(("bench", yaml_str(&fmt_bench_samples(bs))))
+            }
+            TestResult::TrFlaky => {
+                diagnostics.push(("failed_attempts", failed_attempts.to_string()))
+            }
+            TestResult::TrOk | TestResult::TrFailed => {}
+        }
+        if let (Some(panic), true) = (panic, is_failure(result)) {
+            if let Some(message) = &panic.message {
+                // Other failures already have a `message` describing the failure.
+                let key = if matches!(result, TestResult::TrFailed) { "message" } else { "panic" };
+                diagnostics.push((key, yaml_str(message)));
+            }
+            if let Some((file, line, column)) = &panic.location {
+                diagnostics.push(("at", yaml_str(&format!("{file}:{line}:{column}"))));
+            }
+        }
+        if let Some(exec_time) = exec_time {
+            diagnostics.push(("duration_ms", format!("{:.3}", exec_time.0.as_secs_f64() * 1000.0)));
+        }
+        let display_stdout = state.options.display_output || is_failure(result);
+        if display_stdout && !stdout.is_empty() {
+            diagnostics.push(("stdout", yaml_str(&String::from_utf8_lossy(stdout))));
+        }
+
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+        self.writeln_message(&format!("{indent}  ---"))?;
+        for (key, value) in diagnostics {
+            self.writeln_message(&format!("{indent}  {key}: {value}"))?;
+        }
+        self.writeln_message(&format!("{indent}  ..."))
+    }
+
+    fn write_doctest_subtests(&mut self, state: &ConsoleTestState) -> io::Result<()> {
+        for (file, mut results) in std::mem::take(&mut self.doctests) {
+            results.sort_by(|a, b| a.desc.name.as_slice().cmp(b.desc.name.as_slice()));
+
+            self.writeln_message(&format!("    # Subtest: {}", escape_line_breaks(&file)))?;
+            self.writeln_message(&format!("    1..{}", results.len()))?;
+            for (i, result) in results.iter().enumerate() {
+                let name = doctest_location(&result.desc).1;
+                self.write_test_point("    ", i + 1, name, result, state)?;
+            }
+
+            let failed = results.iter().any(|r| is_failure(&r.result));
+            self.test_number += 1;
+            let status = if failed { "not ok" } else { "ok" };
+            let file = escape_description(&file);
+            self.writeln_message(&format!("{status} {} - {file}", self.test_number))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Write> OutputFormatter for TapFormatter<T> {
+    fn write_run_start(&mut self, _test_count: usize, shuffle_seed: Option<u64>) -> io::Result<()> {
+        // The plan is written at the end of the run: doctests are reported as one
+        // subtest per file, so the number of top-level test points isn't known yet.
+        self.writeln_message("TAP version 14")?;
+        if let Some(shuffle_seed) = shuffle_seed {
+            self.writeln_message(&format!("# shuffle seed: {shuffle_seed}"))?;
+        }
+        Ok(())
+    }
+
+    fn write_test_start(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        // We do not output anything on test start.
+        Ok(())
+    }
+
+    fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
+        self.writeln_message(&format!(
+            "# test {} has been running for over {} seconds",
+            escape_line_breaks(desc.name.as_slice()),
+            time::TEST_WARN_TIMEOUT_S
+        ))
+    }
+
+    fn write_result(&mut self, test: &CompletedTest, state: &ConsoleTestState) -> io::Result<()> {
+        let desc = &test.desc;
+        let result = TapResult {
+            desc: desc.clone(),
+            result: test.result.clone(),
+            exec_time: test.exec_time.clone(),
+            stdout: test.output().into_owned(),
+            panic: test.panic.clone(),
+            failed_attempts: test.failed_attempts.len(),
+        };
+        if desc.test_type == TestType::DocTest {
+            let file = doctest_location(desc).0.to_string();
+            self.doctests.entry(file).or_default().push(result);
+            return Ok(());
+        }
+
+        self.test_number += 1;
+        self.write_test_point("", self.test_number, desc.name.as_slice(), &result, state)
+    }
+
+    fn write_bench_comparison(&mut self, desc: &TestDesc, cmp: &BenchComparison) -> io::Result<()> {
+        let (verdict, _) = bench_verdict(cmp);
+        let name = escape_line_breaks(desc.name.as_slice());
+        self.writeln_message(&format!("# {name}: {verdict}, {}", fmt_bench_comparison(cmp)))
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        self.write_doctest_subtests(state)?;
+        self.writeln_message(&format!("1..{}", self.test_number))?;
+        self.writeln_message(&format!(
+            "# {} passed; {} failed; {} ignored; {} measured; {} filtered out",
+            state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+        ))?;
+
+        Ok(state.success())
+    }
+}
+
+/// Renders `s` as a double-quoted YAML scalar, which shares its escapes with JSON.
+fn yaml_str(s: &str) -> String {
+    format!("\"{}\"", EscapedString(s))
+}
+
+/// Escapes the line breaks in `s`, such as those of a dynamic test name, as
+/// they would end the line `s` is written on.
+fn escape_line_breaks(s: &str) -> String {
+    s.replace('\r', "\\r").replace('\n', "\\n")
+}
+
+/// Escapes `s` for the description of a test point, where `#` starts a directive.
+fn escape_description(s: &str) -> String {
+    escape_line_breaks(&s.replace('\\', "\\\\").replace('#', "\\#"))
+}
+
+fn is_failure(result: &TestResult) -> bool {
+    matches!(
+        result,
+        TestResult::TrFailed
+            | TestResult::TrFailedMsg(_)
+            | TestResult::TrTimedFail
+            | TestResult::TrTimedOut
+    )
+}
+
+/// Splits a doctest name such as `src/lib.rs - foo::bar (line 10)` into
+/// the file and the location within it.
+fn doctest_location(desc: &TestDesc) -> (&str, &str) {
+    let name = desc.name.as_slice();
+    name.split_once(" - ").map_or((name, name), |(file, location)| (file.trim(), location.trim()))
+}
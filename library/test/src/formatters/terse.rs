@@ -1,9 +1,10 @@
 use std::{io, io::prelude::Write};
 
-use super::OutputFormatter;
+use super::{bench_verdict, fmt_bench_regressions, OutputFormatter};
 use crate::{
-    bench::fmt_bench_samples,
+    bench::{fmt_bench_comparison, fmt_bench_samples, BenchComparison},
     console::{ConsoleTestState, OutputLocation},
+    event::CompletedTest,
     term,
     test_result::TestResult,
     time,
@@ -55,6 +56,14 @@ pub fn write_ignored(&mut self) -> io::Result<()> {
         self.write_short_result("i", term::color::YELLOW)
     }
 
+    pub fn write_flaky(&mut self) -> io::Result<()> {
+        self.write_short_result("f", term::color::YELLOW)
+    }
+
+    pub fn write_timed_out(&mut self) -> io::Result<()> {
+        self.write_short_result("T", term::color::RED)
+    }
+
     pub fn write_bench(&mut self) -> io::Result<()> {
         self.write_pretty("bench", term::color::CYAN)
     }
@@ -129,26 +138,40 @@ pub fn write_outputs(&mut self, state: &ConsoleTestState) -> io::Result<()> {
     }
 
     pub fn write_failures(&mut self, state: &ConsoleTestState) -> io::Result<()> {
-        self.write_plain("\nfailures:\n")?;
-        let mut failures = Vec::new();
-        let mut fail_out = String::new();
-        for &(ref f, ref stdout) in &state.failures {
-            failures.push(f.name.to_string());
+        self.write_results(&state.failures, "failures")
+    }
+
+    pub fn write_flaky_tests(&mut self, state: &ConsoleTestState) -> io::Result<()> {
+        self.write_results(&state.flaky_tests, "flaky tests")
+    }
+
+    fn write_results(
+        &mut self,
+        inputs: &Vec<(TestDesc, Vec<u8>)>,
+        results_type: &str,
+    ) -> io::Result<()> {
+        let results_out_str = format!("\n{results_type}:\n");
+
+        self.write_plain(&results_out_str)?;
+        let mut results = Vec::new();
+        let mut stdouts = String::new();
+        for &(ref f, ref stdout) in inputs {
+            results.push(f.name.to_string());
             if !stdout.is_empty() {
-                fail_out.push_str(&format!("---- {} stdout ----\n", f.name));
+                stdouts.push_str(&format!("---- {} stdout ----\n", f.name));
                 let output = String::from_utf8_lossy(stdout);
-                fail_out.push_str(&output);
-                fail_out.push('\n');
+                stdouts.push_str(&output);
+                stdouts.push('\n');
             }
         }
-        if !fail_out.is_empty() {
+        if !stdouts.is_empty() {
             self.write_plain("\n")?;
-            self.write_plain(&fail_out)?;
+            self.write_plain(&stdouts)?;
         }
 
-        self.write_plain("\nfailures:\n")?;
-        failures.sort();
-        for name in &failures {
+        self.write_plain(&results_out_str)?;
+        results.sort();
+        for name in &results {
             self.write_plain(&format!("    {name}\n"))?;
         }
         Ok(())
@@ -190,20 +213,16 @@ fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()> {
         Ok(())
     }
 
-    fn write_result(
-        &mut self,
-        desc: &TestDesc,
-        result: &TestResult,
-        _: Option<&time::TestExecTime>,
-        _: &[u8],
-        _: &ConsoleTestState,
-    ) -> io::Result<()> {
-        match *result {
+    fn write_result(&mut self, test: &CompletedTest, _: &ConsoleTestState) -> io::Result<()> {
+        let desc = &test.desc;
+        match test.result {
             TestResult::TrOk => self.write_ok(),
             TestResult::TrFailed | TestResult::TrFailedMsg(_) | TestResult::TrTimedFail => {
                 self.write_failed()
             }
             TestResult::TrIgnored => self.write_ignored(),
+            TestResult::TrTimedOut => self.write_timed_out(),
+            TestResult::TrFlaky => self.write_flaky(),
             TestResult::TrBench(ref bs) => {
                 if self.is_multithreaded {
                     self.write_test_name(desc)?;
@@ -222,12 +241,31 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_bench_comparison(
+        &mut self,
+        _desc: &TestDesc,
+        cmp: &BenchComparison,
+    ) -> io::Result<()> {
+        self.write_plain("    change: ")?;
+        match bench_verdict(cmp) {
+            (verdict, Some(color)) => self.write_pretty(verdict, color)?,
+            (verdict, None) => self.write_plain(verdict)?,
+        }
+        self.write_plain(&format!(", {}\n", fmt_bench_comparison(cmp)))
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         if state.options.display_output {
             self.write_outputs(state)?;
         }
-        let success = state.failed == 0;
-        if !success {
+        let success = state.success();
+        if !state.flaky_tests.is_empty() {
+            self.write_flaky_tests(state)?;
+        }
+        if !state.bench_regressions.is_empty() {
+            self.write_plain(&fmt_bench_regressions(state))?;
+        }
+        if state.failed != 0 {
             self.write_failures(state)?;
         }
 
@@ -240,9 +278,11 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
             self.write_pretty("FAILED", term::color::RED)?;
         }
 
+        let flaky =
+            if state.flaky != 0 { format!("{} flaky; ", state.flaky) } else { String::new() };
         let s = format!(
-            ". {} passed; {} failed; {} ignored; {} measured; {} filtered out",
-            state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+            ". {} passed; {} failed; {}{} ignored; {} measured; {} filtered out",
+            state.passed, state.failed, flaky, state.ignored, state.measured, state.filtered_out
         );
 
         self.write_plain(&s)?;
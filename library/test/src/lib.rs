@@ -18,6 +18,7 @@
 #![feature(nll)]
 #![feature(bench_black_box)]
 #![feature(internal_output_capture)]
+#![feature(once_cell)]
 #![feature(staged_api)]
 #![feature(process_exitcode_internals)]
 #![feature(test)]
@@ -25,6 +26,7 @@
 // Public reexports
 pub use self::bench::{black_box, Bencher};
 pub use self::console::run_tests_console;
+pub use self::dynamic::{test_main_dynamic, test_main_dynamic_abort, DynamicTest};
 pub use self::options::{ColorConfig, Options, OutputFormat, RunIgnored, ShouldPanic};
 pub use self::types::TestName::*;
 pub use self::types::*;
@@ -35,11 +37,14 @@
 pub mod test {
     pub use crate::{
         assert_test_result,
-        bench::Bencher,
+        bench::{BaselineOptions, Bencher},
         cli::{parse_opts, TestOpts},
+        dynamic::{test_main_dynamic, test_main_dynamic_abort, DynamicTest},
         filter_tests,
         helpers::metrics::{Metric, MetricMap},
-        options::{Concurrent, Options, RunIgnored, RunStrategy, ShouldPanic},
+        options::{
+            Concurrent, Options, Partition, RerunFailed, RunIgnored, RunStrategy, ShouldPanic,
+        },
         run_test, test_main, test_main_static,
         test_result::{TestResult, TrFailed, TrFailedMsg, TrIgnored, TrOk},
         time::{TestExecTime, TestTimeOptions},
@@ -51,10 +56,17 @@ pub mod test {
 }
 
 use std::{
+    any::Any,
+    cell::Cell,
+    collections::hash_map::RandomState,
     collections::VecDeque,
-    env, io,
+    env, fs,
+    hash::{BuildHasher, Hasher},
+    io,
     io::prelude::Write,
-    panic::{self, catch_unwind, AssertUnwindSafe, PanicInfo},
+    lazy::SyncOnceCell,
+    panic::{self, catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
     process::{self, Command, Termination},
     sync::mpsc::{channel, Sender},
     sync::{Arc, Mutex},
@@ -65,10 +77,12 @@ pub mod test {
 pub mod bench;
 mod cli;
 mod console;
+mod dynamic;
 mod event;
 mod formatters;
 mod helpers;
 mod options;
+mod run_state;
 pub mod stats;
 mod term;
 mod test_result;
@@ -78,11 +92,12 @@ pub mod test {
 #[cfg(test)]
 mod tests;
 
-use event::{CompletedTest, TestEvent};
+use event::{CompletedTest, FailedAttempt, TestEvent, TestPanic};
 use helpers::concurrency::get_concurrency;
 use helpers::exit_code::get_exit_code;
 use helpers::shuffle::{get_shuffle_seed, shuffle_tests};
-use options::{Concurrent, RunStrategy};
+use options::{Concurrent, RerunFailed, RunStrategy};
+use run_state::TestRunState;
 use test_result::*;
 use time::TestExecTime;
 
@@ -90,10 +105,41 @@ pub mod test {
 const ERROR_EXIT_CODE: i32 = 101;
 
 const SECONDARY_TEST_INVOKER_VAR: &str = "__RUST_TEST_INVOKE";
+// Where a secondary process writes its `SecondaryReport`.
+const SECONDARY_TEST_REPORT_VAR: &str = "__RUST_TEST_REPORT";
 
 // The default console test runner. It accepts the command line
 // arguments and a vector of test_descs.
 pub fn test_main(args: &[String], tests: Vec<TestDescAndFn>, options: Option<Options>) {
+    // If we're being run in SpawnedSecondary mode, run the test here. run_test
+    // will then exit the process.
+    if let Ok(name) = env::var(SECONDARY_TEST_INVOKER_VAR) {
+        env::remove_var(SECONDARY_TEST_INVOKER_VAR);
+        let report_path = env::var_os(SECONDARY_TEST_REPORT_VAR).map(PathBuf::from);
+        env::remove_var(SECONDARY_TEST_REPORT_VAR);
+        let test = tests
+            .into_iter()
+            .find(|test| test.desc.name.as_slice() == name)
+            .unwrap_or_else(|| panic!("couldn't find a test with the provided name '{name}'"));
+        let TestDescAndFn { desc, testfn } = test;
+        // Dynamic tests are found again if the harness registers them the
+        // same way in every process, see `test_main_dynamic_abort`.
+        let testfn: Box<dyn FnOnce() + Send> = match testfn {
+            StaticTestFn(f) => Box::new(f),
+            DynTestFn(f) => f,
+            // Benchmarks run as tests, which `convert_benchmarks_to_tests`
+            // has only done in the primary process.
+            StaticBenchFn(f) => {
+                Box::new(move || bench::run_once(|b| __rust_begin_short_backtrace(|| f(b))))
+            }
+            DynBenchFn(f) => {
+                Box::new(move || bench::run_once(|b| __rust_begin_short_backtrace(|| f(b))))
+            }
+        };
+        let panic_abort = options.map_or(false, |options| options.panic_abort);
+        run_test_in_spawned_subprocess(desc, testfn, report_path, panic_abort);
+    }
+
     let mut opts = match cli::parse_opts(args) {
         Some(Ok(o)) => o,
         Some(Err(msg)) => {
@@ -111,7 +157,9 @@ pub fn test_main(args: &[String], tests: Vec<TestDescAndFn>, options: Option<Opt
             process::exit(ERROR_EXIT_CODE);
         }
     } else {
-        match console::run_tests_console(&opts, tests) {
+        let result = console::run_tests_console(&opts, tests);
+        remove_report_dir();
+        match result {
             Ok(true) => {}
             Ok(false) => process::exit(ERROR_EXIT_CODE),
             Err(e) => {
@@ -142,24 +190,6 @@ pub fn test_main_static(tests: &[&TestDescAndFn]) {
 /// This is the entry point for the main function generated by `rustc --test`
 /// when panic=abort.
 pub fn test_main_static_abort(tests: &[&TestDescAndFn]) {
-    // If we're being run in SpawnedSecondary mode, run the test here. run_test
-    // will then exit the process.
-    if let Ok(name) = env::var(SECONDARY_TEST_INVOKER_VAR) {
-        env::remove_var(SECONDARY_TEST_INVOKER_VAR);
-        let test = tests
-            .iter()
-            .filter(|test| test.desc.name.as_slice() == name)
-            .map(make_owned_test)
-            .next()
-            .unwrap_or_else(|| panic!("couldn't find a test with the provided name '{name}'"));
-        let TestDescAndFn { desc, testfn } = test;
-        let testfn = match testfn {
-            StaticTestFn(f) => f,
-            _ => panic!("only static tests are supported"),
-        };
-        run_test_in_spawned_subprocess(desc, Box::new(testfn));
-    }
-
     let args = env::args().collect::<Vec<_>>();
     let owned_tests: Vec<_> = tests.iter().map(make_owned_test).collect();
     test_main(&args, owned_tests, Some(Options::new().panic_abort(true)))
@@ -254,16 +284,72 @@ struct TimeoutEntry {
     let mut remaining = filtered_tests;
     if let Some(shuffle_seed) = shuffle_seed {
         shuffle_tests(shuffle_seed, &mut remaining);
+        // The tests that failed last time are still run first, shuffled
+        // separately from the others.
+        if opts.rerun_failed == RerunFailed::First {
+            let last_run = last_run_state(opts);
+            // Tests are run from the end of `remaining`, and the sort is stable.
+            remaining.sort_by_key(|(_, test)| last_run.failed(test.desc.name.as_slice()));
+        }
     } else {
         remaining.reverse();
     }
     let mut pending = 0;
 
     let (tx, rx) = channel::<CompletedTest>();
-    let run_strategy = if opts.options.panic_abort && !opts.force_run_in_process {
-        RunStrategy::SpawnPrimary
+    let run_strategy = |test: &TestDescAndFn| {
+        if opts.force_run_in_process {
+            RunStrategy::InProcess
+        } else if opts.options.panic_abort {
+            RunStrategy::SpawnPrimary
+        } else if opts.isolate && matches!(test.testfn, StaticTestFn(_)) {
+            // Only static tests can be looked up by name in the secondary process.
+            RunStrategy::SpawnPrimary
+        } else {
+            RunStrategy::InProcess
+        }
+    };
+
+    // The functions of the tests that can be run again after failing, see
+    // `--retries`. Dynamic test functions, which include doctests and the
+    // benchmarks run as tests, can only be called once.
+    let retryable: HashMap<TestId, fn()> = if opts.retries > 0 {
+        remaining
+            .iter()
+            .filter_map(|(id, test)| match test.testfn {
+                StaticTestFn(f) => Some((*id, f)),
+                _ => None,
+            })
+            .collect()
     } else {
-        RunStrategy::InProcess
+        HashMap::new()
+    };
+    let mut failed_attempts: HashMap<TestId, Vec<FailedAttempt>> = HashMap::new();
+
+    // Returns the test to run again if it failed and has retries left.
+    // Otherwise, attaches the earlier failed attempts to its result.
+    let mut take_retry = |completed_test: &mut CompletedTest| -> Option<TestDescAndFn> {
+        let id = completed_test.id;
+        let mut attempts = failed_attempts.remove(&id).unwrap_or_default();
+        let failed =
+            matches!(completed_test.result, TrFailed | TrFailedMsg(_) | TrTimedFail | TrTimedOut);
+        match retryable.get(&id) {
+            Some(&f) if failed && attempts.len() < opts.retries => {
+                attempts.push(FailedAttempt {
+                    result: completed_test.result.clone(),
+                    output: completed_test.output().into_owned(),
+                });
+                failed_attempts.insert(id, attempts);
+                Some(TestDescAndFn { desc: completed_test.desc.clone(), testfn: StaticTestFn(f) })
+            }
+            _ => {
+                if completed_test.result == TrOk && !attempts.is_empty() {
+                    completed_test.result = TrFlaky;
+                }
+                completed_test.failed_attempts = attempts;
+                None
+            }
+        }
     };
 
     let mut running_tests: TestMap = HashMap::default();
@@ -299,10 +385,15 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
             let (id, test) = remaining.pop().unwrap();
             let event = TestEvent::TeWait(test.desc.clone());
             notify_about_test_event(event)?;
+            let strategy = run_strategy(&test);
             let join_handle =
-                run_test(opts, !opts.run_tests, id, test, run_strategy, tx.clone(), Concurrent::No);
+                run_test(opts, !opts.run_tests, id, test, strategy, tx.clone(), Concurrent::No);
             assert!(join_handle.is_none());
-            let completed_test = rx.recv().unwrap();
+            let mut completed_test = rx.recv().unwrap();
+            while let Some(test) = take_retry(&mut completed_test) {
+                run_test(opts, false, id, test, strategy, tx.clone(), Concurrent::No);
+                completed_test = rx.recv().unwrap();
+            }
 
             let event = TestEvent::TeResult(completed_test);
             notify_about_test_event(event)?;
@@ -316,12 +407,13 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
 
                 let event = TestEvent::TeWait(desc.clone());
                 notify_about_test_event(event)?; //here no pad
+                let strategy = run_strategy(&test);
                 let join_handle = run_test(
                     opts,
                     !opts.run_tests,
                     id,
                     test,
-                    run_strategy,
+                    strategy,
                     tx.clone(),
                     Concurrent::Yes,
                 );
@@ -365,6 +457,21 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
                 }
             }
 
+            if let Some(test) = take_retry(&mut completed_test) {
+                let id = completed_test.id;
+                let timeout = time::get_default_test_timeout();
+                let desc = test.desc.clone();
+                let strategy = run_strategy(&test);
+                let join_handle =
+                    run_test(opts, false, id, test, strategy, tx.clone(), Concurrent::Yes);
+                running_tests.insert(id, RunningTest { join_handle });
+                // The retry gets the whole timeout, rather than what's left of the
+                // timeout of the failed attempt.
+                timeout_queue.retain(|entry| entry.id != id);
+                timeout_queue.push_back(TimeoutEntry { id, desc, timeout });
+                continue;
+            }
+
             let event = TestEvent::TeResult(completed_test);
             notify_about_test_event(event)?;
             pending -= 1;
@@ -376,7 +483,8 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
         for (id, b) in filtered_benchs {
             let event = TestEvent::TeWait(b.desc.clone());
             notify_about_test_event(event)?;
-            run_test(opts, false, id, b, run_strategy, tx.clone(), Concurrent::No);
+            let strategy = run_strategy(&b);
+            run_test(opts, false, id, b, strategy, tx.clone(), Concurrent::No);
             let completed_test = rx.recv().unwrap();
 
             let event = TestEvent::TeResult(completed_test);
@@ -425,9 +533,38 @@ pub fn filter_tests(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<TestDescA
     // Sort the tests alphabetically
     filtered.sort_by(|t1, t2| t1.desc.name.as_slice().cmp(t2.desc.name.as_slice()));
 
+    // Only keep this runner's share of the tests. This happens after sorting so
+    // that every runner agrees on which tests belong to which partition.
+    if let Some(partition) = opts.partition {
+        filtered = filtered
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, _)| partition.contains(i))
+            .map(|(_, test)| test)
+            .collect();
+    }
+
+    // Give priority to the tests that failed in the previous run. This happens
+    // after partitioning, as runners needn't share their state files.
+    if opts.rerun_failed != RerunFailed::No {
+        let last_run = last_run_state(opts);
+        let failed = |test: &TestDescAndFn| last_run.failed(test.desc.name.as_slice());
+        match opts.rerun_failed {
+            RerunFailed::First => filtered.sort_by_key(|test| !failed(test)),
+            // Failures of tests that aren't selected by this run don't count.
+            RerunFailed::Only if filtered.iter().any(failed) => filtered.retain(failed),
+            RerunFailed::Only | RerunFailed::No => {}
+        }
+    }
+
     filtered
 }
 
+/// The outcomes of the previous run, used by `--failed-first` and `--only-failed`.
+fn last_run_state(opts: &TestOpts) -> TestRunState {
+    opts.state_file.as_deref().map(TestRunState::load).unwrap_or_default()
+}
+
 pub fn convert_benchmarks_to_tests(tests: Vec<TestDescAndFn>) -> Vec<TestDescAndFn> {
     // convert benchmarks to tests, if we're not benchmarking them
     tests
@@ -474,6 +611,9 @@ struct TestRunOpts {
         pub nocapture: bool,
         pub concurrency: Concurrent,
         pub time: Option<time::TestTimeOptions>,
+        pub report_time: bool,
+        pub timeout: Option<Duration>,
+        pub split_output: bool,
     }
 
     fn run_test_inner(
@@ -491,18 +631,21 @@ fn run_test_inner(
                 id,
                 desc,
                 opts.nocapture,
-                opts.time.is_some(),
+                opts.report_time,
                 testfn,
                 monitor_ch,
                 opts.time,
+                opts.timeout,
+                opts.split_output,
             ),
             RunStrategy::SpawnPrimary => spawn_test_subprocess(
                 id,
                 desc,
                 opts.nocapture,
-                opts.time.is_some(),
+                opts.report_time,
                 monitor_ch,
                 opts.time,
+                opts.timeout,
             ),
         };
 
@@ -530,8 +673,19 @@ fn run_test_inner(
         }
     }
 
-    let test_run_opts =
-        TestRunOpts { strategy, nocapture: opts.nocapture, concurrency, time: opts.time_options };
+    let test_run_opts = TestRunOpts {
+        strategy,
+        nocapture: opts.nocapture,
+        concurrency,
+        time: opts.time_options,
+        // The JSON output always reports how long tests took.
+        report_time: opts.time_options.is_some()
+            || (opts.format == OutputFormat::Json && time::is_instant_supported()),
+        timeout: time::get_test_timeout(&desc, opts.test_timeout),
+        // The JSON output reports both streams separately. Other formats show
+        // them interleaved, the way they were written.
+        split_output: opts.format == OutputFormat::Json,
+    };
 
     match testfn {
         DynBenchFn(benchfn) => {
@@ -544,19 +698,13 @@ fn run_test_inner(
             crate::bench::benchmark(id, desc, monitor_ch, opts.nocapture, benchfn);
             None
         }
-        DynTestFn(f) => {
-            match strategy {
-                RunStrategy::InProcess => (),
-                _ => panic!("Cannot run dynamic test fn out-of-process"),
-            };
-            run_test_inner(
-                id,
-                desc,
-                monitor_ch,
-                Box::new(move || __rust_begin_short_backtrace(f)),
-                test_run_opts,
-            )
-        }
+        DynTestFn(f) => run_test_inner(
+            id,
+            desc,
+            monitor_ch,
+            Box::new(move || __rust_begin_short_backtrace(f)),
+            test_run_opts,
+        ),
         StaticTestFn(f) => run_test_inner(
             id,
             desc,
@@ -576,6 +724,85 @@ fn __rust_begin_short_backtrace<F: FnOnce()>(f: F) {
     black_box(());
 }
 
+thread_local! {
+    /// Where the latest panic on this thread happened, as recorded by the hook
+    /// set by `record_panic_locations`.
+    static PANIC_LOCATION: Cell<Option<(String, u32, u32)>> = Cell::new(None);
+}
+
+/// Wraps the panic hook to record where tests run in-process panic, so that it
+/// can be reported in a structured way.
+pub(crate) fn record_panic_locations() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(location) = info.location() {
+            let location = (location.file().to_string(), location.line(), location.column());
+            PANIC_LOCATION.with(|l| l.set(Some(location)));
+        }
+        hook(info);
+    }));
+}
+
+/// The message of a panic, if its payload is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+}
+
+/// The buffers capturing the output of a test run in-process.
+#[derive(Clone)]
+struct OutputCapture {
+    stdout: Arc<Mutex<Vec<u8>>>,
+    /// Standard error, if it is captured apart from standard output.
+    stderr: Option<Arc<Mutex<Vec<u8>>>>,
+}
+
+/// Runs `testfn` with its output sent to `capture`, returning whether it
+/// panicked and, if so, where.
+fn catch_test_panic(
+    testfn: Box<dyn FnOnce() + Send>,
+    capture: Option<OutputCapture>,
+) -> (thread::Result<()>, Option<(String, u32, u32)>) {
+    let has_capture = capture.is_some();
+    if let Some(capture) = capture {
+        io::set_output_capture(Some(capture.stdout));
+        io::set_error_capture(capture.stderr);
+    }
+    PANIC_LOCATION.with(|l| l.take());
+    let result = catch_unwind(AssertUnwindSafe(testfn));
+    if has_capture {
+        io::set_output_capture(None);
+        io::set_error_capture(None);
+    }
+    (result, PANIC_LOCATION.with(|l| l.take()))
+}
+
+/// Runs `testfn` on its own thread, giving up on it after `timeout`. Unlike a
+/// test process, a thread can't be killed, so a test that times out is left
+/// running in the background until the test binary exits. Returns `None` if
+/// the test timed out.
+fn run_with_timeout(
+    testfn: Box<dyn FnOnce() + Send>,
+    timeout: Duration,
+    capture: Option<OutputCapture>,
+) -> Option<(thread::Result<()>, Option<(String, u32, u32)>)> {
+    let (tx, rx) = channel();
+    let mut cfg = thread::Builder::new();
+    if let Some(name) = thread::current().name() {
+        cfg = cfg.name(name.to_owned());
+    }
+    let spawned = cfg.spawn(move || {
+        // The receiver is gone if the test timed out.
+        let _ = tx.send(catch_test_panic(testfn, capture));
+    });
+    if let Err(e) = spawned {
+        panic!("failed to spawn thread to run test: {e}");
+    }
+    rx.recv_timeout(timeout).ok()
+}
+
 fn run_test_in_process(
     id: TestId,
     desc: TestDesc,
@@ -584,29 +811,38 @@ fn run_test_in_process(
     testfn: Box<dyn FnOnce() + Send>,
     monitor_ch: Sender<CompletedTest>,
     time_opts: Option<time::TestTimeOptions>,
+    timeout: Option<Duration>,
+    split_output: bool,
 ) {
-    // Buffer for capturing standard I/O
+    // Buffers for capturing standard I/O
     let data = Arc::new(Mutex::new(Vec::new()));
-
-    if !nocapture {
-        io::set_output_capture(Some(data.clone()));
-    }
+    let err_data = split_output.then(|| Arc::new(Mutex::new(Vec::new())));
+    let capture =
+        (!nocapture).then(|| OutputCapture { stdout: data.clone(), stderr: err_data.clone() });
 
     let start = report_time.then(Instant::now);
-    let result = catch_unwind(AssertUnwindSafe(testfn));
+    let supports_threads = !cfg!(target_os = "emscripten") && !cfg!(target_family = "wasm");
+    let result = match timeout {
+        Some(timeout) if supports_threads => run_with_timeout(testfn, timeout, capture),
+        _ => Some(catch_test_panic(testfn, capture)),
+    };
     let exec_time = start.map(|start| {
         let duration = start.elapsed();
         TestExecTime(duration)
     });
 
-    io::set_output_capture(None);
-
-    let test_result = match result {
-        Ok(()) => calc_result(&desc, Ok(()), &time_opts, &exec_time),
-        Err(e) => calc_result(&desc, Err(e.as_ref()), &time_opts, &exec_time),
+    let (test_result, panic) = match result {
+        None => (TrTimedOut, None),
+        Some((Ok(()), _)) => (calc_result(&desc, Ok(()), &time_opts, &exec_time), None),
+        Some((Err(e), location)) => {
+            let panic = TestPanic { message: panic_message(&*e), location };
+            (calc_result(&desc, Err(e.as_ref()), &time_opts, &exec_time), Some(panic))
+        }
     };
     let stdout = data.lock().unwrap_or_else(|e| e.into_inner()).to_vec();
-    let message = CompletedTest::new(id, desc, test_result, exec_time, stdout);
+    let mut message = CompletedTest::new(id, desc, test_result, exec_time, stdout);
+    message.stderr = err_data.map(|data| data.lock().unwrap_or_else(|e| e.into_inner()).to_vec());
+    message.panic = panic;
     monitor_ch.send(message).unwrap();
 }
 
@@ -617,24 +853,39 @@ fn spawn_test_subprocess(
     report_time: bool,
     monitor_ch: Sender<CompletedTest>,
     time_opts: Option<time::TestTimeOptions>,
+    timeout: Option<Duration>,
 ) {
-    let (result, test_output, exec_time) = (|| {
+    let (result, stdout, stderr, exec_time, panic) = (|| {
         let args = env::args().collect::<Vec<_>>();
         let current_exe = &args[0];
 
+        // Without a report, the test process falls back to printing the failure
+        // message to stderr.
+        let report_path = report_dir().ok().map(|dir| dir.join(format!("{}.report", id.0)));
         let mut command = Command::new(current_exe);
         command.env(SECONDARY_TEST_INVOKER_VAR, desc.name.as_slice());
+        if let Some(ref report_path) = report_path {
+            command.env(SECONDARY_TEST_REPORT_VAR, report_path);
+        }
         if nocapture {
             command.stdout(process::Stdio::inherit());
             command.stderr(process::Stdio::inherit());
         }
 
         let start = report_time.then(Instant::now);
-        let output = match command.output() {
+        let output = match timeout {
+            Some(timeout) => command
+                .stdout(process::Stdio::piped())
+                .stderr(process::Stdio::piped())
+                .spawn()
+                .and_then(|child| wait_with_timeout(child, timeout)),
+            None => command.output().map(|output| (output, false)),
+        };
+        let (output, timed_out) = match output {
             Ok(out) => out,
             Err(e) => {
                 let err = format!("Failed to spawn {} as child for test: {:?}", args[0], e);
-                return (TrFailed, err.into_bytes(), None);
+                return (TrFailed, Vec::new(), err.into_bytes(), None, None);
             }
         };
         let exec_time = start.map(|start| {
@@ -642,45 +893,147 @@ fn spawn_test_subprocess(
             TestExecTime(duration)
         });
 
-        let std::process::Output { stdout, stderr, status } = output;
-        let mut test_output = stdout;
-        formatters::write_stderr_delimiter(&mut test_output, &desc.name);
-        test_output.extend_from_slice(&stderr);
+        // The report is missing if the test exited or crashed on its own.
+        let report = match report_path.as_ref().map(|path| (path, fs::read_to_string(path))) {
+            Some((path, Ok(report))) => {
+                let _ = fs::remove_file(path);
+                SecondaryReport::decode(&report)
+            }
+            _ => SecondaryReport::default(),
+        };
+        // Prefer the time measured by the test process, which doesn't include
+        // starting it, like the time of tests run in-process.
+        let exec_time = exec_time.map(|t| report.exec_time.unwrap_or(t));
+
+        let std::process::Output { stdout, mut stderr, status } = output;
+
+        if timed_out {
+            return (TrTimedOut, stdout, stderr, exec_time, None);
+        }
 
         let result = match (|| -> Result<TestResult, String> {
             let exit_code = get_exit_code(status)?;
             Ok(get_result_from_exit_code(&desc, exit_code, &time_opts, &exec_time))
         })() {
+            Ok(TrFailed) => report.failed_msg.map_or(TrFailed, TrFailedMsg),
             Ok(r) => r,
             Err(e) => {
-                write!(&mut test_output, "Unexpected error: {}", e).unwrap();
+                write!(&mut stderr, "Unexpected error: {}", e).unwrap();
                 TrFailed
             }
         };
+        let panic = if result == TrOk { None } else { report.panic };
 
-        (result, test_output, exec_time)
+        (result, stdout, stderr, exec_time, panic)
     })();
 
-    let message = CompletedTest::new(id, desc, result, exec_time, test_output);
+    let mut message = CompletedTest::new(id, desc, result, exec_time, stdout);
+    message.stderr = Some(stderr);
+    message.panic = panic;
     monitor_ch.send(message).unwrap();
 }
 
-fn run_test_in_spawned_subprocess(desc: TestDesc, testfn: Box<dyn FnOnce() + Send>) -> ! {
-    let builtin_panic_hook = panic::take_hook();
-    let record_result = Arc::new(move |panic_info: Option<&'_ PanicInfo<'_>>| {
-        let test_result = match panic_info {
-            Some(info) => calc_result(&desc, Err(info.payload()), &None, &None),
-            None => calc_result(&desc, Ok(()), &None, &None),
-        };
+/// The directory the test processes write their reports to. It has a random
+/// name and, on Unix, only its owner can access it, so that other users can
+/// neither read the reports nor plant symlinks at their paths.
+static REPORT_DIR: SyncOnceCell<PathBuf> = SyncOnceCell::new();
 
-        // We don't support serializing TrFailedMsg, so just
-        // print the message out to stderr.
-        if let TrFailedMsg(msg) = &test_result {
-            eprintln!("{msg}");
+fn report_dir() -> io::Result<&'static Path> {
+    REPORT_DIR.get_or_try_init(create_private_dir).map(PathBuf::as_path)
+}
+
+fn remove_report_dir() {
+    if let Some(dir) = REPORT_DIR.get() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Creates a new directory with a random name in the temporary directory,
+/// which only the current user can access on Unix.
+fn create_private_dir() -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    let mut attempts = 0;
+    loop {
+        let random = RandomState::new().build_hasher().finish();
+        let dir = env::temp_dir().join(format!("rust-test-{}-{random:016x}", process::id()));
+        match builder.create(&dir) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 16 => attempts += 1,
+            result => return result.map(|()| dir),
+        }
+    }
+}
+
+/// Waits for `child` to exit while collecting its output, killing it if it is
+/// still running once `timeout` has elapsed. The returned flag tells whether
+/// the child had to be killed.
+fn wait_with_timeout(
+    mut child: process::Child,
+    timeout: Duration,
+) -> io::Result<(process::Output, bool)> {
+    fn read_pipe<R: io::Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                // The child may be killed mid-write; keep whatever was read so far.
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    }
+
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            // The child may have exited since `try_wait`, in which case there is
+            // nothing left to kill.
+            let _ = child.kill();
+            timed_out = true;
+            break child.wait()?;
         }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    Ok((process::Output { status, stdout, stderr }, timed_out))
+}
 
-        if let Some(info) = panic_info {
-            builtin_panic_hook(info);
+fn run_test_in_spawned_subprocess(
+    desc: TestDesc,
+    testfn: Box<dyn FnOnce() + Send>,
+    report_path: Option<PathBuf>,
+    panic_abort: bool,
+) -> ! {
+    let start = Instant::now();
+    let record_result = move |task_result: Result<(), &(dyn Any + Send)>,
+                              location: Option<(String, u32, u32)>| {
+        let exec_time = TestExecTime(start.elapsed());
+        let test_result = calc_result(&desc, task_result, &None, &None);
+
+        let panic = task_result
+            .err()
+            .map(|payload| TestPanic { message: panic_message(payload), location });
+        let failed_msg = match test_result {
+            TrFailedMsg(ref msg) => Some(msg.clone()),
+            _ => None,
+        };
+        let report = SecondaryReport { exec_time: Some(exec_time), failed_msg, panic };
+        let reported = match report_path {
+            Some(ref path) => fs::write(path, report.encode()).is_ok(),
+            None => false,
+        };
+        // Without a report, the message can only be passed on through stderr.
+        if let (false, Some(msg)) = (reported, &report.failed_msg) {
+            eprintln!("{msg}");
         }
 
         if let TrOk = test_result {
@@ -688,10 +1041,26 @@ fn run_test_in_spawned_subprocess(desc: TestDesc, testfn: Box<dyn FnOnce() + Sen
         } else {
             process::exit(test_result::TR_FAILED);
         }
-    });
+    };
+
+    if !panic_abort {
+        // The test may catch panics itself, so only the panic it unwinds with
+        // fails it, as when it is run in-process.
+        record_panic_locations();
+        let (result, location) = catch_test_panic(testfn, None);
+        record_result(result.as_ref().map(|_| ()).map_err(|payload| &**payload), location);
+        unreachable!("recording the result should have exited the process")
+    }
+
+    let builtin_panic_hook = panic::take_hook();
+    let record_result = Arc::new(record_result);
     let record_result2 = record_result.clone();
-    panic::set_hook(Box::new(move |info| record_result2(Some(&info))));
+    panic::set_hook(Box::new(move |info| {
+        let location = info.location().map(|l| (l.file().to_string(), l.line(), l.column()));
+        builtin_panic_hook(info);
+        record_result2(Err(info.payload()), location)
+    }));
     testfn();
-    record_result(None);
+    record_result(Ok(()), None);
     unreachable!("panic=abort callback should have exited the process")
 }
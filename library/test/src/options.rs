@@ -1,5 +1,8 @@
 //! Enums denoting options for test execution.
 
+#[cfg(not(bootstrap))]
+use std::borrow::Cow;
+
 /// Whether to execute tests concurrently or not
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Concurrent {
@@ -15,11 +18,15 @@ pub enum BenchMode {
 }
 
 /// Whether test is expected to panic or not
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(bootstrap, derive(Copy))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ShouldPanic {
     No,
     Yes,
+    #[cfg(bootstrap)]
     YesWithMessage(&'static str),
+    #[cfg(not(bootstrap))]
+    YesWithMessage(Cow<'static, str>),
 }
 
 /// Whether should console output be colored or not
@@ -41,6 +48,8 @@ pub enum OutputFormat {
     Json,
     /// JUnit output
     Junit,
+    /// Test Anything Protocol output
+    Tap,
 }
 
 /// Whether ignored test should be run or not
@@ -52,6 +61,34 @@ pub enum RunIgnored {
     Only,
 }
 
+/// Whether tests that failed in the previous run should be given priority
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RerunFailed {
+    No,
+    /// Run previously failed tests before all others
+    First,
+    /// Run only previously failed tests
+    Only,
+}
+
+/// Which share of the test suite to run when splitting it across several runners.
+/// Tests are assigned to partitions round-robin in alphabetical order, so every test
+/// belongs to exactly one of the `count` partitions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Partition {
+    /// One-based index of this partition.
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Partition {
+    /// Returns whether the test at `position` in the sorted test list belongs
+    /// to this partition.
+    pub fn contains(&self, position: usize) -> bool {
+        position % self.count == self.index - 1
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum RunStrategy {
     /// Runs the test in the current process, and sends the result back over the
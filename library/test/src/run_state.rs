@@ -0,0 +1,120 @@
+//! Outcomes of previous test runs, used to run the tests that failed last time
+//! first (`--failed-first`) or on their own (`--only-failed`).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::test_result::TestResult;
+
+/// Environment variable overriding the file the outcomes are stored in.
+pub const STATE_FILE_VAR: &str = "RUST_TEST_STATE_FILE";
+
+/// What happened to a test the last time it was run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    Failed,
+    TimedOut,
+    Ignored,
+    Bench,
+}
+
+impl Outcome {
+    pub fn from_result(result: &TestResult) -> Outcome {
+        match *result {
+            TestResult::TrOk | TestResult::TrFlaky => Outcome::Ok,
+            TestResult::TrFailed | TestResult::TrFailedMsg(_) | TestResult::TrTimedFail => {
+                Outcome::Failed
+            }
+            TestResult::TrTimedOut => Outcome::TimedOut,
+            TestResult::TrIgnored => Outcome::Ignored,
+            TestResult::TrBench(_) => Outcome::Bench,
+        }
+    }
+
+    pub fn is_failure(self) -> bool {
+        matches!(self, Outcome::Failed | Outcome::TimedOut)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Failed => "failed",
+            Outcome::TimedOut => "timeout",
+            Outcome::Ignored => "ignored",
+            Outcome::Bench => "bench",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Outcome> {
+        match s {
+            "ok" => Some(Outcome::Ok),
+            "failed" => Some(Outcome::Failed),
+            "timeout" => Some(Outcome::TimedOut),
+            "ignored" => Some(Outcome::Ignored),
+            "bench" => Some(Outcome::Bench),
+            _ => None,
+        }
+    }
+}
+
+/// The last known outcome of every test, keyed by test name.
+///
+/// It is stored as one `<outcome>\t<name>` line per test.
+#[derive(Clone, Debug, Default)]
+pub struct TestRunState {
+    outcomes: BTreeMap<String, Outcome>,
+}
+
+impl TestRunState {
+    /// Reads the state stored at `path`. The state only speeds up the edit-test
+    /// loop, so a missing or unreadable file is treated as empty and unrecognized
+    /// lines are skipped.
+    pub fn load(path: &Path) -> TestRunState {
+        let contents = fs::read_to_string(path).unwrap_or_default();
+        let outcomes = contents
+            .lines()
+            .filter_map(|line| {
+                let (outcome, name) = line.split_once('\t')?;
+                Some((name.to_string(), Outcome::from_str(outcome)?))
+            })
+            .collect();
+        TestRunState { outcomes }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = String::new();
+        for (name, outcome) in &self.outcomes {
+            contents.push_str(outcome.as_str());
+            contents.push('\t');
+            contents.push_str(name);
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn record(&mut self, name: &str, result: &TestResult) {
+        // Such names can't be stored on a single line; forgetting them only
+        // means they aren't prioritized in the next run.
+        if name.contains(&['\n', '\r'][..]) {
+            return;
+        }
+        self.outcomes.insert(name.to_string(), Outcome::from_result(result));
+    }
+
+    /// Forgets about the tests for which `keep` returns false, such as tests
+    /// that no longer exist.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.outcomes.retain(|name, _| keep(name));
+    }
+
+    pub fn outcome(&self, name: &str) -> Option<Outcome> {
+        self.outcomes.get(name).copied()
+    }
+
+    pub fn failed(&self, name: &str) -> bool {
+        self.outcome(name).map_or(false, Outcome::is_failure)
+    }
+}
@@ -300,3 +300,116 @@ pub fn winsorize(samples: &mut [f64], pct: f64) {
         }
     }
 }
+
+/// Welch's t-test: the two-sided p-value for the hypothesis that two populations have the same
+/// mean, given the sample mean, sample variance and number of samples of each. Unlike Student's
+/// t-test, it doesn't assume that both populations have the same variance.
+///
+/// See: <https://en.wikipedia.org/wiki/Welch%27s_t-test>
+pub fn welch_t_test(a: (f64, f64, usize), b: (f64, f64, usize)) -> f64 {
+    let ((a_mean, a_var, a_count), (b_mean, b_var, b_count)) = (a, b);
+    if a_count < 2 || b_count < 2 {
+        // There is no estimate of the variance to test against.
+        return 1.0;
+    }
+    let (a_n, b_n) = (a_count as f64, b_count as f64);
+    let (a_se2, b_se2) = (a_var / a_n, b_var / b_n);
+    let se2 = a_se2 + b_se2;
+    if se2 == 0.0 {
+        return if a_mean == b_mean { 1.0 } else { 0.0 };
+    }
+
+    let t = (a_mean - b_mean) / se2.sqrt();
+    // Welch–Satterthwaite approximation of the degrees of freedom.
+    let df = se2 * se2 / (a_se2 * a_se2 / (a_n - 1.0) + b_se2 * b_se2 / (b_n - 1.0));
+    student_t_two_sided_p(t, df)
+}
+
+// Probability that the absolute value of a variable following Student's t-distribution with `df`
+// degrees of freedom is at least `|t|`.
+fn student_t_two_sided_p(t: f64, df: f64) -> f64 {
+    incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+// Regularized incomplete beta function I_x(a, b), evaluated with the continued fraction from
+// "Numerical Recipes", which converges quickly for x < (a + 1) / (a + b + 2). The symmetry
+// I_x(a, b) = 1 - I_(1-x)(b, a) is used for larger x.
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    let front = ln_front.exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+// Evaluates the continued fraction for the incomplete beta function with Lentz's method.
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 300;
+    const EPSILON: f64 = 1.0e-15;
+    const TINY: f64 = 1.0e-300;
+    let not_tiny = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / not_tiny(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let even = m * (b - m) * x / ((a - 1.0 + m2) * (a + m2));
+        d = 1.0 / not_tiny(1.0 + even * d);
+        c = not_tiny(1.0 + even / c);
+        h *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + m2) * (a + 1.0 + m2));
+        d = 1.0 / not_tiny(1.0 + odd * d);
+        c = not_tiny(1.0 + odd / c);
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+// Natural logarithm of the gamma function for positive `x`, using the Lanczos approximation
+// (g = 7, n = 9), which is accurate to about 15 significant digits.
+//
+// See: <https://en.wikipedia.org/wiki/Lanczos_approximation>
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.99999999999980993,
+        676.5203681218851,
+        -1259.1392167224028,
+        771.32342877765313,
+        -176.61502916214059,
+        12.507343278686905,
+        -0.13857109526572012,
+        9.9843695780195716e-6,
+        1.5056327351493116e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
@@ -571,6 +571,43 @@ fn test_sum_f64_between_ints_that_sum_to_0() {
     assert_eq!([1e30f64, 1.2f64, -1e30f64].sum(), 1.2);
 }
 
+#[test]
+fn test_ln_gamma() {
+    assert_approx_eq!(ln_gamma(0.5), 0.5723649429247);
+    assert_approx_eq!(ln_gamma(1.0), 0.0);
+    assert_approx_eq!(ln_gamma(10.5), 13.9406252194038);
+    assert_approx_eq!(ln_gamma(100.0), 359.134205369575);
+}
+
+#[test]
+fn test_student_t_two_sided_p() {
+    assert_approx_eq!(student_t_two_sided_p(0.0, 10.0), 1.0);
+    // The t-distribution with one degree of freedom is the Cauchy distribution.
+    assert_approx_eq!(student_t_two_sided_p(1.0, 1.0), 0.5);
+    assert_approx_eq!(student_t_two_sided_p(2.0, 2.0), 1.0 - 2.0 / 6_f64.sqrt());
+    assert_approx_eq!(student_t_two_sided_p(-2.228, 10.0), 0.0500117718171114);
+}
+
+#[test]
+fn test_welch_t_test() {
+    let a = [19.8, 20.4, 19.6, 17.8, 18.5, 18.9, 18.3, 18.9, 19.5, 22.0];
+    let b = [
+        28.2, 26.6, 20.1, 23.3, 25.2, 22.1, 17.7, 27.6, 20.6, 13.7, 23.2, 17.5, 20.6, 18.0, 23.9,
+        21.6, 24.3, 20.4, 23.9, 13.3,
+    ];
+    let a = (a.mean(), a.var(), a.len());
+    let b = (b.mean(), b.var(), b.len());
+    assert_approx_eq!(welch_t_test(a, b), 0.0354845308300103);
+    assert_approx_eq!(welch_t_test(b, a), 0.0354845308300103);
+    assert_approx_eq!(welch_t_test(a, a), 1.0);
+
+    // Without variance, only identical means are plausibly the same.
+    assert_eq!(welch_t_test((5.0, 0.0, 10), (5.0, 0.0, 10)), 1.0);
+    assert_eq!(welch_t_test((5.0, 0.0, 10), (6.0, 0.0, 10)), 0.0);
+    // A single sample gives no estimate of the variance.
+    assert_eq!(welch_t_test((5.0, 0.0, 1), (6.0, 0.0, 10)), 1.0);
+}
+
 #[bench]
 pub fn sum_three_items(b: &mut Bencher) {
     b.iter(|| {
@@ -1,6 +1,8 @@
 use std::any::Any;
+use std::time::Duration;
 
 use super::bench::BenchSamples;
+use super::event::TestPanic;
 use super::options::ShouldPanic;
 use super::time;
 use super::types::TestDesc;
@@ -13,6 +15,104 @@
 pub const TR_OK: i32 = 50;
 pub const TR_FAILED: i32 = 51;
 
+/// What a secondary process reports about the test it ran, besides its exit
+/// code, so that the result matches that of running the test in-process.
+///
+/// It is stored as `key\tvalue` lines, with backslashes, tabs and newlines
+/// escaped in the values.
+#[derive(Debug, Default, PartialEq)]
+pub struct SecondaryReport {
+    pub exec_time: Option<time::TestExecTime>,
+    /// The message of a `TrFailedMsg` result.
+    pub failed_msg: Option<String>,
+    pub panic: Option<TestPanic>,
+}
+
+impl SecondaryReport {
+    pub fn encode(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ref exec_time) = self.exec_time {
+            lines.push(format!("exec_time\t{}", exec_time.0.as_nanos()));
+        }
+        if let Some(ref msg) = self.failed_msg {
+            lines.push(format!("failed_msg\t{}", escape(msg)));
+        }
+        if let Some(ref panic) = self.panic {
+            lines.push("panic\t".to_string());
+            if let Some(ref msg) = panic.message {
+                lines.push(format!("panic_message\t{}", escape(msg)));
+            }
+            if let Some((ref file, line, column)) = panic.location {
+                lines.push(format!("panic_location\t{}\t{line}\t{column}", escape(file)));
+            }
+        }
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    /// Parses a report, skipping any line it doesn't understand.
+    pub fn decode(s: &str) -> SecondaryReport {
+        let mut report = SecondaryReport::default();
+        for line in s.lines() {
+            let (key, value) = match line.split_once('\t') {
+                Some(key_value) => key_value,
+                None => continue,
+            };
+            match key {
+                "exec_time" => {
+                    report.exec_time = value
+                        .parse()
+                        .ok()
+                        .map(|nanos| time::TestExecTime(Duration::from_nanos(nanos)))
+                }
+                "failed_msg" => report.failed_msg = Some(unescape(value)),
+                "panic" => {
+                    report.panic.get_or_insert(TestPanic { message: None, location: None });
+                }
+                "panic_message" => {
+                    if let Some(ref mut panic) = report.panic {
+                        panic.message = Some(unescape(value));
+                    }
+                }
+                "panic_location" => {
+                    let mut parts = value.rsplitn(3, '\t');
+                    let column = parts.next().and_then(|c| c.parse().ok());
+                    let line = parts.next().and_then(|l| l.parse().ok());
+                    let file = parts.next().map(unescape);
+                    if let (Some(ref mut panic), Some(file), Some(line), Some(column)) =
+                        (&mut report.panic, file, line, column)
+                    {
+                        panic.location = Some((file, line, column));
+                    }
+                }
+                _ => {}
+            }
+        }
+        report
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TestResult {
     TrOk,
@@ -21,6 +121,9 @@ pub enum TestResult {
     TrIgnored,
     TrBench(BenchSamples),
     TrTimedFail,
+    TrTimedOut,
+    /// Passed after failing at least once, see `--retries`.
+    TrFlaky,
 }
 
 /// Creates a `TestResult` depending on the raw result of test execution
@@ -33,13 +136,13 @@ pub fn calc_result<'a>(
 ) -> TestResult {
     let result = match (&desc.should_panic, task_result) {
         (&ShouldPanic::No, Ok(())) | (&ShouldPanic::Yes, Err(_)) => TestResult::TrOk,
-        (&ShouldPanic::YesWithMessage(msg), Err(ref err)) => {
+        (&ShouldPanic::YesWithMessage(ref msg), Err(ref err)) => {
             let maybe_panic_str = err
                 .downcast_ref::<String>()
                 .map(|e| &**e)
                 .or_else(|| err.downcast_ref::<&'static str>().copied());
 
-            if maybe_panic_str.map(|e| e.contains(msg)).unwrap_or(false) {
+            if maybe_panic_str.map(|e| e.contains(&**msg)).unwrap_or(false) {
                 TestResult::TrOk
             } else if let Some(panic_str) = maybe_panic_str {
                 TestResult::TrFailedMsg(format!(
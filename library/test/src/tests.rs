@@ -3,7 +3,7 @@
 use crate::{
     bench::Bencher,
     console::OutputLocation,
-    formatters::PrettyFormatter,
+    formatters::{JsonFormatter, JunitFormatter, OutputFormatter, PrettyFormatter, TapFormatter},
     options::OutputFormat,
     test::{
         filter_tests,
@@ -12,6 +12,7 @@
         DynTestFn,
         DynTestName,
         MetricMap,
+        Partition,
         RunIgnored,
         RunStrategy,
         ShouldPanic,
@@ -27,6 +28,8 @@
     },
     time::{TestTimeOptions, TimeThreshold},
 };
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
@@ -37,6 +40,7 @@ fn new() -> TestOpts {
             filters: vec![],
             filter_exact: false,
             force_run_in_process: false,
+            isolate: false,
             exclude_should_panic: false,
             run_ignored: RunIgnored::No,
             run_tests: false,
@@ -49,7 +53,13 @@ fn new() -> TestOpts {
             shuffle_seed: None,
             test_threads: None,
             skip: vec![],
+            partition: None,
+            rerun_failed: RerunFailed::No,
+            state_file: None,
+            retries: 0,
             time_options: None,
+            baseline_options: None,
+            test_timeout: None,
             options: Options::new(),
         }
     }
@@ -66,6 +76,10 @@ fn one_ignored_one_unignored_test() -> Vec<TestDescAndFn> {
                 compile_fail: false,
                 no_run: false,
                 test_type: TestType::Unknown,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                #[cfg(not(bootstrap))]
+                location: TestLocation::default(),
             },
             testfn: DynTestFn(Box::new(move || {})),
         },
@@ -78,6 +92,10 @@ fn one_ignored_one_unignored_test() -> Vec<TestDescAndFn> {
                 compile_fail: false,
                 no_run: false,
                 test_type: TestType::Unknown,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                #[cfg(not(bootstrap))]
+                location: TestLocation::default(),
             },
             testfn: DynTestFn(Box::new(move || {})),
         },
@@ -98,6 +116,10 @@ fn f() {
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -119,6 +141,10 @@ fn f() {}
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -144,6 +170,10 @@ fn f() {
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -165,10 +195,14 @@ fn f() {
             name: StaticTestName("whatever"),
             ignore: false,
             ignore_message: None,
-            should_panic: ShouldPanic::YesWithMessage("error message"),
+            should_panic: ShouldPanic::YesWithMessage("error message".into()),
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -195,10 +229,14 @@ fn f() {
             name: StaticTestName("whatever"),
             ignore: false,
             ignore_message: None,
-            should_panic: ShouldPanic::YesWithMessage(expected),
+            should_panic: ShouldPanic::YesWithMessage(expected.into()),
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -229,10 +267,14 @@ fn f() {
             name: StaticTestName("whatever"),
             ignore: false,
             ignore_message: None,
-            should_panic: ShouldPanic::YesWithMessage(expected),
+            should_panic: ShouldPanic::YesWithMessage(expected.into()),
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -246,19 +288,24 @@ fn f() {
 #[test]
 #[cfg(not(target_os = "emscripten"))]
 fn test_should_panic_but_succeeds() {
-    let should_panic_variants = [ShouldPanic::Yes, ShouldPanic::YesWithMessage("error message")];
+    let should_panic_variants =
+        [ShouldPanic::Yes, ShouldPanic::YesWithMessage("error message".into())];
 
-    for &should_panic in should_panic_variants.iter() {
+    for should_panic in should_panic_variants.iter() {
         fn f() {}
         let desc = TestDescAndFn {
             desc: TestDesc {
                 name: StaticTestName("whatever"),
                 ignore: false,
                 ignore_message: None,
-                should_panic,
+                should_panic: should_panic.clone(),
                 compile_fail: false,
                 no_run: false,
                 test_type: TestType::Unknown,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                #[cfg(not(bootstrap))]
+                location: TestLocation::default(),
             },
             testfn: DynTestFn(Box::new(f)),
         };
@@ -293,6 +340,10 @@ fn f() {}
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -328,6 +379,10 @@ fn f() {}
             compile_fail: false,
             no_run: false,
             test_type,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(f)),
     };
@@ -367,6 +422,10 @@ fn typed_test_desc(test_type: TestType) -> TestDesc {
         compile_fail: false,
         no_run: false,
         test_type,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: TestLocation::default(),
     }
 }
 
@@ -429,6 +488,44 @@ fn parse_include_ignored_flag() {
     assert_eq!(opts.run_ignored, RunIgnored::Yes);
 }
 
+#[test]
+fn parse_test_timeout_flag() {
+    let args = vec![
+        "progname".to_string(),
+        "filter".to_string(),
+        "-Zunstable-options".to_string(),
+        "--test-timeout=5".to_string(),
+    ];
+    let opts = parse_opts(&args).unwrap().unwrap();
+    assert_eq!(opts.test_timeout, Some(Duration::from_secs(5)));
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn test_timeout_attribute_overrides_flag() {
+    let mut desc = typed_test_desc(TestType::UnitTest);
+    let default = Some(Duration::from_secs(60));
+    assert_eq!(time::get_test_timeout(&desc, None), None);
+    assert_eq!(time::get_test_timeout(&desc, default), default);
+
+    desc.timeout = Some(5);
+    assert_eq!(time::get_test_timeout(&desc, default), Some(Duration::from_secs(5)));
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn dynamic_test_timeout() {
+    let mut desc = typed_test_desc(TestType::Unknown);
+    desc.timeout = Some(1);
+    let desc = TestDescAndFn {
+        desc,
+        testfn: DynTestFn(Box::new(|| std::thread::sleep(Duration::from_secs(60)))),
+    };
+    let (tx, rx) = channel();
+    run_test(&TestOpts::new(), false, TestId(0), desc, RunStrategy::InProcess, tx, Concurrent::No);
+    assert_eq!(rx.recv().unwrap().result, TestResult::TrTimedOut);
+}
+
 #[test]
 pub fn filter_for_ignored_option() {
     // When we run ignored tests the test filter should filter out all the
@@ -479,6 +576,10 @@ pub fn exclude_should_panic_option() {
             compile_fail: false,
             no_run: false,
             test_type: TestType::Unknown,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
         },
         testfn: DynTestFn(Box::new(move || {})),
     });
@@ -503,6 +604,10 @@ fn tests() -> Vec<TestDescAndFn> {
                     compile_fail: false,
                     no_run: false,
                     test_type: TestType::Unknown,
+                    #[cfg(not(bootstrap))]
+                    timeout: None,
+                    #[cfg(not(bootstrap))]
+                    location: TestLocation::default(),
                 },
                 testfn: DynTestFn(Box::new(move || {})),
             })
@@ -592,6 +697,10 @@ fn testfn() {}
                 compile_fail: false,
                 no_run: false,
                 test_type: TestType::Unknown,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                #[cfg(not(bootstrap))]
+                location: TestLocation::default(),
             },
             testfn: DynTestFn(Box::new(testfn)),
         };
@@ -627,6 +736,230 @@ pub fn sort_tests() {
     }
 }
 
+#[test]
+pub fn partition_tests() {
+    let count = 3;
+    let mut seen = Vec::new();
+    for index in 1..=count {
+        let opts = TestOpts { partition: Some(Partition { index, count }), ..TestOpts::new() };
+        let partition = filter_tests(&opts, sample_tests());
+        assert!(partition.len() == 3 || partition.len() == 4);
+        seen.extend(partition.into_iter().map(|t| t.desc.name.to_string()));
+    }
+
+    // Every test runs in exactly one partition.
+    seen.sort();
+    let mut expected: Vec<_> =
+        sample_tests().into_iter().map(|t| t.desc.name.to_string()).collect();
+    expected.sort();
+    assert_eq!(seen, expected);
+}
+
+#[test]
+pub fn partition_applies_after_filters() {
+    let opts = TestOpts {
+        filters: vec!["test::".into()],
+        partition: Some(Partition { index: 2, count: 2 }),
+        ..TestOpts::new()
+    };
+    let names: Vec<_> =
+        filter_tests(&opts, sample_tests()).into_iter().map(|t| t.desc.name.to_string()).collect();
+    assert_eq!(
+        names,
+        [
+            "test::filter_for_ignored_option",
+            "test::ignored_tests_result_in_ignored",
+            "test::parse_include_ignored_flag",
+            "test::sort_tests",
+        ]
+    );
+}
+
+#[test]
+fn parse_partition_flag() {
+    let args = |partition: &str| {
+        vec!["progname".to_string(), "-Zunstable-options".to_string(), partition.to_string()]
+    };
+    let opts = parse_opts(&args("--partition=2/5")).unwrap().unwrap();
+    assert_eq!(opts.partition, Some(Partition { index: 2, count: 5 }));
+
+    for invalid in ["--partition=0/5", "--partition=6/5", "--partition=1", "--partition=a/b"] {
+        assert!(parse_opts(&args(invalid)).unwrap().is_err());
+    }
+}
+
+#[test]
+pub fn rerun_failed_tests() {
+    let path = env::temp_dir().join(format!("libtest-rerun-failed-{}", process::id()));
+    let mut state = run_state::TestRunState::default();
+    state.record("test::sort_tests", &TrFailed);
+    state.record("isize::test_pow", &TrOk);
+    state.record("sha1::test", &TrTimedOut);
+    state.save(&path).unwrap();
+
+    let names = |rerun_failed| {
+        let opts = TestOpts { rerun_failed, state_file: Some(path.clone()), ..TestOpts::new() };
+        filter_tests(&opts, sample_tests()).into_iter().map(|t| t.desc.name.to_string()).collect()
+    };
+    let failed_first: Vec<String> = names(RerunFailed::First);
+    assert_eq!(failed_first[..3], ["sha1::test", "test::sort_tests", "isize::test_pow"]);
+    assert_eq!(failed_first.len(), sample_tests().len());
+    let only_failed: Vec<String> = names(RerunFailed::Only);
+    assert_eq!(only_failed, ["sha1::test", "test::sort_tests"]);
+
+    // Failures of tests that this run filters out don't count.
+    let opts = TestOpts {
+        filters: vec!["isize".to_string()],
+        rerun_failed: RerunFailed::Only,
+        state_file: Some(path.clone()),
+        ..TestOpts::new()
+    };
+    let only_failed: Vec<String> =
+        filter_tests(&opts, sample_tests()).into_iter().map(|t| t.desc.name.to_string()).collect();
+    assert_eq!(only_failed, ["isize::test_pow", "isize::test_to_str"]);
+
+    // Without any recorded failures, every test is run.
+    state.record("test::sort_tests", &TrOk);
+    state.record("sha1::test", &TrOk);
+    state.save(&path).unwrap();
+    let only_failed: Vec<String> = names(RerunFailed::Only);
+    assert_eq!(only_failed.len(), sample_tests().len());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn parse_rerun_failed_flags() {
+    let args = |flags: &[&str]| {
+        let mut args = vec!["progname".to_string(), "-Zunstable-options".to_string()];
+        args.extend(flags.iter().map(|flag| flag.to_string()));
+        args
+    };
+    let opts = parse_opts(&args(&["--failed-first"])).unwrap().unwrap();
+    assert_eq!(opts.rerun_failed, RerunFailed::First);
+    assert!(opts.state_file.is_some());
+    let opts = parse_opts(&args(&["--only-failed"])).unwrap().unwrap();
+    assert_eq!(opts.rerun_failed, RerunFailed::Only);
+
+    assert!(parse_opts(&args(&["--failed-first", "--only-failed"])).unwrap().is_err());
+    let stable = vec!["progname".to_string(), "--failed-first".to_string()];
+    assert!(parse_opts(&stable).unwrap().is_err());
+}
+
+#[test]
+fn retry_failed_tests() {
+    static FLAKY_RUNS: AtomicUsize = AtomicUsize::new(0);
+    fn flaky() {
+        if FLAKY_RUNS.fetch_add(1, Ordering::SeqCst) < 2 {
+            panic!("not yet");
+        }
+    }
+    fn broken() {
+        panic!("never");
+    }
+    fn test(name: &'static str, testfn: fn()) -> TestDescAndFn {
+        TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName(name),
+                ignore: false,
+                ignore_message: None,
+                should_panic: ShouldPanic::No,
+                compile_fail: false,
+                no_run: false,
+                test_type: TestType::Unknown,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                #[cfg(not(bootstrap))]
+                location: TestLocation::default(),
+            },
+            testfn: StaticTestFn(testfn),
+        }
+    }
+
+    for test_threads in [1, 2] {
+        FLAKY_RUNS.store(0, Ordering::SeqCst);
+        let opts = TestOpts {
+            run_tests: true,
+            retries: 2,
+            test_threads: Some(test_threads),
+            ..TestOpts::new()
+        };
+        let mut results = Vec::new();
+        run_tests(&opts, vec![test("flaky", flaky), test("broken", broken)], |event| {
+            if let TestEvent::TeResult(completed_test) = event {
+                results.push(completed_test);
+            }
+            Ok(())
+        })
+        .unwrap();
+        results.sort_by_key(|t| t.desc.name.to_string());
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].desc.name.as_slice(), "broken");
+        assert_eq!(results[0].result, TrFailed);
+        assert_eq!(results[0].failed_attempts.len(), 2);
+        assert_eq!(results[1].desc.name.as_slice(), "flaky");
+        assert_eq!(results[1].result, TrFlaky);
+        assert_eq!(results[1].failed_attempts.len(), 2);
+        assert!(results[1].failed_attempts.iter().all(|a| a.result == TrFailed));
+    }
+}
+
+#[test]
+fn junit_escapes_flaky_failure_messages() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JunitFormatter::new(OutputLocation::Raw(Vec::new()));
+    let desc = typed_test_desc(TestType::Unknown);
+    let mut test = CompletedTest::new(TestId(0), desc, TrFlaky, None, Vec::new());
+    test.failed_attempts.push(FailedAttempt {
+        result: TrFailedMsg("expected <a> & \"b\"\nfound 'c'".to_string()),
+        output: Vec::new(),
+    });
+    out.write_result(&test, &st).unwrap();
+    out.write_run_finish(&st).unwrap();
+
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert!(s.contains(
+        "<flakyFailure message=\"expected &lt;a&gt; &amp; &quot;b&quot;&#10;found &apos;c&apos;\" \
+         type=\"assert\"/>"
+    ));
+}
+
+#[test]
+fn secondary_report_roundtrip() {
+    let report = SecondaryReport {
+        exec_time: Some(test_exec_time(12)),
+        failed_msg: Some("panic did not contain expected string\n\tsee \\ here".to_string()),
+        panic: Some(TestPanic {
+            message: Some("bar".to_string()),
+            location: Some(("src/with\ttab.rs".to_string(), 3, 5)),
+        }),
+    };
+    assert_eq!(report.encode().lines().count(), 5);
+    assert_eq!(SecondaryReport::decode(&report.encode()), report);
+
+    let report = SecondaryReport {
+        panic: Some(TestPanic { message: None, location: None }),
+        ..SecondaryReport::default()
+    };
+    assert_eq!(SecondaryReport::decode(&report.encode()), report);
+    assert_eq!(SecondaryReport::decode("garbage\nexec_time\tnope\n"), SecondaryReport::default());
+}
+
+#[test]
+fn parse_isolate_flag() {
+    let args = |flags: &[&str]| {
+        let mut args = vec!["progname".to_string(), "-Zunstable-options".to_string()];
+        args.extend(flags.iter().map(|flag| flag.to_string()));
+        args
+    };
+    assert!(parse_opts(&args(&["--isolate"])).unwrap().unwrap().isolate);
+    assert!(parse_opts(&args(&["--isolate", "--force-run-in-process"])).unwrap().is_err());
+}
+
 #[test]
 pub fn shuffle_tests() {
     let mut opts = TestOpts::new();
@@ -743,6 +1076,10 @@ fn f(_: &mut Bencher) {}
         compile_fail: false,
         no_run: false,
         test_type: TestType::Unknown,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: TestLocation::default(),
     };
 
     crate::bench::benchmark(TestId(0), desc, tx, true, f);
@@ -765,12 +1102,48 @@ fn f(b: &mut Bencher) {
         compile_fail: false,
         no_run: false,
         test_type: TestType::Unknown,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: TestLocation::default(),
     };
 
     crate::bench::benchmark(TestId(0), desc, tx, true, f);
     rx.recv().unwrap();
 }
 
+#[test]
+fn bench_baseline_comparison() {
+    use crate::bench::{BenchBaseline, BenchSamples};
+
+    let samples = |ns: f64| {
+        let samples: Vec<f64> = (0..50).map(|i| ns + (i % 10) as f64).collect();
+        BenchSamples { ns_iter_summ: stats::Summary::new(&samples), sample_count: 50, mb_s: 0 }
+    };
+
+    let path = env::temp_dir().join(format!("libtest-baseline-{}", process::id()));
+    let mut baseline = BenchBaseline::new("main");
+    baseline.record("bench::a", &samples(1000.0));
+    baseline.save(&path).unwrap();
+    let baseline = BenchBaseline::load("main", &path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let same = baseline.compare("bench::a", &samples(1000.0), 5.0).unwrap();
+    assert_eq!(same.change_pct, 0.0);
+    assert!(!same.regression && !same.improvement);
+
+    let slower = baseline.compare("bench::a", &samples(1200.0), 5.0).unwrap();
+    assert!(slower.change_pct > 19.0 && slower.p_value < 0.05);
+    assert!(slower.regression);
+    // Significant, but within the threshold.
+    assert!(!baseline.compare("bench::a", &samples(1200.0), 50.0).unwrap().regression);
+
+    let faster = baseline.compare("bench::a", &samples(800.0), 5.0).unwrap();
+    assert!(faster.improvement && !faster.regression);
+
+    assert_eq!(baseline.compare("bench::b", &samples(1000.0), 5.0), None);
+}
+
 #[test]
 fn should_sort_failures_before_printing_them() {
     let test_a = TestDesc {
@@ -781,6 +1154,10 @@ fn should_sort_failures_before_printing_them() {
         compile_fail: false,
         no_run: false,
         test_type: TestType::Unknown,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: TestLocation::default(),
     };
 
     let test_b = TestDesc {
@@ -791,6 +1168,10 @@ fn should_sort_failures_before_printing_them() {
         compile_fail: false,
         no_run: false,
         test_type: TestType::Unknown,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: TestLocation::default(),
     };
 
     let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
@@ -800,6 +1181,7 @@ fn should_sort_failures_before_printing_them() {
         total: 0,
         passed: 0,
         failed: 0,
+        flaky: 0,
         ignored: 0,
         filtered_out: 0,
         measured: 0,
@@ -809,6 +1191,11 @@ fn should_sort_failures_before_printing_them() {
         options: Options::new(),
         not_failures: Vec::new(),
         time_failures: Vec::new(),
+        flaky_tests: Vec::new(),
+        run_state: None,
+        baseline: None,
+        new_baseline: None,
+        bench_regressions: Vec::new(),
     };
 
     out.write_failures(&st).unwrap();
@@ -821,3 +1208,199 @@ fn should_sort_failures_before_printing_them() {
     let bpos = s.find("b").unwrap();
     assert!(apos < bpos);
 }
+
+#[test]
+fn tap_groups_doctests_by_file() {
+    fn desc(name: &'static str, test_type: TestType) -> TestDesc {
+        TestDesc {
+            name: StaticTestName(name),
+            ignore: false,
+            ignore_message: None,
+            should_panic: ShouldPanic::No,
+            compile_fail: false,
+            no_run: false,
+            test_type,
+            #[cfg(not(bootstrap))]
+            timeout: None,
+            #[cfg(not(bootstrap))]
+            location: TestLocation::default(),
+        }
+    }
+
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = TapFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_run_start(5, None).unwrap();
+    for (name, test_type, result) in [
+        ("src/lib.rs - foo (line 3)", TestType::DocTest, TrOk),
+        ("tests::a", TestType::UnitTest, TrFailedMsg("bad # thing".to_string())),
+        ("src/b.rs - bar (line 7)", TestType::DocTest, TrIgnored),
+        ("src/lib.rs - baz (line 1)", TestType::DocTest, TrFailed),
+        ("tests::b", TestType::UnitTest, TrOk),
+    ] {
+        let test = CompletedTest::new(TestId(0), desc(name, test_type), result, None, Vec::new());
+        out.write_result(&test, &st).unwrap();
+    }
+    out.write_run_finish(&st).unwrap();
+
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert_eq!(
+        s,
+        r#"TAP version 14
+not ok 1 - tests::a
+  ---
+  message: "bad # thing"
+  ...
+ok 2 - tests::b
+    # Subtest: src/b.rs
+    1..1
+    ok 1 - bar (line 7) # SKIP
+ok 3 - src/b.rs
+    # Subtest: src/lib.rs
+    1..2
+    not ok 1 - baz (line 1)
+    ok 2 - foo (line 3)
+not ok 4 - src/lib.rs
+1..4
+# 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+"#
+    );
+}
+
+#[test]
+fn tap_escapes_line_breaks_in_names() {
+    let mut desc = typed_test_desc(TestType::UnitTest);
+    desc.name = DynTestName("case\n#1".to_string());
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = TapFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_timeout(&desc).unwrap();
+    let test = CompletedTest::new(TestId(0), desc, TrOk, None, Vec::new());
+    out.write_result(&test, &st).unwrap();
+
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert_eq!(s, "# test case\\n#1 has been running for over 60 seconds\nok 1 - case\\n\\#1\n");
+}
+
+#[test]
+fn tap_reports_panic_message_and_location() {
+    let desc = one_ignored_one_unignored_test().remove(1).desc;
+    let mut test = CompletedTest::new(TestId(0), desc, TrFailed, None, Vec::new());
+    test.panic = Some(TestPanic {
+        message: Some("boom".to_string()),
+        location: Some(("src/lib.rs".to_string(), 3, 5)),
+    });
+
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = TapFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_result(&test, &st).unwrap();
+    test.result = TrFailedMsg("panic did not contain expected string".to_string());
+    out.write_result(&test, &st).unwrap();
+
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert_eq!(
+        s,
+        r#"not ok 1 - 2
+  ---
+  message: "boom"
+  at: "src/lib.rs:3:5"
+  ...
+not ok 2 - 2
+  ---
+  message: "panic did not contain expected string"
+  panic: "boom"
+  at: "src/lib.rs:3:5"
+  ...
+"#
+    );
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn test_source_location() {
+    let mut desc = one_ignored_one_unignored_test().remove(0).desc;
+    assert_eq!(desc.source_location(), None);
+
+    desc.location = TestLocation::new("src/lib.rs", 10, 4, 10, 8);
+    assert_eq!(desc.source_location().map(ToString::to_string).as_deref(), Some("src/lib.rs:10:4"));
+
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_test_start(&desc).unwrap();
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert_eq!(
+        s,
+        "{ \"type\": \"test\", \"event\": \"started\", \"name\": \"1\", \"source_path\": \"src/lib.rs\", \
+         \"start_line\": 10, \"start_col\": 4, \"end_line\": 10, \"end_col\": 8 }\n"
+    );
+}
+
+#[test]
+fn json_reports_output_and_panic_separately() {
+    let desc = one_ignored_one_unignored_test().remove(1).desc;
+    let mut test =
+        CompletedTest::new(TestId(0), desc, TrFailed, Some(test_exec_time(500)), b"out\n".to_vec());
+    test.stderr = Some(b"err\n".to_vec());
+    test.panic = Some(TestPanic {
+        message: Some("boom".to_string()),
+        location: Some(("src/lib.rs".to_string(), 3, 5)),
+    });
+
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_result(&test, &st).unwrap();
+    out.write_run_finish(&st).unwrap();
+    let s = match out.output_location() {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    };
+    assert_eq!(
+        s,
+        "{ \"type\": \"test\", \"name\": \"2\", \"event\": \"failed\", \"exec_time\": 0.5, \
+         \"stdout\": \"out\\n\", \"stderr\": \"err\\n\", \"panic\": { \"message\": \"boom\", \
+         \"file\": \"src/lib.rs\", \"line\": 3, \"column\": 5 } }\n\
+         { \"type\": \"suite\", \"event\": \"ok\", \"passed\": 0, \"failed\": 0, \"flaky\": 0, \"ignored\": 0, \
+         \"measured\": 0, \"filtered_out\": 0, \"exec_times\": { \"2\": 0.5 } }\n"
+    );
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn dynamic_tests() {
+    let tests = vec![
+        DynamicTest::new("data::a", || {}),
+        DynamicTest::new("data::b", || panic!("wrong data")).should_panic_with("wrong"),
+        DynamicTest::new("data::c", || panic!("other")).should_panic_with("wrong"),
+        DynamicTest::new("data::d", || {}).ignore_with_reason(format!("needs {}", "network")),
+        DynamicTest::new("other", || {}).location("tests/data/other.txt", 1),
+    ];
+    assert_eq!(tests[4].name(), "other");
+
+    let opts = TestOpts { run_tests: true, filters: vec!["data::".to_string()], ..TestOpts::new() };
+    let mut results = Vec::new();
+    run_tests(&opts, tests.into_iter().map(DynamicTest::into_test).collect(), |event| {
+        if let TestEvent::TeResult(completed_test) = event {
+            results.push((completed_test.desc, completed_test.result));
+        }
+        Ok(())
+    })
+    .unwrap();
+    results.sort_by_key(|(desc, _)| desc.name.to_string());
+
+    let names: Vec<_> = results.iter().map(|(desc, _)| desc.name.as_slice()).collect();
+    assert_eq!(names, ["data::a", "data::b", "data::c", "data::d"]);
+    assert_eq!(results[0].1, TrOk);
+    assert_eq!(results[1].1, TrOk);
+    assert!(matches!(results[2].1, TrFailedMsg(_)));
+    assert_eq!(results[3].1, TrIgnored);
+    assert_eq!(results[3].0.ignore_message.as_deref(), Some("needs network"));
+}
@@ -61,6 +61,25 @@ pub fn get_default_test_timeout() -> Instant {
     Instant::now() + Duration::from_secs(TEST_WARN_TIMEOUT_S)
 }
 
+/// Returns the duration after which the test is reported as timed out, if any.
+/// A `#[test_timeout]` attribute on the test takes precedence over the
+/// `--test-timeout` value passed as `default`.
+#[cfg_attr(bootstrap, allow(unused_variables))]
+pub fn get_test_timeout(desc: &TestDesc, default: Option<Duration>) -> Option<Duration> {
+    #[cfg(not(bootstrap))]
+    if let Some(secs) = desc.timeout {
+        return Some(Duration::from_secs(secs));
+    }
+    default
+}
+
+/// Returns whether `Instant` may be used to measure time. It is currently not
+/// supported for wasm targets, and is disabled for miri because it's not
+/// available when isolation is enabled.
+pub fn is_instant_supported() -> bool {
+    !cfg!(target_family = "wasm") && !cfg!(miri)
+}
+
 /// The measured execution time of a unit test.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TestExecTime(pub Duration);
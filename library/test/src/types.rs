@@ -117,11 +117,63 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 pub struct TestDesc {
     pub name: TestName,
     pub ignore: bool,
+    #[cfg(bootstrap)]
     pub ignore_message: Option<&'static str>,
+    #[cfg(not(bootstrap))]
+    pub ignore_message: Option<Cow<'static, str>>,
     pub should_panic: options::ShouldPanic,
     pub compile_fail: bool,
     pub no_run: bool,
     pub test_type: TestType,
+    /// Overrides `--test-timeout` for this test, in seconds.
+    #[cfg(not(bootstrap))]
+    pub timeout: Option<u64>,
+    #[cfg(not(bootstrap))]
+    pub location: TestLocation,
+}
+
+/// Where a test is defined, as reported to tools.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestLocation {
+    /// The file the test was defined in, or an empty string if unknown.
+    pub source_file: Cow<'static, str>,
+    /// One-based position of the test's name in `source_file`.
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl TestLocation {
+    pub const fn new(
+        source_file: &'static str,
+        start_line: usize,
+        start_col: usize,
+        end_line: usize,
+        end_col: usize,
+    ) -> TestLocation {
+        TestLocation {
+            source_file: Cow::Borrowed(source_file),
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+        }
+    }
+}
+
+/// Borrows a message given in a test attribute, such as `#[ignore = "reason"]`.
+/// The code `rustc` generates for `#[test]` functions uses it to fill in
+/// `TestDesc::ignore_message` and `ShouldPanic::YesWithMessage`.
+#[cfg(not(bootstrap))]
+pub const fn static_message(msg: &'static str) -> Cow<'static, str> {
+    Cow::Borrowed(msg)
+}
+
+impl fmt::Display for TestLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source_file, self.start_line, self.start_col)
+    }
 }
 
 impl TestDesc {
@@ -138,6 +190,15 @@ pub fn padded_name(&self, column_count: usize, align: NamePadding) -> String {
         }
     }
 
+    /// Returns where the test is defined, if known.
+    pub fn source_location(&self) -> Option<&TestLocation> {
+        #[cfg(not(bootstrap))]
+        if !self.location.source_file.is_empty() {
+            return Some(&self.location);
+        }
+        None
+    }
+
     /// Returns None for ignored test or that that are just run, otherwise give a description of the type of test.
     /// Descriptions include "should panic", "compile fail" and "compile".
     pub fn test_mode(&self) -> Option<&'static str> {
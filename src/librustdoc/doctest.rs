@@ -1031,6 +1031,17 @@ fn add_test(&mut self, test: String, config: LangString, line: usize) {
                 compile_fail: config.compile_fail,
                 no_run,
                 test_type: test::TestType::DocTest,
+                #[cfg(not(bootstrap))]
+                timeout: None,
+                // The code block's position within the file; its column isn't tracked.
+                #[cfg(not(bootstrap))]
+                location: test::TestLocation {
+                    source_file: filename.prefer_local().to_string().into(),
+                    start_line: line,
+                    start_col: 1,
+                    end_line: line,
+                    end_col: 1,
+                },
             },
             testfn: test::DynTestFn(box move || {
                 let report_unused_externs = |uext| {
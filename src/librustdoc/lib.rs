@@ -174,9 +174,13 @@ pub fn main() {
     init_logging();
     rustc_driver::init_env_logger("RUSTDOC_LOG");
 
-    let exit_code = rustc_driver::catch_with_exit_code(|| match get_args() {
-        Some(args) => main_args(&args),
-        _ => Err(ErrorGuaranteed::unchecked_claim_error_was_emitted()),
+    let exit_code = rustc_driver::catch_with_exit_code(|| {
+        // One SARIF log for all the handlers of rustdoc and of the session.
+        let _sarif_log = rustc_errors::sarif::defer_session_log();
+        match get_args() {
+            Some(args) => main_args(&args),
+            _ => Err(ErrorGuaranteed::unchecked_claim_error_was_emitted()),
+        }
     });
     process::exit(exit_code);
 }
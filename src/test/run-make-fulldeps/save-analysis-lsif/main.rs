@@ -0,0 +1,14 @@
+struct Greeting;
+
+macro_rules! greet {
+    () => {
+        krate2::hello()
+    };
+}
+
+fn main() {
+    let _greeting = Greeting;
+    krate2::hello();
+    greet!();
+    let _crab = "🦀"; let _after = _crab;
+}
@@ -0,0 +1,32 @@
+#!/usr/bin/env python
+
+import sys
+import json
+
+elements = [json.loads(line) for line in sys.stdin]
+by_id = {}
+for element in elements:
+    assert element["id"] not in by_id
+    by_id[element["id"]] = element
+    # Edges only refer to vertices that were written before them.
+    if element["type"] == "edge":
+        for v in [element.get("outV"), element.get("inV")] + element.get("inVs", []):
+            assert v is None or v < element["id"], element
+
+assert elements[0]["label"] == "metaData"
+monikers = [e for e in elements if e["label"] == "moniker"]
+assert any(m["kind"] == "export" and m["identifier"] == "main::Greeting" for m in monikers)
+assert any(m["kind"] == "import" and m["identifier"] == "krate2::hello" for m in monikers)
+
+packages = [e for e in elements if e["label"] == "packageInformation"]
+assert any(p["name"] == "krate2" for p in packages)
+
+# Both calls to `hello`, including the one expanded from `greet!`, are references.
+hello = next(m["id"] for m in monikers if m["identifier"] == "krate2::hello")
+result_set = next(e["outV"] for e in elements if e["label"] == "moniker" and e.get("inV") == hello)
+ranges = [by_id[e["outV"]] for e in elements if e["label"] == "next" and e["inV"] == result_set]
+assert sorted(r["start"]["line"] for r in ranges) == [10, 11], ranges
+
+# Columns are in UTF-16 code units, in which the crab takes two.
+ranges = [e for e in elements if e["label"] == "range" and e["start"]["line"] == 12]
+assert 26 in [r["start"]["character"] for r in ranges], ranges
@@ -0,0 +1,11 @@
+mod sub;
+
+fn main() {
+    let x = 1;
+    println!("{}", x);
+    // Both `while_true` and `unused_parens` suggest to change this line.
+    loop {
+        break;
+    }
+    sub::f();
+}
@@ -0,0 +1,11 @@
+mod sub;
+
+fn main() {
+    let mut x = 1;
+    println!("{}", x);
+    // Both `while_true` and `unused_parens` suggest to change this line.
+    while (true) {
+        break;
+    }
+    sub::f();
+}
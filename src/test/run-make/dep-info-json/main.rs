@@ -0,0 +1,6 @@
+const BYTES: &[u8] = include_bytes!("data.txt");
+const VALUE: Option<&str> = option_env!("DEP_INFO_JSON_VAR");
+
+fn main() {
+    let _ = (BYTES, VALUE);
+}
@@ -0,0 +1,37 @@
+#![crate_type = "lib"]
+#![feature(negative_impls)]
+
+pub trait Builder {}
+pub trait Part {}
+
+pub struct Gadget<T>(T);
+pub struct Banned;
+pub struct Pair<A, B>(A, B);
+
+impl<T: Part> Builder for Vec<T> {}
+impl Part for Gadget<u8> {}
+impl<T: Copy> Part for Gadget<Option<T>> {}
+impl !Part for Banned {}
+impl<A: Part, B: Part> Part for Pair<A, B> {}
+
+fn requires_builder<T: Builder>() {}
+
+pub fn unification() {
+    requires_builder::<Vec<Gadget<u16>>>();
+}
+
+pub fn where_clause() {
+    requires_builder::<Vec<Gadget<Option<String>>>>();
+}
+
+pub fn negative_impl() {
+    requires_builder::<Vec<Banned>>();
+}
+
+pub fn repeated_obligation() {
+    requires_builder::<Vec<Pair<Banned, Banned>>>();
+}
+
+pub fn return_position() -> dyn Part {
+    Gadget(0u8)
+}
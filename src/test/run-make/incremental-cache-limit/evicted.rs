@@ -0,0 +1,5 @@
+#![crate_type = "rlib"]
+
+pub fn evicted() -> u32 {
+    1
+}
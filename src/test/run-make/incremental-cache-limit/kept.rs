@@ -0,0 +1,5 @@
+#![crate_type = "rlib"]
+
+pub fn kept() -> u32 {
+    2
+}
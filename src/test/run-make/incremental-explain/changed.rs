@@ -0,0 +1,13 @@
+#![crate_type = "rlib"]
+
+pub mod unchanged {
+    pub fn one() -> u32 {
+        1
+    }
+}
+
+pub mod changed {
+    pub fn two() -> u32 {
+        3
+    }
+}
@@ -0,0 +1,38 @@
+macro_rules! doubled {
+    ($name:ident, $value:expr) => {
+        fn $name() -> u32 {
+            let value = $value;
+            value * 2
+        }
+    };
+}
+
+static TABLE: [u32; 3] = [5, 6, 7];
+const OFFSETS: &[u32] = &[1, 2, 3];
+
+fn one() -> u32 {
+    [1, 2, 3].iter().sum::<u32>() - 5
+}
+
+fn two() -> u32 {
+    let v: Vec<u32> = (0..4).map(|i| i * 2).collect();
+    v[1]
+}
+
+fn three() -> String {
+    format!("{}", 3)
+}
+
+doubled!(four, 2);
+
+fn five() -> u32 {
+    TABLE[0]
+}
+
+fn six() -> u32 {
+    OFFSETS.iter().sum()
+}
+
+fn main() {
+    println!("{} {} {} {} {} {}", one(), two(), three(), four(), five(), six());
+}
@@ -0,0 +1,38 @@
+macro_rules! doubled {
+    ($name:ident, $value:expr) => {
+        fn $name() -> u32 {
+            let value = $value + 0;
+            value * 2
+        }
+    };
+}
+
+static TABLE: [u32; 4] = [5, 6, 7, 8];
+const OFFSETS: &[u32] = &[3, 3];
+
+fn one() -> u32 {
+    [4, 5].iter().sum::<u32>() - 8
+}
+
+fn two() -> u32 {
+    let v: Vec<u32> = (1..5).map(|i| i * 2).collect();
+    v[0]
+}
+
+fn three() -> String {
+    format!("{}{}", 3, "")
+}
+
+doubled!(four, 1 + 1);
+
+fn five() -> u32 {
+    TABLE[3] - 3
+}
+
+fn six() -> u32 {
+    OFFSETS.iter().copied().sum()
+}
+
+fn main() {
+    println!("{} {} {} {} {} {}", one(), two(), three(), four(), five(), six());
+}
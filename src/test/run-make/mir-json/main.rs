@@ -0,0 +1,8 @@
+fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+fn main() {
+    let x = add(1, 2);
+    assert_eq!(x, 3);
+}
@@ -0,0 +1,6 @@
+extern crate upstream;
+
+fn main() {
+    upstream::generic::<u32>();
+    upstream::calls_generic::<u64>();
+}
@@ -0,0 +1,9 @@
+#![crate_type = "rlib"]
+
+pub fn generic<T: Default>() -> T {
+    T::default()
+}
+
+pub fn calls_generic<T: Default>() -> T {
+    generic::<T>()
+}
@@ -0,0 +1,9 @@
+#![crate_type = "rlib"]
+
+pub fn answer() -> u32 {
+    42
+}
+
+pub fn greet(name: &str) -> String {
+    format!("hello, {name}")
+}
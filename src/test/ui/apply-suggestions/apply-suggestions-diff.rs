@@ -0,0 +1,8 @@
+// check-pass
+// compile-flags: -Zapply-suggestions=diff
+
+fn main() {
+    let mut x = 1;
+    //~^ WARN variable does not need to be mutable
+    println!("{}", x);
+}
@@ -0,0 +1,10 @@
+// Using `_` as a typed hole requires `#![feature(typed_holes)]`.
+
+fn build(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+fn main() {
+    let _x = build(_, 3);
+    //~^ ERROR in expressions, `_` can only be used on the left-hand side of an assignment
+}
@@ -0,0 +1,36 @@
+// check-pass
+// compile-flags: -Zdiagnostic-baseline=enforce:{{src-base}}/lint/diagnostic-baseline/baseline.txt
+
+// Only the lints that aren't in the baseline are reported.
+
+fn old() {
+    let x = 1;
+    while true {
+        break;
+    }
+}
+
+fn new() {
+    let x = 1;
+    //~^ WARN unused variable: `x`
+    while true {
+        //~^ WARN denote infinite loops with `loop { ... }`
+        break;
+    }
+}
+
+struct S;
+
+impl S {
+    fn old() {
+        while true {
+            break;
+        }
+    }
+}
+
+fn main() {
+    old();
+    new();
+    S::old();
+}
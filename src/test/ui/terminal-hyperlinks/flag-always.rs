@@ -0,0 +1,10 @@
+// compile-flags: -Z terminal-hyperlinks=always
+// compile-flags: -Z terminal-hyperlinks-file-url=editor://{path}:{line}:{col}
+
+// This test checks that `-Z terminal-hyperlinks` wraps the file locations and error codes of the
+// human error output in OSC 8 hyperlinks.
+
+fn main() {
+    let _: () = 42;
+    //~^ ERROR mismatched types
+}
@@ -0,0 +1,26 @@
+// no-prefer-dynamic
+// compile-flags: -Cpanic=abort
+// run-flags: --test-threads=1
+// run-fail
+// check-run-results
+// exec-env:RUST_BACKTRACE=0
+// normalize-stdout-test "finished in \d+\.\d+s" -> "finished in $$TIME"
+
+// ignore-wasm no panic or subprocess support
+// ignore-emscripten no panic or subprocess support
+// ignore-sgx no subprocess support
+
+#![feature(test)]
+
+extern crate test;
+
+use test::DynamicTest;
+
+fn main() {
+    let tests = vec![
+        DynamicTest::new("dynamic::it_works", || assert_eq!(1 + 1, 2)),
+        DynamicTest::new("dynamic::it_panics", || panic!("expected")).should_panic_with("expected"),
+        DynamicTest::new("dynamic::it_fails", || panic!("wrong data")),
+    ];
+    test::test_main_dynamic_abort(tests);
+}
@@ -0,0 +1,43 @@
+// compile-flags: --test
+// run-flags: --test-threads=1 -Zunstable-options --isolate
+// run-fail
+// check-run-results
+// exec-env:RUST_BACKTRACE=0
+// normalize-stdout-test "finished in \d+\.\d+s" -> "finished in $$TIME"
+
+// ignore-wasm no subprocess support
+// ignore-emscripten no subprocess support
+// ignore-sgx no subprocess support
+
+#![cfg(test)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static POISONED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn a_poisons_global_state() {
+    POISONED.store(true, Ordering::SeqCst);
+}
+
+#[test]
+fn b_sees_fresh_global_state() {
+    assert!(!POISONED.load(Ordering::SeqCst));
+}
+
+#[test]
+fn c_catches_panics() {
+    assert!(std::panic::catch_unwind(|| panic!("caught")).is_err());
+    assert!(std::thread::spawn(|| panic!("joined")).join().is_err());
+}
+
+#[test]
+fn it_exits() {
+    std::process::exit(123);
+}
+
+#[test]
+#[should_panic(expected = "foo")]
+fn it_panics_with_the_wrong_message() {
+    panic!("bar");
+}
@@ -0,0 +1,42 @@
+#![feature(typed_holes)]
+#![allow(unused)]
+
+struct Foo(u32);
+
+impl Foo {
+    fn new() -> Foo {
+        Foo(0)
+    }
+}
+
+fn make_foo() -> Foo {
+    Foo(1)
+}
+
+fn build(foo: Foo, n: u32) -> Foo {
+    Foo(foo.0 + n)
+}
+
+fn main() {
+    let a = Foo(2);
+    let n: u32 = 3;
+    let x: Foo = build(_, 3);
+    //~^ ERROR found a typed hole
+    let y = _;
+    //~^ ERROR found a typed hole
+    let z = build(x, _);
+    //~^ ERROR found a typed hole
+}
+
+// Only the functions in scope are listed.
+mod other {
+    pub fn imported_foo() -> super::Foo {
+        super::Foo(4)
+    }
+
+    pub fn other_foo() -> super::Foo {
+        super::Foo(5)
+    }
+}
+
+use other::imported_foo;
@@ -480,6 +480,7 @@ fn is_linted_explicit_deref_position(parent: Option<Node<'_>>, child_id: HirId,
         | ExprKind::Match(_, _, MatchSource::TryDesugar | MatchSource::AwaitDesugar)
         | ExprKind::Field(..)
         | ExprKind::Index(..)
+        | ExprKind::Hole
         | ExprKind::Err => false,
 
         ExprKind::Box(..)
@@ -186,6 +186,7 @@ fn never_loop_expr(expr: &Expr<'_>, main_loop_id: HirId) -> NeverLoopResult {
         | ExprKind::Path(_)
         | ExprKind::ConstBlock(_)
         | ExprKind::Lit(_)
+        | ExprKind::Hole
         | ExprKind::Err => NeverLoopResult::Otherwise,
     }
 }
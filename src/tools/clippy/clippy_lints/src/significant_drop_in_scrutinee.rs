@@ -384,6 +384,7 @@ fn visit_expr(&mut self, ex: &'tcx Expr<'_>) {
                 ExprKind::Continue(_) |
                 ExprKind::DropTemps(_) |
                 ExprKind::Err |
+                ExprKind::Hole |
                 ExprKind::InlineAsm(_) |
                 ExprKind::Let(_) |
                 ExprKind::Lit(_) |
@@ -572,6 +572,7 @@ macro_rules! kind {
                     },
                 }
             },
+            ExprKind::Hole => kind!("Hole"),
             ExprKind::Err => kind!("Err"),
             ExprKind::DropTemps(expr) => {
                 bind!(self, expr);
@@ -176,6 +176,7 @@ fn visit_expr(&mut self, e: &'tcx Expr<'_>) {
                 | ExprKind::Ret(_)
                 | ExprKind::InlineAsm(_)
                 | ExprKind::Yield(..)
+                | ExprKind::Hole
                 | ExprKind::Err => {
                     self.eagerness = ForceNoChange;
                     return;
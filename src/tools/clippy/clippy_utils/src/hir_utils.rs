@@ -695,7 +695,7 @@ pub fn hash_expr(&mut self, e: &Expr<'_>) {
                 }
                 self.hash_pat(pat);
             },
-            ExprKind::Err => {},
+            ExprKind::Hole | ExprKind::Err => {},
             ExprKind::Lit(ref l) => {
                 l.node.hash(&mut self.s);
             },
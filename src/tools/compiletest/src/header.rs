@@ -895,6 +895,10 @@ pub fn make_test_description<R: Read>(
         compile_fail: false,
         no_run: false,
         test_type: test::TestType::Unknown,
+        #[cfg(not(bootstrap))]
+        timeout: None,
+        #[cfg(not(bootstrap))]
+        location: test::TestLocation::default(),
     }
 }
 
@@ -510,10 +510,17 @@ pub fn test_opts(config: &Config) -> test::TestOpts {
         shuffle_seed: None,
         test_threads: None,
         skip: config.skip.clone(),
+        partition: None,
+        rerun_failed: test::RerunFailed::No,
+        state_file: None,
+        retries: 0,
         list: false,
         options: test::Options::new(),
         time_options: None,
+        baseline_options: None,
+        test_timeout: None,
         force_run_in_process: false,
+        isolate: false,
     }
 }
 